@@ -1,15 +1,24 @@
 use byocvpn_aws::{AwsCredentials, AwsProvider};
+use byocvpn_azure::{AzureProvider, credentials::AzureCredentials};
 use byocvpn_core::{
-    cloud_provider::{CloudProvider, CloudProviderName},
-    commands,
+    cloud_provider::{
+        CloudProvider, CloudProviderName, ServerLifetime, SpawnOptions, StatusEndpointAccess,
+    },
+    commands::{self, connect::ConnectOptions},
     connectivity::wait_until_ready,
     credentials::CredentialStore,
-    crypto::generate_keypair,
+    crypto::{derive_status_key_from_base64, generate_keypair, generate_preshared_key},
+    daemon_client::SplitTunnelConfig,
     error::Result,
+    proxy::{self, ProxyOptions},
 };
-use byocvpn_daemon::daemon_client::UnixDaemonClient;
+use byocvpn_daemon::{constants, daemon_client::UnixDaemonClient};
+use byocvpn_gcp::{GcpProvider, credentials::GcpCredentials};
+use byocvpn_oracle::{OracleProvider, credentials::OracleCredentials};
 use clap::{Parser, Subcommand};
+use ipnet::IpNet;
 use log::*;
+use std::{net::SocketAddr, time::Duration};
 #[derive(Parser)]
 #[command(name = "byocvpn")]
 #[command(about = "BYOC VPN CLI", long_about = None)]
@@ -21,47 +30,214 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     Spawn {
-        #[arg(short, long, help = "AWS region")]
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(short, long, help = "Cloud region")]
         region: String,
+
+        #[arg(
+            long,
+            default_value = "open",
+            help = "Status endpoint access after the client connects: open, client-only or shutdown"
+        )]
+        status_access: StatusEndpointAccess,
+
+        #[arg(long, help = "Minutes without a handshake after which the server deletes itself")]
+        idle_timeout: Option<u32>,
+
+        #[arg(long, help = "Minutes after which the server deletes itself regardless")]
+        max_lifetime: Option<u32>,
+
+        #[arg(long, help = "Attach a static IP that is kept after termination and reused")]
+        reserve_ip: bool,
     },
     Terminate {
-        #[arg(help = "The EC2 instance ID to terminate")]
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(help = "The instance ID to terminate")]
         instance_id: String,
 
-        #[arg(short, long, help = "AWS region")]
+        #[arg(short, long, help = "Cloud region")]
+        region: String,
+    },
+    Stop {
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(help = "The instance ID to stop")]
+        instance_id: String,
+
+        #[arg(short, long, help = "Cloud region")]
+        region: String,
+    },
+    Start {
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(help = "The instance ID to start")]
+        instance_id: String,
+
+        #[arg(short, long, help = "Cloud region")]
         region: String,
     },
     List {
-        #[arg(short, long, help = "AWS region")]
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(short, long, help = "Cloud region")]
+        region: Option<String>,
+    },
+    ReservedIps {
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(short, long, help = "Cloud region")]
         region: Option<String>,
     },
+    ReleaseIp {
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(help = "The reserved IP ID to release")]
+        address_id: String,
+
+        #[arg(short, long, help = "Cloud region")]
+        region: String,
+    },
     Connect {
-        #[arg(help = "The EC2 instance ID to connect to")]
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(help = "The instance ID to connect to")]
         instance_id: String,
 
-        #[arg(short, long, help = "AWS region")]
+        #[arg(short, long, help = "Cloud region")]
         region: String,
+
+        #[arg(long = "include", help = "Only route this CIDR through the VPN (repeatable)")]
+        include_routes: Vec<IpNet>,
+
+        #[arg(long = "exclude", help = "Never route this CIDR through the VPN (repeatable)")]
+        exclude_routes: Vec<IpNet>,
+
+        #[arg(
+            long = "include-domain",
+            help = "Only route this hostname through the VPN (repeatable)"
+        )]
+        include_domains: Vec<String>,
+
+        #[arg(
+            long,
+            num_args = 0..=1,
+            default_missing_value = constants::DEFAULT_NETWORK_NAMESPACE,
+            help = "Keep the tunnel in this Linux network namespace; use `exec` to run programs through it"
+        )]
+        namespace: Option<String>,
+
+        #[arg(long, help = "Let the kill switch pass LAN traffic (printers, NAS)")]
+        allow_lan: bool,
+
+        #[arg(long, help = "Tunnel MTU (default 1280; the ceiling for --probe-mtu)")]
+        mtu: Option<u16>,
+
+        #[arg(long, help = "Probe the path for the largest working MTU before connecting")]
+        probe_mtu: bool,
     },
     Disconnect,
-    Setup,
+    AllowLan {
+        #[arg(action = clap::ArgAction::Set, help = "Allow (true) or block (false) LAN traffic")]
+        enabled: bool,
+    },
+    Lockdown {
+        #[arg(
+            action = clap::ArgAction::Set,
+            help = "Keep traffic blocked whenever the VPN is down, across restarts (true/false)"
+        )]
+        enabled: bool,
+    },
+    CaptivePortal {
+        #[arg(short, long, default_value_t = 5, help = "Minutes before the kill switch re-locks")]
+        minutes: u64,
+
+        #[arg(long, help = "Re-lock immediately")]
+        close: bool,
+    },
+    History {
+        #[arg(short, long, default_value_t = 7, help = "How many days back to report")]
+        days: u64,
+
+        #[arg(short, long, help = "Only report sessions in this region")]
+        region: Option<String>,
+    },
+    Proxy {
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(help = "The instance ID to tunnel through")]
+        instance_id: String,
+
+        #[arg(short, long, help = "Cloud region")]
+        region: String,
+
+        #[arg(
+            long,
+            help = "SOCKS5 listen address (default 127.0.0.1:1080 unless only --http is given)"
+        )]
+        socks: Option<SocketAddr>,
+
+        #[arg(
+            long,
+            help = "HTTP CONNECT listen address (default 127.0.0.1:8080 unless only --socks is given)"
+        )]
+        http: Option<SocketAddr>,
+
+        #[arg(long, help = "Tunnel MTU (default 1280)")]
+        mtu: Option<u16>,
+    },
+    Exec {
+        #[arg(long, default_value = constants::DEFAULT_NETWORK_NAMESPACE, help = "Network namespace to run in")]
+        namespace: String,
+
+        #[arg(
+            trailing_var_arg = true,
+            required = true,
+            help = "Program and arguments to run through the VPN, as the user who ran sudo"
+        )]
+        command: Vec<String>,
+    },
+    Setup {
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+    },
     EnableRegion {
-        #[arg(short, long, help = "AWS region")]
+        #[arg(short, long, default_value = "aws", help = "Cloud provider")]
+        provider: CloudProviderName,
+
+        #[arg(short, long, help = "Cloud region")]
         region: String,
     },
 }
 
 async fn create_cloud_provider(provider_name: CloudProviderName) -> Result<Box<dyn CloudProvider>> {
-    match provider_name {
+    debug!("Creating {} cloud provider", provider_name);
+    let store = CredentialStore::load().await?;
+    let provider: Box<dyn CloudProvider> = match provider_name {
         CloudProviderName::Aws => {
-            let store = CredentialStore::load().await?;
-            Ok(Box::new(
-                AwsProvider::new(AwsCredentials::from_store(&store)?.into()).await,
-            ))
+            Box::new(AwsProvider::new(AwsCredentials::from_store(&store)?.into()).await)
         }
-        unsupported => {
-            unimplemented!("CLI does not support provider: {}", unsupported)
+        CloudProviderName::Oracle => Box::new(OracleProvider::new(
+            OracleCredentials::from_store(&store)?.into(),
+        )),
+        CloudProviderName::Gcp => {
+            Box::new(GcpProvider::new(GcpCredentials::from_store(&store)?.into())?)
         }
-    }
+        CloudProviderName::Azure => Box::new(AzureProvider::new(
+            AzureCredentials::from_store(&store)?.into(),
+        )?),
+    };
+    Ok(provider)
 }
 
 #[tokio::main]
@@ -70,24 +246,43 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Spawn { region } => {
-            let provider = create_cloud_provider(CloudProviderName::Aws).await?;
+        Commands::Spawn {
+            provider,
+            region,
+            status_access,
+            idle_timeout,
+            max_lifetime,
+            reserve_ip,
+        } => {
+            let provider = create_cloud_provider(provider).await?;
             commands::setup::setup(&*provider).await?;
             commands::setup::enable_region(&*provider, &region).await?;
 
             let (client_private_key, client_public_key) = generate_keypair();
-            let (server_private_key, server_public_key) = generate_keypair();
+            let preshared_key = generate_preshared_key();
 
             let instance = commands::spawn::launch_instance(
                 &*provider,
                 region.as_str(),
                 "",
-                &server_private_key,
                 &client_public_key,
+                &preshared_key,
+                SpawnOptions {
+                    status_access,
+                    lifetime: ServerLifetime {
+                        idle_timeout_minutes: idle_timeout,
+                        max_lifetime_minutes: max_lifetime,
+                    },
+                    reserve_ip,
+                },
             )
             .await?;
 
-            wait_until_ready(&instance.public_ip_v4).await?;
+            let status_key = derive_status_key_from_base64(&preshared_key)?;
+            let server_public_key = wait_until_ready(&instance.public_ip_v4, &status_key, |stage| {
+                info!("Server install stage: {}", stage)
+            })
+            .await?;
 
             let provider_name = provider.get_provider_name();
             commands::spawn::write_wireguard_config(
@@ -96,6 +291,7 @@ async fn main() -> Result<()> {
                 &instance,
                 &client_private_key,
                 &server_public_key,
+                &preshared_key,
             )
             .await?;
 
@@ -105,11 +301,19 @@ async fn main() -> Result<()> {
             );
         }
         Commands::Connect {
+            provider,
             region,
             instance_id,
+            include_routes,
+            exclude_routes,
+            include_domains,
+            namespace,
+            allow_lan,
+            mtu,
+            probe_mtu,
         } => {
             info!("Connecting to VPN...");
-            let provider = create_cloud_provider(CloudProviderName::Aws).await?;
+            let provider = create_cloud_provider(provider).await?;
             let daemon_client = UnixDaemonClient;
 
             commands::connect::connect(
@@ -119,10 +323,31 @@ async fn main() -> Result<()> {
                 instance_id.as_str(),
                 None,
                 None,
-                true,
+                ConnectOptions {
+                    kill_switch_enabled: true,
+                    split_tunnel: SplitTunnelConfig {
+                        include_routes,
+                        exclude_routes,
+                        include_domains,
+                    },
+                    network_namespace: namespace,
+                    allow_lan,
+                    mtu,
+                    probe_mtu,
+                },
             )
             .await?;
             info!("Connected to VPN");
+            if probe_mtu {
+                let status = commands::status::fetch_vpn_status(&daemon_client).await?;
+                if let Some(error) = status.mtu_probe_error {
+                    warn!(
+                        "MTU probe failed ({}); using MTU {}",
+                        error,
+                        status.mtu.unwrap_or_default()
+                    );
+                }
+            }
         }
         Commands::Disconnect => {
             info!("Disconnecting from VPN...");
@@ -130,18 +355,149 @@ async fn main() -> Result<()> {
             commands::disconnect::disconnect(&daemon_client).await?;
             info!("Disconnected from VPN");
         }
+        Commands::AllowLan { enabled } => {
+            let daemon_client = UnixDaemonClient;
+            commands::kill_switch::set_allow_lan(&daemon_client, enabled).await?;
+            info!("LAN access {}", if enabled { "allowed" } else { "blocked" });
+        }
+        Commands::Lockdown { enabled } => {
+            let daemon_client = UnixDaemonClient;
+            commands::kill_switch::set_lockdown(&daemon_client, enabled).await?;
+            info!("Lockdown {}", if enabled { "enabled" } else { "disabled" });
+        }
+        Commands::CaptivePortal { minutes, close } => {
+            let daemon_client = UnixDaemonClient;
+            if close {
+                commands::kill_switch::close_captive_portal(&daemon_client).await?;
+                info!("Captive portal window closed");
+            } else {
+                commands::kill_switch::open_captive_portal(
+                    &daemon_client,
+                    Duration::from_secs(minutes * 60),
+                )
+                .await?;
+                info!("Captive portal window open for {} minutes", minutes);
+            }
+        }
+        Commands::History { days, region } => {
+            let since = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or_default()
+                .saturating_sub(days * 24 * 60 * 60);
+            let daemon_client = UnixDaemonClient;
+            let sessions =
+                commands::history::fetch_traffic_history(&daemon_client, Some(since)).await?;
+
+            let (mut total_sent, mut total_received) = (0, 0);
+            for session in sessions
+                .iter()
+                .filter(|session| region.as_ref().is_none_or(|region| &session.region == region))
+            {
+                total_sent += session.bytes_sent;
+                total_received += session.bytes_received;
+                info!(
+                    "{} {} {}: connected at {}, {} (sent {} bytes, received {} bytes)",
+                    session.provider,
+                    session.region,
+                    session.instance_id,
+                    session.connected_at,
+                    match session.disconnected_at {
+                        Some(disconnected_at) => format!("disconnected at {}", disconnected_at),
+                        None => "still connected".to_string(),
+                    },
+                    session.bytes_sent,
+                    session.bytes_received
+                );
+            }
+            info!(
+                "Last {} days: sent {} bytes, received {} bytes",
+                days, total_sent, total_received
+            );
+        }
+        Commands::Proxy {
+            provider,
+            instance_id,
+            region,
+            socks,
+            http,
+            mtu,
+        } => {
+            let options = if socks.is_none() && http.is_none() {
+                ProxyOptions {
+                    socks_listen: proxy::DEFAULT_SOCKS_LISTEN.parse().ok(),
+                    http_listen: proxy::DEFAULT_HTTP_LISTEN.parse().ok(),
+                    mtu,
+                }
+            } else {
+                ProxyOptions {
+                    socks_listen: socks,
+                    http_listen: http,
+                    mtu,
+                }
+            };
+
+            let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(());
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    let _ = shutdown_tx.send(());
+                }
+            });
+
+            info!("Starting proxy through {}...", instance_id);
+            commands::proxy::run_proxy(&provider, &region, &instance_id, options, shutdown_rx)
+                .await?;
+        }
+        Commands::Exec { namespace, command } => {
+            #[cfg(target_os = "linux")]
+            {
+                let status = byocvpn_daemon::netns::exec_in_namespace(&namespace, &command)?;
+                std::process::exit(status.code().unwrap_or(1));
+            }
+            #[cfg(not(target_os = "linux"))]
+            {
+                let _ = (namespace, command);
+                error!("`exec` requires Linux network namespaces");
+                std::process::exit(1);
+            }
+        }
         Commands::Terminate {
+            provider,
             region,
             instance_id,
         } => {
             info!("Terminating instance: {}", instance_id);
-            let provider = create_cloud_provider(CloudProviderName::Aws).await?;
+            let provider = create_cloud_provider(provider).await?;
             commands::terminate::terminate_instance(&*provider, &region, &instance_id).await?;
             info!("Instance terminated: {}", instance_id);
         }
-        Commands::List { region } => {
+        Commands::Stop {
+            provider,
+            region,
+            instance_id,
+        } => {
+            info!("Stopping instance: {}", instance_id);
+            let provider = create_cloud_provider(provider).await?;
+            commands::stop::stop_instance(&*provider, &region, &instance_id).await?;
+            info!("Instance stopping: {}", instance_id);
+        }
+        Commands::Start {
+            provider,
+            region,
+            instance_id,
+        } => {
+            info!("Starting instance: {}", instance_id);
+            let provider = create_cloud_provider(provider).await?;
+            let instance =
+                commands::start::start_instance(&*provider, &region, &instance_id).await?;
+            info!(
+                "Instance started: {} ({})",
+                instance_id, instance.public_ip_v4
+            );
+        }
+        Commands::List { provider, region } => {
             info!("Listing instances...");
-            let provider = create_cloud_provider(CloudProviderName::Aws).await?;
+            let provider = create_cloud_provider(provider).await?;
             let active_instances =
                 commands::list::list_instances(&*provider, region.as_deref()).await?;
             info!(
@@ -156,15 +512,45 @@ async fn main() -> Result<()> {
                 info!("{:?}", instance);
             }
         }
-        Commands::Setup => {
+        Commands::ReservedIps { provider, region } => {
+            let provider = create_cloud_provider(provider).await?;
+            let reserved_ips =
+                commands::reserved_ip::list_reserved_ips(&*provider, region.as_deref()).await?;
+            if reserved_ips.is_empty() {
+                info!("No reserved IPs");
+            }
+            for reserved_ip in reserved_ips {
+                info!(
+                    "{} {} ({}): {}",
+                    reserved_ip.region,
+                    reserved_ip.public_ip,
+                    reserved_ip.id,
+                    match &reserved_ip.instance_id {
+                        Some(instance_id) => format!("attached to {}", instance_id),
+                        None => format!("idle at ${}/hour", reserved_ip.ip_hourly_rate),
+                    }
+                );
+            }
+        }
+        Commands::ReleaseIp {
+            provider,
+            region,
+            address_id,
+        } => {
+            info!("Releasing reserved IP: {}", address_id);
+            let provider = create_cloud_provider(provider).await?;
+            commands::reserved_ip::release_reserved_ip(&*provider, &region, &address_id).await?;
+            info!("Reserved IP released: {}", address_id);
+        }
+        Commands::Setup { provider } => {
             info!("Setting up cloud provider...");
-            let provider = create_cloud_provider(CloudProviderName::Aws).await?;
+            let provider = create_cloud_provider(provider).await?;
             commands::setup::setup(&*provider).await?;
             info!("Cloud provider setup complete.");
         }
-        Commands::EnableRegion { region } => {
+        Commands::EnableRegion { provider, region } => {
             info!("Enabling region: {}", region);
-            let provider = create_cloud_provider(CloudProviderName::Aws).await?;
+            let provider = create_cloud_provider(provider).await?;
 
             commands::setup::enable_region(&*provider, &region).await?;
             info!("Region enabled: {}", region);
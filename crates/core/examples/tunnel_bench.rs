@@ -0,0 +1,69 @@
+//! One end of a throughput benchmark for `Tunnel`. Run by
+//! `scripts/bench-tunnel.sh`, once in each of two network namespaces joined
+//! by a veth pair:
+//!
+//!     tunnel_bench a   # 10.200.0.1 on the veth, 10.201.0.1 on the TUN
+//!     tunnel_bench b   # 10.200.0.2 on the veth, 10.201.0.2 on the TUN
+
+use std::{net::Ipv4Addr, time::Duration};
+
+use boringtun::{
+    noise::Tunn,
+    x25519::{PublicKey, StaticSecret},
+};
+use byocvpn_core::tunnel::Tunnel;
+use tokio::{net::UdpSocket, sync::watch};
+use tun_rs::DeviceBuilder;
+
+const UDP_PORT: u16 = 51820;
+const MTU: u16 = 1420;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let side = std::env::args().nth(1).unwrap_or_default();
+    let (local, peer) = match side.as_str() {
+        "a" => (1u8, 2u8),
+        "b" => (2u8, 1u8),
+        _ => return Err("usage: tunnel_bench <a|b>".into()),
+    };
+
+    // Fixed keys: this only ever talks to the other half of the benchmark.
+    let private_key = StaticSecret::from([local; 32]);
+    let peer_public_key = PublicKey::from(&StaticSecret::from([peer; 32]));
+    let wg = Tunn::new(private_key, peer_public_key, None, Some(25), 0, None)?;
+
+    let tun = DeviceBuilder::new()
+        .ipv4(Ipv4Addr::new(10, 201, 0, local), 24, None)
+        .mtu(MTU)
+        .build_async()?;
+
+    let udp = UdpSocket::bind((Ipv4Addr::new(10, 200, 0, local), UDP_PORT)).await?;
+    udp.connect((Ipv4Addr::new(10, 200, 0, peer), UDP_PORT))
+        .await?;
+
+    let (_shutdown_tx, shutdown_rx) = watch::channel(());
+    let mut tunnel = Tunnel::new(tun, udp, wg, shutdown_rx);
+    let metrics = tunnel.metrics.clone();
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let mut last = (0, 0);
+        loop {
+            interval.tick().await;
+            let metrics = metrics.read().await.clone();
+            eprintln!(
+                "[{}] tx {:>8.1} Mbit/s  rx {:>8.1} Mbit/s",
+                side,
+                (metrics.bytes_sent - last.0) as f64 * 8.0 / 1e6,
+                (metrics.bytes_received - last.1) as f64 * 8.0 / 1e6,
+            );
+            last = (metrics.bytes_sent, metrics.bytes_received);
+        }
+    });
+
+    tokio::select! {
+        result = tunnel.run() => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    Ok(())
+}
@@ -8,16 +8,82 @@ use crate::{commands::setup::Region, error::Result};
 
 pub struct SpawnInstanceParams<'a> {
     pub region: &'a str,
-    pub server_private_key: &'a str,
     pub client_public_key: &'a str,
+    pub preshared_key: &'a str,
+    /// Hex-encoded key the status endpoint MACs its replies with.
+    pub status_key: &'a str,
+    pub status_access: StatusEndpointAccess,
+    pub lifetime: ServerLifetime,
+    /// Attach a reserved address that outlives the instance, reusing a free
+    /// one in the region before allocating a new one.
+    pub reserve_ip: bool,
     pub spawn_id: &'a str,
 }
 
+/// Per-server choices made at spawn time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpawnOptions {
+    pub status_access: StatusEndpointAccess,
+    pub lifetime: ServerLifetime,
+    pub reserve_ip: bool,
+}
+
+/// Limits the server enforces on itself by deleting itself through its
+/// provider's API, so it goes away even when no client is left to terminate
+/// it. `None` disables a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerLifetime {
+    /// Minutes without a WireGuard handshake, counted from boot until the
+    /// first one.
+    pub idle_timeout_minutes: Option<u32>,
+    /// Minutes since the instance last started.
+    pub max_lifetime_minutes: Option<u32>,
+}
+
+impl ServerLifetime {
+    pub fn is_limited(&self) -> bool {
+        self.idle_timeout_minutes.is_some() || self.max_lifetime_minutes.is_some()
+    }
+}
+
+/// Who may still query the server's status endpoint once the client has
+/// completed a WireGuard handshake.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Display, EnumString,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum StatusEndpointAccess {
+    /// Anyone can ask; replies are still authenticated.
+    #[default]
+    Open,
+    /// Only the address the client's tunnel comes from.
+    ClientOnly,
+    /// The endpoint closes for good.
+    Shutdown,
+}
+
 pub struct TerminateInstanceParams<'a> {
     pub region: &'a str,
     pub instance_id: &'a str,
 }
 
+pub struct StopInstanceParams<'a> {
+    pub region: &'a str,
+    pub instance_id: &'a str,
+}
+
+pub struct StartInstanceParams<'a> {
+    pub region: &'a str,
+    pub instance_id: &'a str,
+}
+
+pub struct ReleaseReservedIpParams<'a> {
+    pub region: &'a str,
+    pub address_id: &'a str,
+}
+
 #[async_trait]
 pub trait CloudProvider: Send + Sync {
     async fn setup(&self) -> Result<()>;
@@ -25,7 +91,16 @@ pub trait CloudProvider: Send + Sync {
     async fn enable_region(&self, region: &str) -> Result<()>;
     async fn spawn_instance(&self, params: &SpawnInstanceParams) -> Result<InstanceInfo>;
     async fn terminate_instance(&self, params: &TerminateInstanceParams) -> Result<()>;
+    /// Stops the instance but keeps its disk, so it can be started again
+    /// later with the same keys.
+    async fn stop_instance(&self, params: &StopInstanceParams) -> Result<()>;
+    /// Starts a stopped instance and waits until it is running. The public
+    /// addresses in the returned info may differ from before the stop.
+    async fn start_instance(&self, params: &StartInstanceParams) -> Result<InstanceInfo>;
     async fn list_instances(&self, region: Option<&str>) -> Result<Vec<InstanceInfo>>;
+    /// Addresses reserved by `reserve_ip` spawns, attached or not.
+    async fn list_reserved_ips(&self, region: Option<&str>) -> Result<Vec<ReservedIpInfo>>;
+    async fn release_reserved_ip(&self, params: &ReleaseReservedIpParams) -> Result<()>;
     async fn get_regions(&self) -> Result<Vec<Region>>;
     fn get_provider_name(&self) -> CloudProviderName;
 
@@ -84,6 +159,19 @@ pub struct InstanceInfo {
     pub spawn_id: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReservedIpInfo {
+    pub id: String,
+    pub region: String,
+    pub public_ip: String,
+    pub provider: CloudProviderName,
+    /// The instance currently holding the address, if any.
+    pub instance_id: Option<String>,
+    /// What the address costs per hour while it is not attached.
+    pub ip_hourly_rate: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PricingInfo {
@@ -1,12 +1,22 @@
 use crate::{
     cloud_provider::CloudProvider,
     config::get_wireguard_config_file_path,
-    daemon_client::{DaemonClient, DaemonCommand, VpnConnectParams},
+    daemon_client::{DaemonClient, DaemonCommand, SplitTunnelConfig, VpnConnectParams},
     error::Result,
     wireguard_config::parse_wireguard_config,
 };
 use log::*;
 
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    pub kill_switch_enabled: bool,
+    pub split_tunnel: SplitTunnelConfig,
+    pub network_namespace: Option<String>,
+    pub allow_lan: bool,
+    pub mtu: Option<u16>,
+    pub probe_mtu: bool,
+}
+
 pub async fn connect(
     provider: &dyn CloudProvider,
     daemon_client: &dyn DaemonClient,
@@ -14,7 +24,7 @@ pub async fn connect(
     instance_id: &str,
     public_ip_v4: Option<String>,
     public_ip_v6: Option<String>,
-    kill_switch_enabled: bool,
+    options: ConnectOptions,
 ) -> Result<()> {
     let provider_name = provider.get_provider_name();
     let wireguard_file_path =
@@ -28,6 +38,7 @@ pub async fn connect(
             instance_id: instance_id.to_string(),
             private_key: wireguard_config.private_key,
             public_key: wireguard_config.public_key,
+            preshared_key: wireguard_config.preshared_key,
             server_endpoint: wireguard_config.server_endpoint,
             private_ipv4: wireguard_config.private_ipv4,
             private_ipv6: wireguard_config.private_ipv6,
@@ -36,7 +47,12 @@ pub async fn connect(
             provider: provider_name,
             public_ip_v4,
             public_ip_v6,
-            kill_switch_enabled,
+            kill_switch_enabled: options.kill_switch_enabled,
+            split_tunnel: options.split_tunnel,
+            network_namespace: options.network_namespace,
+            allow_lan: options.allow_lan,
+            mtu: options.mtu,
+            probe_mtu: options.probe_mtu,
         }))
         .await?;
 
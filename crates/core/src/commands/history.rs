@@ -0,0 +1,24 @@
+use crate::{
+    daemon_client::{DaemonClient, DaemonCommand},
+    error::{ConfigurationError, Result},
+    traffic_history::SessionHistory,
+};
+
+/// Sessions recorded by the daemon that were connected at or after `since`
+/// (Unix seconds), oldest first.
+pub async fn fetch_traffic_history(
+    client: &impl DaemonClient,
+    since: Option<u64>,
+) -> Result<Vec<SessionHistory>> {
+    let response = client
+        .send_command(DaemonCommand::History { since })
+        .await?;
+
+    serde_json::from_value(response).map_err(|error| {
+        ConfigurationError::ParseError {
+            value: "daemon history response".to_string(),
+            reason: error.to_string(),
+        }
+        .into()
+    })
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use crate::{
+    daemon_client::{DaemonClient, DaemonCommand},
+    error::Result,
+};
+
+pub async fn set_allow_lan(daemon_client: &dyn DaemonClient, enabled: bool) -> Result<()> {
+    daemon_client
+        .send_command(DaemonCommand::SetAllowLan { enabled })
+        .await?;
+    Ok(())
+}
+
+pub async fn open_captive_portal(daemon_client: &dyn DaemonClient, duration: Duration) -> Result<()> {
+    daemon_client
+        .send_command(DaemonCommand::OpenCaptivePortal {
+            duration_secs: duration.as_secs(),
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn close_captive_portal(daemon_client: &dyn DaemonClient) -> Result<()> {
+    daemon_client
+        .send_command(DaemonCommand::CloseCaptivePortal)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_lockdown(daemon_client: &dyn DaemonClient, enabled: bool) -> Result<()> {
+    daemon_client
+        .send_command(DaemonCommand::SetLockdown { enabled })
+        .await?;
+    Ok(())
+}
@@ -1,9 +1,15 @@
 pub mod connect;
 pub mod disconnect;
+pub mod history;
+pub mod kill_switch;
 pub mod list;
+pub mod proxy;
+pub mod reserved_ip;
 pub mod setup;
 pub mod spawn;
+pub mod start;
 pub mod status;
+pub mod stop;
 pub mod subscribe;
 pub mod terminate;
 pub mod verify_permissions;
@@ -0,0 +1,24 @@
+use tokio::sync::watch;
+
+use crate::{
+    cloud_provider::CloudProviderName,
+    config::get_wireguard_config_file_path,
+    error::Result,
+    proxy::{self, ProxyOptions},
+    wireguard_config::parse_wireguard_config,
+};
+
+/// Runs proxy mode on the stored client config for an instance.
+pub async fn run_proxy(
+    provider_name: &CloudProviderName,
+    region: &str,
+    instance_id: &str,
+    options: ProxyOptions,
+    shutdown_rx: watch::Receiver<()>,
+) -> Result<()> {
+    let wireguard_file_path =
+        get_wireguard_config_file_path(provider_name, region, instance_id).await?;
+    let wireguard_config = parse_wireguard_config(&wireguard_file_path.to_string_lossy()).await?;
+
+    proxy::run_proxy(&wireguard_config, &options, shutdown_rx).await
+}
@@ -0,0 +1,24 @@
+use crate::{
+    cloud_provider::{CloudProvider, ReleaseReservedIpParams, ReservedIpInfo},
+    error::Result,
+};
+use log::*;
+
+pub async fn list_reserved_ips(
+    provider: &dyn CloudProvider,
+    region: Option<&str>,
+) -> Result<Vec<ReservedIpInfo>> {
+    provider.list_reserved_ips(region).await
+}
+
+pub async fn release_reserved_ip(
+    provider: &dyn CloudProvider,
+    region: &str,
+    address_id: &str,
+) -> Result<()> {
+    let params = ReleaseReservedIpParams { region, address_id };
+    provider.release_reserved_ip(&params).await?;
+
+    info!("Released reserved IP: {}", address_id);
+    Ok(())
+}
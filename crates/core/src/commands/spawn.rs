@@ -6,11 +6,12 @@ use tokio::{fs, io::AsyncWriteExt};
 
 use crate::{
     cloud_provider::{
-        CloudProvider, CloudProviderName, InstanceInfo, SpawnInstanceParams, SpawnStep,
-        SpawnStepStatus,
+        CloudProvider, CloudProviderName, InstanceInfo, SpawnInstanceParams, SpawnOptions,
+        SpawnStep, SpawnStepStatus,
     },
     config::{generate_client_config, get_wireguard_config_file_path},
     connectivity,
+    crypto::derive_status_key_from_base64,
     error::{ConfigurationError, Result},
 };
 
@@ -20,9 +21,9 @@ pub async fn run_spawn_steps<F1, F2>(
     region: &str,
     spawn_id: &str,
     client_private_key: &str,
-    server_private_key: &str,
     client_public_key: &str,
-    server_public_key: &str,
+    preshared_key: &str,
+    options: SpawnOptions,
     on_step_progress: F1,
     on_instance_launched: F2,
 ) -> Result<InstanceInfo>
@@ -36,19 +37,18 @@ where
         match step.id.as_str() {
             "launch" => {
                 on_step_progress("launch", SpawnStepStatus::Running, None);
-                match launch_instance(provider, region, spawn_id, server_private_key, client_public_key).await
+                match launch_instance(
+                    provider,
+                    region,
+                    spawn_id,
+                    client_public_key,
+                    preshared_key,
+                    options,
+                )
+                .await
                 {
                     Ok(instance) => {
                         on_step_progress("launch", SpawnStepStatus::Completed, None);
-                        let provider_name = provider.get_provider_name();
-                        write_wireguard_config(
-                            &provider_name,
-                            region,
-                            &instance,
-                            client_private_key,
-                            server_public_key,
-                        )
-                        .await?;
                         on_instance_launched(&instance);
                         spawned_instance = Some(instance);
                     }
@@ -63,13 +63,63 @@ where
                 }
             }
             "wireguard_ready" => {
-                let instance_ip = spawned_instance
+                let instance = spawned_instance
                     .as_ref()
-                    .expect("launch step must precede wireguard_ready")
-                    .public_ip_v4
-                    .clone();
+                    .expect("launch step must precede wireguard_ready");
                 on_step_progress("wireguard_ready", SpawnStepStatus::Running, None);
-                if let Err(error) = connectivity::wait_until_ready(&instance_ip).await {
+                // The server generates its own key, so the client config can
+                // only be written once the server has published the public half.
+                // Install stages the server reports are surfaced as sub-steps
+                // named `wireguard_ready.<stage>`.
+                let mut current_stage: Option<String> = None;
+                let ready = match derive_status_key_from_base64(preshared_key) {
+                    Ok(status_key) => {
+                        connectivity::wait_until_ready(
+                            &instance.public_ip_v4,
+                            &status_key,
+                            |stage| {
+                                if let Some(previous) = current_stage.replace(stage.to_string()) {
+                                    on_step_progress(
+                                        &format!("wireguard_ready.{}", previous),
+                                        SpawnStepStatus::Completed,
+                                        None,
+                                    );
+                                }
+                                on_step_progress(
+                                    &format!("wireguard_ready.{}", stage),
+                                    SpawnStepStatus::Running,
+                                    None,
+                                );
+                            },
+                        )
+                        .await
+                    }
+                    Err(error) => Err(error),
+                };
+                if let Some(stage) = &current_stage
+                    && ready.is_ok()
+                {
+                    on_step_progress(
+                        &format!("wireguard_ready.{}", stage),
+                        SpawnStepStatus::Completed,
+                        None,
+                    );
+                }
+                let result = match ready {
+                    Ok(server_public_key) => {
+                        write_wireguard_config(
+                            &provider.get_provider_name(),
+                            region,
+                            instance,
+                            client_private_key,
+                            &server_public_key,
+                            preshared_key,
+                        )
+                        .await
+                    }
+                    Err(error) => Err(error),
+                };
+                if let Err(error) = result {
                     on_step_progress(
                         "wireguard_ready",
                         SpawnStepStatus::Failed,
@@ -98,13 +148,19 @@ pub async fn launch_instance(
     provider: &dyn CloudProvider,
     region: &str,
     spawn_id: &str,
-    server_private_key: &str,
     client_public_key: &str,
+    preshared_key: &str,
+    options: SpawnOptions,
 ) -> Result<InstanceInfo> {
+    let status_key = hex::encode(derive_status_key_from_base64(preshared_key)?);
     let params = SpawnInstanceParams {
         region,
-        server_private_key,
         client_public_key,
+        preshared_key,
+        status_key: &status_key,
+        status_access: options.status_access,
+        lifetime: options.lifetime,
+        reserve_ip: options.reserve_ip,
         spawn_id,
     };
 
@@ -120,10 +176,12 @@ pub async fn write_wireguard_config(
     instance: &InstanceInfo,
     client_private_key: &str,
     server_public_key: &str,
+    preshared_key: &str,
 ) -> Result<()> {
     let client_config = generate_client_config(
         client_private_key,
         server_public_key,
+        preshared_key,
         &instance.public_ip_v4,
     )?;
 
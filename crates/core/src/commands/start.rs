@@ -0,0 +1,72 @@
+use tokio::fs;
+
+use crate::{
+    cloud_provider::{CloudProvider, InstanceInfo, StartInstanceParams},
+    config::get_wireguard_config_file_path,
+    error::{ConfigurationError, Result},
+};
+use log::*;
+
+pub async fn start_instance(
+    provider: &dyn CloudProvider,
+    region: &str,
+    instance_id: &str,
+) -> Result<InstanceInfo> {
+    let params = StartInstanceParams {
+        region,
+        instance_id,
+    };
+    let instance = provider.start_instance(&params).await?;
+
+    // Without a reserved address the instance comes back with a new public
+    // IP, so point the stored client config at it.
+    let provider_name = provider.get_provider_name();
+    let wireguard_file_path =
+        get_wireguard_config_file_path(&provider_name, region, instance_id).await?;
+    if fs::metadata(&wireguard_file_path).await.is_ok() {
+        let config = fs::read_to_string(&wireguard_file_path)
+            .await
+            .map_err(|error| ConfigurationError::TunnelConfiguration {
+                reason: format!("failed to read config file: {}", error),
+            })?;
+        let updated = rewrite_endpoint(&config, &instance.public_ip_v4);
+        if updated != config {
+            fs::write(&wireguard_file_path, updated)
+                .await
+                .map_err(|error| ConfigurationError::TunnelConfiguration {
+                    reason: format!("failed to write config file: {}", error),
+                })?;
+            info!(
+                "Client config endpoint updated to {}",
+                instance.public_ip_v4
+            );
+        }
+    } else {
+        warn!("No client config for instance {}", instance_id);
+    }
+
+    info!("Started instance: {}", instance_id);
+    Ok(instance)
+}
+
+/// Replaces the host of every `Endpoint` line, keeping its port.
+fn rewrite_endpoint(config: &str, public_ip_v4: &str) -> String {
+    let mut rewritten: String = config
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((key, value)) if key.trim().eq_ignore_ascii_case("Endpoint") => {
+                let port = value.trim().rsplit_once(':').map(|(_, port)| port);
+                match port {
+                    Some(port) => format!("{}= {}:{}", key, public_ip_v4, port),
+                    None => format!("{}= {}", key, public_ip_v4),
+                }
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if config.ends_with('\n') {
+        rewritten.push('\n');
+    }
+    rewritten
+}
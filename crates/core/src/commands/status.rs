@@ -12,6 +12,10 @@ pub async fn fetch_vpn_status(client: &impl DaemonClient) -> Result<VpnStatus> {
             metrics: None,
             connected_at: None,
             connection_error: None,
+            reconnecting: false,
+            backend: None,
+            mtu: None,
+            mtu_probe_error: None,
         });
     }
 
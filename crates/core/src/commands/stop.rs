@@ -0,0 +1,20 @@
+use crate::{
+    cloud_provider::{CloudProvider, StopInstanceParams},
+    error::Result,
+};
+use log::*;
+
+pub async fn stop_instance(
+    provider: &dyn CloudProvider,
+    region: &str,
+    instance_id: &str,
+) -> Result<()> {
+    let params = StopInstanceParams {
+        region,
+        instance_id,
+    };
+    provider.stop_instance(&params).await?;
+
+    info!("Stopped instance: {}", instance_id);
+    Ok(())
+}
@@ -14,12 +14,14 @@ use log::*;
 struct ClientConfigContext {
     client_private_key: String,
     server_public_key: String,
+    preshared_key: String,
     server_ip_v4: String,
 }
 
 pub fn generate_client_config(
     client_private_key: &str,
     server_public_key: &str,
+    preshared_key: &str,
     server_ip_v4: &str,
 ) -> Result<String> {
     let template_text: &str = include_str!("templates/client_config.hbs");
@@ -27,6 +29,7 @@ pub fn generate_client_config(
     let context = ClientConfigContext {
         client_private_key: client_private_key.to_string(),
         server_public_key: server_public_key.to_string(),
+        preshared_key: preshared_key.to_string(),
         server_ip_v4: server_ip_v4.to_string(),
     };
 
@@ -82,6 +85,16 @@ pub fn session_file_path() -> Result<PathBuf> {
     Ok(home_dir.join(".byocvpn").join("session.json"))
 }
 
+pub fn lockdown_file_path() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or(ConfigurationError::HomeDirectoryNotAvailable)?;
+    Ok(home_dir.join(".byocvpn").join("lockdown.json"))
+}
+
+pub fn history_dir_path() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or(ConfigurationError::HomeDirectoryNotAvailable)?;
+    Ok(home_dir.join(".byocvpn").join("history"))
+}
+
 fn get_wireguard_config_file_name(
     provider_name: &CloudProviderName,
     region: &str,
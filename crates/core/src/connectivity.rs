@@ -1,30 +1,97 @@
+use base64::{Engine, engine::general_purpose};
+use hmac::{Hmac, Mac};
 use serde::Deserialize;
+use sha2::Sha256;
 use tokio::{
-    io::AsyncReadExt,
+    io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream as AsyncTcpStream,
     time::{Duration as TokioDuration, sleep, timeout},
 };
 
-use crate::error::{Result, SystemError};
+use crate::{
+    cloud_provider::CloudProviderName,
+    config::get_wireguard_config_file_path,
+    crypto::derive_status_key,
+    error::{Result, SystemError},
+    wireguard_config::parse_wireguard_config,
+};
 use log::*;
 
 const PROBE_MAX_ATTEMPTS: u32 = 30;
 const PROBE_RETRY_DELAY_SECS: u64 = 10;
 const PROBE_CONNECT_TIMEOUT_SECS: u64 = 3;
+const NONCE_SIZE: usize = 16;
+
+/// The document the server's status endpoint serves. Servers from before
+/// version 2 only send `status`, `reason` and `server_public_key`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusDocument {
+    #[serde(default = "legacy_status_version")]
+    pub version: u32,
+    /// `pending`, `ready` or `error`.
+    pub status: String,
+    pub reason: Option<String>,
+    pub server_public_key: Option<String>,
+    /// The install stage the startup script is in (or finished last).
+    pub stage: Option<String>,
+    #[serde(default)]
+    pub stages: Vec<InstallStage>,
+    pub wireguard: Option<WireguardStats>,
+    pub uptime_seconds: Option<f64>,
+    /// 1, 5 and 15 minute load averages.
+    pub load_average: Option<[f64; 3]>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstallStage {
+    /// `packages`, `sysctl` or `wg_quick`.
+    pub name: String,
+    /// Unix time the stage started.
+    pub started_at: i64,
+}
+
+/// Counters for the server's only peer, from `wg show`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WireguardStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Unix time of the last handshake, if there has been one.
+    pub latest_handshake: Option<i64>,
+}
 
-#[derive(Deserialize)]
-struct ServerStatus {
-    status: String,
-    reason: Option<String>,
+fn legacy_status_version() -> u32 {
+    1
 }
 
 pub enum ProbeStatus {
-    Ready,
+    /// WireGuard is up. Servers spawned before keys were generated on the
+    /// instance do not publish their public key.
+    Ready {
+        server_public_key: Option<String>,
+    },
     Installing,
     Error(String),
 }
 
-pub async fn probe_status(ip_address: &str) -> ProbeStatus {
+pub async fn probe_status(ip_address: &str, status_key: Option<&[u8]>) -> ProbeStatus {
+    match fetch_status(ip_address, status_key).await {
+        Some(document) if document.status == "ready" => ProbeStatus::Ready {
+            server_public_key: document.server_public_key,
+        },
+        Some(document) if document.status == "error" => ProbeStatus::Error(
+            document
+                .reason
+                .unwrap_or_else(|| "unknown error".to_string()),
+        ),
+        _ => ProbeStatus::Installing,
+    }
+}
+
+/// Fetches the server's status document. With a `status_key` the reply must
+/// carry a valid MAC over our nonce or it is ignored; without one (no local
+/// config for the instance) the reply is taken on trust. Returns `None` while
+/// the server is unreachable or its reply is unusable.
+pub async fn fetch_status(ip_address: &str, status_key: Option<&[u8]>) -> Option<StatusDocument> {
     let address = format!("{}:51820", ip_address);
 
     let connect = timeout(
@@ -33,36 +100,108 @@ pub async fn probe_status(ip_address: &str) -> ProbeStatus {
     )
     .await;
 
-    match connect {
-        Ok(Ok(mut stream)) => {
-            let mut buffer = Vec::new();
-            let _ = timeout(TokioDuration::from_secs(3), stream.read_to_end(&mut buffer)).await;
-            let raw = String::from_utf8_lossy(&buffer).trim().to_string();
-            match serde_json::from_str::<ServerStatus>(&raw) {
-                Ok(ServerStatus { status, .. }) if status == "ready" => ProbeStatus::Ready,
-                Ok(ServerStatus { status, reason }) if status == "error" => {
-                    ProbeStatus::Error(reason.unwrap_or_else(|| "unknown error".to_string()))
-                }
-                _ => ProbeStatus::Installing,
-            }
-        }
-        _ => ProbeStatus::Installing,
+    let Ok(Ok(mut stream)) = connect else {
+        return None;
+    };
+
+    let nonce = hex::encode(rand::random::<[u8; NONCE_SIZE]>());
+    let _ = stream.write_all(format!("{}\n", nonce).as_bytes()).await;
+    let mut buffer = Vec::new();
+    let _ = timeout(TokioDuration::from_secs(3), stream.read_to_end(&mut buffer)).await;
+    let raw = String::from_utf8_lossy(&buffer).trim().to_string();
+    let (payload, mac) = match raw.rsplit_once('\n') {
+        Some((payload, mac)) => (payload, Some(mac)),
+        None => (raw.as_str(), None),
+    };
+
+    if let Some(status_key) = status_key
+        && !verify_reply(status_key, &nonce, payload, mac)
+    {
+        warn!(
+            "[probe] ignoring unauthenticated status reply from {}",
+            ip_address
+        );
+        return None;
+    }
+
+    serde_json::from_str(payload).ok()
+}
+
+fn verify_reply(status_key: &[u8], nonce: &str, payload: &str, mac: Option<&str>) -> bool {
+    let Some(mac) = mac.and_then(|mac| hex::decode(mac.trim()).ok()) else {
+        return false;
+    };
+    let mut expected =
+        Hmac::<Sha256>::new_from_slice(status_key).expect("HMAC accepts keys of any length");
+    expected.update(nonce.as_bytes());
+    expected.update(b"\n");
+    expected.update(payload.as_bytes());
+    expected.verify_slice(&mac).is_ok()
+}
+
+/// The status key for an instance spawned from this machine, derived from
+/// the preshared key in its WireGuard config.
+pub async fn load_status_key(
+    provider_name: &CloudProviderName,
+    region: &str,
+    instance_id: &str,
+) -> Option<Vec<u8>> {
+    let path = get_wireguard_config_file_path(provider_name, region, instance_id)
+        .await
+        .ok()?;
+    if !path.exists() {
+        return None;
     }
+    let config = parse_wireguard_config(&path.to_string_lossy()).await.ok()?;
+    config.preshared_key.as_deref().map(derive_status_key)
 }
 
-pub async fn wait_until_ready(ip_address: &str) -> Result<()> {
+/// Waits for the server to come up and returns the WireGuard public key it
+/// generated, which the client pins in its config. `on_stage` is called each
+/// time the server reports entering a new install stage.
+pub async fn wait_until_ready<F>(
+    ip_address: &str,
+    status_key: &[u8],
+    mut on_stage: F,
+) -> Result<String>
+where
+    F: FnMut(&str),
+{
+    let mut current_stage: Option<String> = None;
+
     for attempt in 1..=PROBE_MAX_ATTEMPTS {
         debug!("[probe] attempt {}/{}", attempt, PROBE_MAX_ATTEMPTS);
 
-        match probe_status(ip_address).await {
-            ProbeStatus::Ready => {
+        let document = fetch_status(ip_address, Some(status_key)).await;
+        if let Some(document) = &document {
+            debug!(
+                "[probe] status={} stage={:?} uptime={:?} load={:?} wireguard={:?}",
+                document.status,
+                document.stage,
+                document.uptime_seconds,
+                document.load_average,
+                document.wireguard
+            );
+            if let Some(stage) = &document.stage
+                && current_stage.as_ref() != Some(stage)
+            {
+                on_stage(stage);
+                current_stage = Some(stage.clone());
+            }
+        }
+
+        match document {
+            Some(document) if document.status == "ready" => {
                 debug!("[probe] instance ready");
-                return Ok(());
+                return validate_server_public_key(document.server_public_key);
             }
-            ProbeStatus::Error(reason) => {
+            Some(document) if document.status == "error" => {
+                let reason = document
+                    .reason
+                    .unwrap_or_else(|| "unknown error".to_string());
                 return Err(SystemError::ReadinessProbeFailed { reason }.into());
             }
-            ProbeStatus::Installing => {
+            _ => {
                 debug!("[probe] not ready yet, retrying...");
             }
         }
@@ -74,3 +213,19 @@ pub async fn wait_until_ready(ip_address: &str) -> Result<()> {
 
     Err(SystemError::ReadinessProbeTimedOut.into())
 }
+
+fn validate_server_public_key(server_public_key: Option<String>) -> Result<String> {
+    let server_public_key = server_public_key.ok_or_else(|| SystemError::ReadinessProbeFailed {
+        reason: "server did not publish its WireGuard public key".to_string(),
+    })?;
+    match general_purpose::STANDARD.decode(&server_public_key) {
+        Ok(key) if key.len() == 32 => Ok(server_public_key),
+        _ => Err(SystemError::ReadinessProbeFailed {
+            reason: format!(
+                "server published an invalid public key: {}",
+                server_public_key
+            ),
+        }
+        .into()),
+    }
+}
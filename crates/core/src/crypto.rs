@@ -1,6 +1,12 @@
 use base64::{Engine, engine::general_purpose};
 use boringtun::x25519::{PublicKey, StaticSecret};
+use hmac::{Hmac, Mac};
 use rand::rngs::OsRng;
+use sha2::Sha256;
+
+use crate::error::{ConfigurationError, Result};
+
+const STATUS_KEY_LABEL: &[u8] = b"byocvpn status endpoint v1";
 
 pub fn generate_keypair() -> (String, String) {
     let private_key = StaticSecret::random_from_rng(OsRng);
@@ -11,3 +17,30 @@ pub fn generate_keypair() -> (String, String) {
 
     (private_b64, public_b64)
 }
+
+/// A random 32-byte WireGuard preshared key, base64-encoded.
+pub fn generate_preshared_key() -> String {
+    let preshared_key: [u8; 32] = rand::random();
+    general_purpose::STANDARD.encode(preshared_key)
+}
+
+/// The key the server's status endpoint MACs its replies with. It is derived
+/// from the preshared key, so anyone holding the client config can verify
+/// the server without storing another secret.
+pub fn derive_status_key(preshared_key: &[u8]) -> Vec<u8> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(preshared_key).expect("HMAC accepts keys of any length");
+    mac.update(STATUS_KEY_LABEL);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// [`derive_status_key`] for a base64-encoded preshared key.
+pub fn derive_status_key_from_base64(preshared_key: &str) -> Result<Vec<u8>> {
+    let preshared_key = general_purpose::STANDARD
+        .decode(preshared_key)
+        .map_err(|error| ConfigurationError::InvalidValue {
+            field: "preshared_key".to_string(),
+            reason: error.to_string(),
+        })?;
+    Ok(derive_status_key(&preshared_key))
+}
@@ -8,13 +8,45 @@ use serde_json::Value;
 use crate::{
     cloud_provider::CloudProviderName,
     error::{DaemonError, Result},
+    tunnel::{TunnelMetrics, VpnStatus},
 };
 
+/// Version of the request/response envelope spoken over the daemon socket.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest protocol version this build can still talk to.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Which destinations go through the tunnel. The default sends everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitTunnelConfig {
+    /// Networks routed through the tunnel. When this and `include_domains`
+    /// are both empty, all traffic is tunnelled.
+    #[serde(default)]
+    pub include_routes: Vec<IpNet>,
+    /// Networks that always bypass the tunnel via the local gateway.
+    #[serde(default)]
+    pub exclude_routes: Vec<IpNet>,
+    /// Hostnames whose resolved addresses are routed through the tunnel and
+    /// re-resolved periodically while connected.
+    #[serde(default)]
+    pub include_domains: Vec<String>,
+}
+
+impl SplitTunnelConfig {
+    pub fn is_full_tunnel(&self) -> bool {
+        self.include_routes.is_empty() && self.include_domains.is_empty()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VpnConnectParams {
     pub instance_id: String,
     pub private_key: Vec<u8>,
     pub public_key: Vec<u8>,
+    /// Optional so configs from before preshared keys still connect.
+    #[serde(default)]
+    pub preshared_key: Option<Vec<u8>>,
     pub server_endpoint: SocketAddr,
     pub private_ipv4: IpNet,
     pub private_ipv6: IpNet,
@@ -24,6 +56,23 @@ pub struct VpnConnectParams {
     pub public_ip_v4: Option<String>,
     pub public_ip_v6: Option<String>,
     pub kill_switch_enabled: bool,
+    #[serde(default)]
+    pub split_tunnel: SplitTunnelConfig,
+    /// When set, the tunnel lives in this Linux network namespace instead of
+    /// taking over the host's routes and DNS.
+    #[serde(default)]
+    pub network_namespace: Option<String>,
+    /// Let the kill switch pass LAN traffic (RFC1918, link-local, gateway).
+    #[serde(default)]
+    pub allow_lan: bool,
+    /// Tunnel MTU. The daemon's default when unset, or the probe's ceiling
+    /// when `probe_mtu` is set.
+    #[serde(default)]
+    pub mtu: Option<u16>,
+    /// Probe the path at connect time and use the largest MTU that gets
+    /// through to the server.
+    #[serde(default)]
+    pub probe_mtu: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,9 +80,119 @@ pub struct VpnConnectParams {
 pub enum DaemonCommand {
     Connect(VpnConnectParams),
     Disconnect,
+    SetAllowLan { enabled: bool },
+    OpenCaptivePortal { duration_secs: u64 },
+    CloseCaptivePortal,
+    SetLockdown { enabled: bool },
     Status,
     Stats,
     HealthCheck,
+    /// Turns the connection into an event stream of `DaemonEvent` lines.
+    Subscribe,
+    /// Exchanges protocol versions; the reply carries a `DaemonHello`.
+    Hello { protocol_version: u32 },
+    /// Recorded `SessionHistory` for sessions still connected at or after
+    /// `since` (Unix seconds), or all of them.
+    History {
+        #[serde(default)]
+        since: Option<u64>,
+    },
+}
+
+impl DaemonCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DaemonCommand::Connect(_) => "connect",
+            DaemonCommand::Disconnect => "disconnect",
+            DaemonCommand::SetAllowLan { .. } => "set_allow_lan",
+            DaemonCommand::OpenCaptivePortal { .. } => "open_captive_portal",
+            DaemonCommand::CloseCaptivePortal => "close_captive_portal",
+            DaemonCommand::SetLockdown { .. } => "set_lockdown",
+            DaemonCommand::Status => "status",
+            DaemonCommand::Stats => "stats",
+            DaemonCommand::HealthCheck => "health_check",
+            DaemonCommand::Subscribe => "subscribe",
+            DaemonCommand::Hello { .. } => "hello",
+            DaemonCommand::History { .. } => "history",
+        }
+    }
+
+    /// Every command name this build understands, as advertised in `DaemonHello`.
+    pub const NAMES: &'static [&'static str] = &[
+        "connect",
+        "disconnect",
+        "set_allow_lan",
+        "open_captive_portal",
+        "close_captive_portal",
+        "set_lockdown",
+        "status",
+        "stats",
+        "health_check",
+        "subscribe",
+        "hello",
+        "history",
+    ];
+}
+
+/// A command tagged with a client-chosen ID that the daemon echoes back, so
+/// replies can be matched to requests on a shared connection.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DaemonRequest {
+    pub id: u64,
+    pub command: DaemonCommand,
+}
+
+/// A `DaemonResponse` with the ID of the request it answers. Clients that send
+/// a bare `DaemonCommand` get a reply without an ID, as before versioning.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DaemonReply {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub response: DaemonResponse,
+}
+
+/// What the daemon answers to `DaemonCommand::Hello`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonHello {
+    pub protocol_version: u32,
+    pub min_protocol_version: u32,
+    pub daemon_version: String,
+    pub commands: Vec<String>,
+}
+
+impl DaemonHello {
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+            commands: DaemonCommand::NAMES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Fails with a typed error when the two sides have no protocol version
+    /// in common.
+    pub fn check_compatible(&self) -> std::result::Result<(), DaemonError> {
+        if self.protocol_version < MIN_PROTOCOL_VERSION {
+            return Err(DaemonError::DaemonTooOld {
+                daemon_version: self.protocol_version,
+                required: MIN_PROTOCOL_VERSION,
+            });
+        }
+        if self.min_protocol_version > PROTOCOL_VERSION {
+            return Err(DaemonError::DaemonTooNew {
+                client_version: PROTOCOL_VERSION,
+                required: self.min_protocol_version,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn supports(&self, command: &DaemonCommand) -> bool {
+        self.commands.iter().any(|name| name == command.name())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,6 +202,17 @@ pub enum DaemonResponse {
     Err(DaemonError),
 }
 
+/// Pushed to subscribers, one JSON line per event, after the `Subscribe` ack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "payload", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    StateChanged(VpnStatus),
+    Metrics(TunnelMetrics),
+    Reconnecting { reason: String, attempt: u64 },
+    Reconnected,
+    Error { message: String },
+}
+
 #[async_trait]
 pub trait DaemonClient: Send + Sync {
     async fn send_command(&self, command: DaemonCommand) -> Result<Value>;
@@ -11,6 +11,18 @@ pub enum ComputeProvisioningError {
         reason: String,
     },
 
+    #[error("stopping instance {instance_identifier} failed: {reason}")]
+    InstanceStopFailed {
+        instance_identifier: String,
+        reason: String,
+    },
+
+    #[error("starting instance {instance_identifier} failed: {reason}")]
+    InstanceStartFailed {
+        instance_identifier: String,
+        reason: String,
+    },
+
     #[error("no instance returned in response")]
     NoInstanceInResponse,
 
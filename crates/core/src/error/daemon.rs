@@ -26,4 +26,16 @@ pub enum DaemonError {
 
     #[error("daemon already running")]
     AlreadyRunning,
+
+    #[error("permission denied for {command}: {reason}")]
+    PermissionDenied { command: String, reason: String },
+
+    #[error("daemon is too old: it speaks protocol {daemon_version}, this client needs {required}")]
+    DaemonTooOld { daemon_version: u32, required: u32 },
+
+    #[error("daemon is too new: it requires protocol {required}, this client speaks {client_version}")]
+    DaemonTooNew { client_version: u32, required: u32 },
+
+    #[error("daemon does not support the {command} command")]
+    UnsupportedCommand { command: String },
 }
@@ -83,4 +83,13 @@ pub enum NetworkProvisioningError {
 
     #[error("missing field '{field}' on resource '{resource}'")]
     MissingResourceField { field: &'static str, resource: &'static str },
+
+    #[error("reserved IP allocation failed: {reason}")]
+    ReservedIpAllocationFailed { reason: String },
+
+    #[error("attaching reserved IP {address_id} failed: {reason}")]
+    ReservedIpAssociationFailed { address_id: String, reason: String },
+
+    #[error("releasing reserved IP {address_id} failed: {reason}")]
+    ReservedIpReleaseFailed { address_id: String, reason: String },
 }
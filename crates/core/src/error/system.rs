@@ -16,4 +16,10 @@ pub enum SystemError {
 
     #[error("kill switch operation failed: {reason}")]
     KillSwitchFailed { reason: String },
+
+    #[error("network namespace operation failed: {reason}")]
+    NetworkNamespaceFailed { reason: String },
+
+    #[error("proxy failed: {reason}")]
+    ProxyFailed { reason: String },
 }
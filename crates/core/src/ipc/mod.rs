@@ -1,7 +1,7 @@
 #[cfg(unix)]
 mod unix;
 #[cfg(unix)]
-pub use unix::{IpcSocket, IpcStream};
+pub use unix::{IpcSocket, IpcStream, PeerCredentials};
 
 #[cfg(windows)]
 mod windows;
@@ -23,7 +23,9 @@ impl IpcSocket {
         use std::os::unix::fs::PermissionsExt;
         if let Ok(metadata) = std::fs::metadata(&path) {
             let mut perms = metadata.permissions();
-            perms.set_mode(0o777);
+            // Anyone may connect; the daemon checks each caller's credentials
+            // before running a command.
+            perms.set_mode(0o666);
             let _ = std::fs::set_permissions(&path, perms);
         }
 
@@ -52,11 +54,33 @@ impl Drop for IpcSocket {
     }
 }
 
+/// Identity of the process on the other end of a socket, from the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
 pub struct IpcStream {
     stream: UnixStream,
 }
 
 impl IpcStream {
+    pub fn peer_credentials(&self) -> Result<PeerCredentials> {
+        let credentials = self
+            .stream
+            .peer_cred()
+            .map_err(|error| DaemonError::SocketError {
+                reason: format!("failed to read peer credentials: {}", error),
+            })?;
+        Ok(PeerCredentials {
+            uid: credentials.uid(),
+            gid: credentials.gid(),
+            pid: credentials.pid(),
+        })
+    }
+
     pub async fn connect(path: &PathBuf) -> Result<Self> {
         let stream =
             UnixStream::connect(path)
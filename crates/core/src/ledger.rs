@@ -16,6 +16,16 @@ pub struct LedgerEntry {
     pub setup_complete: bool,
     pub bytes_sent: u64,
     pub bytes_received: u64,
+    /// When the instance was last stopped, while it still is.
+    #[serde(default)]
+    pub stopped_since: Option<DateTime<Utc>>,
+    /// Stopped time accumulated over earlier stopped periods.
+    #[serde(default)]
+    pub stopped_seconds: u64,
+    /// When a stopped instance was last started again. The server's own
+    /// lifetime counts from here.
+    #[serde(default)]
+    pub restarted_at: Option<DateTime<Utc>>,
 }
 
 impl LedgerEntry {
@@ -27,6 +37,30 @@ impl LedgerEntry {
         self.terminated_at = Some(Utc::now());
     }
 
+    pub fn mark_stopped(&mut self) {
+        if self.stopped_since.is_none() {
+            self.stopped_since = Some(Utc::now());
+        }
+    }
+
+    pub fn mark_started(&mut self) {
+        if let Some(stopped_since) = self.stopped_since.take() {
+            let now = Utc::now();
+            let stopped = (now - stopped_since).num_seconds().max(0);
+            self.stopped_seconds += stopped as u64;
+            self.restarted_at = Some(now);
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped_since.is_some()
+    }
+
+    /// When the current run began, which is what time limits count from.
+    pub fn running_since(&self) -> DateTime<Utc> {
+        self.restarted_at.unwrap_or(self.launched_at)
+    }
+
     pub fn mark_setup_complete(&mut self) {
         self.setup_complete = true;
     }
@@ -36,3 +70,57 @@ impl LedgerEntry {
         self.bytes_received = bytes_received;
     }
 }
+
+/// A reserved address, billed from allocation until release. It is only
+/// charged `ip_hourly_rate` while idle; while attached its cost is part of
+/// the instance it is attached to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReservedIpLedgerEntry {
+    pub address_id: String,
+    pub provider: CloudProviderName,
+    pub region: String,
+    pub public_ip: String,
+    pub reserved_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+    pub ip_hourly_rate: f64,
+    /// When the address last became detached, while it still is.
+    pub idle_since: Option<DateTime<Utc>>,
+    /// Idle time accumulated over earlier detached periods.
+    #[serde(default)]
+    pub idle_seconds: u64,
+    /// The instance the address is attached to, while it is.
+    #[serde(default)]
+    pub instance_id: Option<String>,
+}
+
+impl ReservedIpLedgerEntry {
+    pub fn build_store_key(address_id: &str) -> String {
+        format!("reserved-ip/{}", address_id)
+    }
+
+    pub fn mark_idle(&mut self) {
+        self.instance_id = None;
+        if self.idle_since.is_none() {
+            self.idle_since = Some(Utc::now());
+        }
+    }
+
+    pub fn mark_attached(&mut self, instance_id: &str) {
+        self.instance_id = Some(instance_id.to_string());
+        self.end_idle_period();
+    }
+
+    pub fn mark_released(&mut self) {
+        self.instance_id = None;
+        self.end_idle_period();
+        self.released_at = Some(Utc::now());
+    }
+
+    fn end_idle_period(&mut self) {
+        if let Some(idle_since) = self.idle_since.take() {
+            let idle = (Utc::now() - idle_since).num_seconds().max(0);
+            self.idle_seconds += idle as u64;
+        }
+    }
+}
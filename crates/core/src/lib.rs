@@ -12,5 +12,8 @@ pub mod error;
 pub mod ipc;
 pub mod ledger;
 pub mod metrics_stream;
+pub mod proxy;
+pub mod traffic_history;
 pub mod tunnel;
+pub mod udp_batch;
 pub mod wireguard_config;
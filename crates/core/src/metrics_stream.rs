@@ -10,7 +10,7 @@ use std::{
 use crate::{
     error::{Result, SystemError},
     ipc::IpcStream,
-    tunnel::{ConnectedInstance, TunnelMetrics, VpnStatus},
+    tunnel::{ConnectedInstance, PeerState, TunnelMetrics, VpnStatus},
 };
 use log::*;
 
@@ -93,9 +93,13 @@ where
                             on_update(VpnStatus {
                                 connected: true,
                                 instance: Some(current_instance),
+                                reconnecting: metrics.peer_state == PeerState::Reconnecting,
                                 metrics: Some(metrics),
                                 connected_at: current_timestamp,
                                 connection_error: None,
+                                backend: None,
+                                mtu: None,
+                                mtu_probe_error: None,
                             });
                         } else {
                             error!("Failed to parse metrics: {}", line);
@@ -127,6 +131,10 @@ where
                 metrics: None,
                 connected_at: None,
                 connection_error: None,
+                reconnecting: false,
+                backend: None,
+                mtu: None,
+                mtu_probe_error: None,
             });
 
             loop {
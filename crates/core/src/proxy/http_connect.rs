@@ -0,0 +1,96 @@
+//! HTTP proxy front end supporting the `CONNECT` method, which covers HTTPS
+//! and any other TCP protocol a client tunnels through it.
+
+use log::*;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::stack::{StackHandle, Target};
+use crate::error::{Result, SystemError};
+
+/// Longest request head accepted before the client is turned away.
+const MAX_REQUEST_HEAD_SIZE: usize = 8 * 1024;
+
+const RESPONSE_ESTABLISHED: &[u8] = b"HTTP/1.1 200 Connection Established\r\n\r\n";
+const RESPONSE_BAD_REQUEST: &[u8] = b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n";
+const RESPONSE_METHOD_NOT_ALLOWED: &[u8] =
+    b"HTTP/1.1 405 Method Not Allowed\r\nAllow: CONNECT\r\nConnection: close\r\n\r\n";
+const RESPONSE_BAD_GATEWAY: &[u8] = b"HTTP/1.1 502 Bad Gateway\r\nConnection: close\r\n\r\n";
+
+pub async fn handle_client(mut client: TcpStream, stack: StackHandle) -> Result<()> {
+    let head = read_request_head(&mut client).await?;
+    let request_line = head.lines().next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (method, authority) = (parts.next().unwrap_or_default(), parts.next());
+
+    if !method.eq_ignore_ascii_case("CONNECT") {
+        client
+            .write_all(RESPONSE_METHOD_NOT_ALLOWED)
+            .await
+            .map_err(io_error)?;
+        return Err(protocol_error(format!("unsupported method {}", method)));
+    }
+    let Some(target) = authority.and_then(parse_authority) else {
+        client.write_all(RESPONSE_BAD_REQUEST).await.map_err(io_error)?;
+        return Err(protocol_error(format!("bad request line: {}", request_line)));
+    };
+
+    debug!("[Proxy] HTTP CONNECT {}", target);
+    let connection = match stack.connect(target).await {
+        Ok(connection) => connection,
+        Err(error) => {
+            client.write_all(RESPONSE_BAD_GATEWAY).await.map_err(io_error)?;
+            return Err(error);
+        }
+    };
+    client
+        .write_all(RESPONSE_ESTABLISHED)
+        .await
+        .map_err(io_error)?;
+
+    connection.relay(client).await.map_err(io_error)
+}
+
+/// Reads up to the blank line ending the request head. Reads a byte at a
+/// time so nothing the client sends after the head is consumed here.
+async fn read_request_head(client: &mut TcpStream) -> Result<String> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_REQUEST_HEAD_SIZE {
+            client.write_all(RESPONSE_BAD_REQUEST).await.map_err(io_error)?;
+            return Err(protocol_error("request head too large".to_string()));
+        }
+        head.push(client.read_u8().await.map_err(io_error)?);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Parses `host:port`, where an IPv6 host is written in brackets.
+fn parse_authority(authority: &str) -> Option<Target> {
+    let (host, port) = authority.rsplit_once(':')?;
+    let port = port.parse().ok()?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if host.is_empty() {
+        return None;
+    }
+    Some(Target::Domain(host.to_string(), port))
+}
+
+fn protocol_error(reason: String) -> crate::error::Error {
+    SystemError::ProxyFailed {
+        reason: format!("HTTP CONNECT: {}", reason),
+    }
+    .into()
+}
+
+fn io_error(error: std::io::Error) -> crate::error::Error {
+    SystemError::ProxyFailed {
+        reason: error.to_string(),
+    }
+    .into()
+}
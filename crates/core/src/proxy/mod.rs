@@ -0,0 +1,205 @@
+//! Proxy mode: runs the WireGuard session and a TCP/IP stack in userspace
+//! and exposes local SOCKS5 and HTTP CONNECT proxies. It needs no root, TUN
+//! device, routes, DNS or firewall changes; only applications pointed at the
+//! proxy use the tunnel.
+
+mod http_connect;
+mod socks5;
+mod stack;
+
+use std::net::{IpAddr, SocketAddr};
+
+use boringtun::{
+    noise::Tunn,
+    x25519::{PublicKey, StaticSecret},
+};
+use log::*;
+use tokio::{
+    net::{TcpListener, UdpSocket},
+    sync::watch,
+    task::JoinHandle,
+};
+
+use self::stack::{DEFAULT_STACK_MTU, MAX_STACK_MTU, NetStack, StackHandle};
+use crate::{
+    error::{ConfigurationError, Result, SystemError},
+    wireguard_config::WireguardConfig,
+};
+
+pub const DEFAULT_SOCKS_LISTEN: &str = "127.0.0.1:1080";
+pub const DEFAULT_HTTP_LISTEN: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Clone)]
+pub struct ProxyOptions {
+    /// Where to accept SOCKS5 clients, if at all.
+    pub socks_listen: Option<SocketAddr>,
+    /// Where to accept HTTP CONNECT clients, if at all.
+    pub http_listen: Option<SocketAddr>,
+    /// MTU of the userspace interface; defaults to the IPv6 minimum.
+    pub mtu: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ProxyProtocol {
+    Socks5,
+    HttpConnect,
+}
+
+/// Serves the proxies until `shutdown_rx` fires or the tunnel fails.
+pub async fn run_proxy(
+    config: &WireguardConfig,
+    options: &ProxyOptions,
+    shutdown_rx: watch::Receiver<()>,
+) -> Result<()> {
+    if options.socks_listen.is_none() && options.http_listen.is_none() {
+        return Err(ConfigurationError::InvalidValue {
+            field: "proxy listen address".to_string(),
+            reason: "enable at least one of SOCKS5 or HTTP".to_string(),
+        }
+        .into());
+    }
+    let mtu = options.mtu.map_or(DEFAULT_STACK_MTU, usize::from);
+    if !(DEFAULT_STACK_MTU..=MAX_STACK_MTU).contains(&mtu) {
+        return Err(ConfigurationError::InvalidValue {
+            field: "mtu".to_string(),
+            reason: format!("must be between {} and {}", DEFAULT_STACK_MTU, MAX_STACK_MTU),
+        }
+        .into());
+    }
+
+    let tunn = create_tunn(
+        &config.private_key,
+        &config.public_key,
+        config.preshared_key.as_deref(),
+    )?;
+    let udp = connect_udp_socket(config.server_endpoint).await?;
+    let dns_servers: Vec<IpAddr> = config
+        .dns_servers
+        .iter()
+        .filter_map(|server| server.parse().ok())
+        .collect();
+    let (stack, handle) = NetStack::new(
+        tunn,
+        udp,
+        &[config.private_ipv4, config.private_ipv6],
+        &dns_servers,
+        mtu,
+    );
+
+    let mut listeners = Vec::new();
+    if let Some(address) = options.socks_listen {
+        listeners.push(spawn_listener(address, ProxyProtocol::Socks5, handle.clone()).await?);
+    }
+    if let Some(address) = options.http_listen {
+        listeners.push(spawn_listener(address, ProxyProtocol::HttpConnect, handle.clone()).await?);
+    }
+    drop(handle);
+
+    let result = stack.run(shutdown_rx).await;
+    for listener in listeners {
+        listener.abort();
+    }
+    result
+}
+
+async fn spawn_listener(
+    address: SocketAddr,
+    protocol: ProxyProtocol,
+    stack: StackHandle,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|error| SystemError::ProxyFailed {
+            reason: format!("failed to listen on {}: {}", address, error),
+        })?;
+    info!("[Proxy] {:?} proxy listening on {}", protocol, address);
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let (client, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    warn!("[Proxy] Accept failed: {}", error);
+                    continue;
+                }
+            };
+            let stack = stack.clone();
+            tokio::spawn(async move {
+                let result = match protocol {
+                    ProxyProtocol::Socks5 => socks5::handle_client(client, stack).await,
+                    ProxyProtocol::HttpConnect => http_connect::handle_client(client, stack).await,
+                };
+                if let Err(error) = result {
+                    debug!("[Proxy] {:?} client {}: {}", protocol, peer, error);
+                }
+            });
+        }
+    }))
+}
+
+fn create_tunn(
+    private_key: &[u8],
+    public_key: &[u8],
+    preshared_key: Option<&[u8]>,
+) -> Result<Tunn> {
+    let private_key: [u8; 32] =
+        private_key
+            .try_into()
+            .map_err(|_| ConfigurationError::InvalidValue {
+                field: "private_key".to_string(),
+                reason: "Private key must be exactly 32 bytes".to_string(),
+            })?;
+    let public_key: [u8; 32] =
+        public_key
+            .try_into()
+            .map_err(|_| ConfigurationError::InvalidValue {
+                field: "public_key".to_string(),
+                reason: "Public key must be exactly 32 bytes".to_string(),
+            })?;
+    let preshared_key = preshared_key
+        .map(|preshared_key| {
+            <[u8; 32]>::try_from(preshared_key).map_err(|_| ConfigurationError::InvalidValue {
+                field: "preshared_key".to_string(),
+                reason: "Preshared key must be exactly 32 bytes".to_string(),
+            })
+        })
+        .transpose()?;
+
+    Tunn::new(
+        StaticSecret::from(private_key),
+        PublicKey::from(public_key),
+        preshared_key,
+        Some(25),
+        0,
+        None,
+    )
+    .map_err(|error| {
+        ConfigurationError::TunnelConfiguration {
+            reason: format!("Failed to create WireGuard tunnel: {:?}", error),
+        }
+        .into()
+    })
+}
+
+async fn connect_udp_socket(server_endpoint: SocketAddr) -> Result<UdpSocket> {
+    let local: SocketAddr = if server_endpoint.is_ipv4() {
+        (std::net::Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let udp = UdpSocket::bind(local)
+        .await
+        .map_err(|error| SystemError::TunnelIoFailed {
+            reason: format!("Failed to bind UDP socket: {}", error),
+        })?;
+    udp.connect(server_endpoint)
+        .await
+        .map_err(|error| SystemError::TunnelIoFailed {
+            reason: format!(
+                "Failed to connect UDP socket to {}: {}",
+                server_endpoint, error
+            ),
+        })?;
+    info!("[Proxy] UDP socket connected to {}", server_endpoint);
+    Ok(udp)
+}
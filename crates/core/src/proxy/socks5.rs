@@ -0,0 +1,114 @@
+//! SOCKS5 front end (RFC 1928): no authentication, `CONNECT` only.
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use log::*;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::stack::{StackHandle, Target};
+use crate::error::{Result, SystemError};
+
+const VERSION: u8 = 0x05;
+const METHOD_NO_AUTHENTICATION: u8 = 0x00;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xff;
+const COMMAND_CONNECT: u8 = 0x01;
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+const REPLY_SUCCEEDED: u8 = 0x00;
+const REPLY_HOST_UNREACHABLE: u8 = 0x04;
+const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+pub async fn handle_client(mut client: TcpStream, stack: StackHandle) -> Result<()> {
+    let mut header = [0u8; 2];
+    client.read_exact(&mut header).await.map_err(io_error)?;
+    if header[0] != VERSION {
+        return Err(protocol_error(format!("unsupported SOCKS version {}", header[0])));
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    client.read_exact(&mut methods).await.map_err(io_error)?;
+    if !methods.contains(&METHOD_NO_AUTHENTICATION) {
+        client
+            .write_all(&[VERSION, METHOD_NONE_ACCEPTABLE])
+            .await
+            .map_err(io_error)?;
+        return Err(protocol_error("client requires authentication".to_string()));
+    }
+    client
+        .write_all(&[VERSION, METHOD_NO_AUTHENTICATION])
+        .await
+        .map_err(io_error)?;
+
+    let mut request = [0u8; 4];
+    client.read_exact(&mut request).await.map_err(io_error)?;
+    let [_, command, _, address_type] = request;
+
+    let target = match address_type {
+        ADDRESS_IPV4 => {
+            let mut octets = [0u8; 4];
+            client.read_exact(&mut octets).await.map_err(io_error)?;
+            let port = client.read_u16().await.map_err(io_error)?;
+            Target::Address(SocketAddr::new(Ipv4Addr::from(octets).into(), port))
+        }
+        ADDRESS_IPV6 => {
+            let mut octets = [0u8; 16];
+            client.read_exact(&mut octets).await.map_err(io_error)?;
+            let port = client.read_u16().await.map_err(io_error)?;
+            Target::Address(SocketAddr::new(Ipv6Addr::from(octets).into(), port))
+        }
+        ADDRESS_DOMAIN => {
+            let length = client.read_u8().await.map_err(io_error)?;
+            let mut name = vec![0u8; length as usize];
+            client.read_exact(&mut name).await.map_err(io_error)?;
+            let port = client.read_u16().await.map_err(io_error)?;
+            Target::Domain(String::from_utf8_lossy(&name).into_owned(), port)
+        }
+        other => {
+            send_reply(&mut client, REPLY_ADDRESS_NOT_SUPPORTED).await?;
+            return Err(protocol_error(format!("unsupported address type {}", other)));
+        }
+    };
+
+    if command != COMMAND_CONNECT {
+        send_reply(&mut client, REPLY_COMMAND_NOT_SUPPORTED).await?;
+        return Err(protocol_error(format!("unsupported command {}", command)));
+    }
+
+    debug!("[Proxy] SOCKS5 CONNECT {}", target);
+    let connection = match stack.connect(target).await {
+        Ok(connection) => connection,
+        Err(error) => {
+            send_reply(&mut client, REPLY_HOST_UNREACHABLE).await?;
+            return Err(error);
+        }
+    };
+    send_reply(&mut client, REPLY_SUCCEEDED).await?;
+
+    connection.relay(client).await.map_err(io_error)
+}
+
+/// Replies with an unspecified bound address; clients only need the code.
+async fn send_reply(client: &mut TcpStream, code: u8) -> Result<()> {
+    client
+        .write_all(&[VERSION, code, 0x00, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0])
+        .await
+        .map_err(io_error)
+}
+
+fn protocol_error(reason: String) -> crate::error::Error {
+    SystemError::ProxyFailed {
+        reason: format!("SOCKS5: {}", reason),
+    }
+    .into()
+}
+
+fn io_error(error: std::io::Error) -> crate::error::Error {
+    SystemError::ProxyFailed {
+        reason: error.to_string(),
+    }
+    .into()
+}
@@ -0,0 +1,682 @@
+//! A userspace TCP/IP stack whose packets travel through a boringtun session
+//! over an ordinary UDP socket, so no TUN device or root is needed. Proxy
+//! front ends ask it for TCP connections through a [`StackHandle`].
+
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use boringtun::noise::{Tunn, TunnResult, errors::WireGuardError};
+use ipnet::IpNet;
+use log::*;
+use smoltcp::{
+    iface::{Config, Interface, SocketHandle, SocketSet},
+    phy::{self, Device, DeviceCapabilities, Medium},
+    socket::{dns, tcp},
+    wire::{DnsQueryType, HardwareAddress, IpAddress, IpCidr},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+    sync::{Notify, mpsc, oneshot, watch},
+    time::{Instant, MissedTickBehavior},
+};
+
+use crate::error::{Result, SystemError};
+
+/// The IPv6 minimum; used unless a larger MTU is requested.
+pub const DEFAULT_STACK_MTU: usize = 1280;
+pub const MAX_STACK_MTU: usize = 9000;
+
+const TIMER_TICK_INTERVAL: Duration = Duration::from_millis(250);
+const PACKET_BUFFER_SIZE: usize = 16 * 1024;
+const TCP_BUFFER_SIZE: usize = 256 * 1024;
+/// Chunks queued between a proxied client and its stack socket, each way.
+const CHANNEL_CAPACITY: usize = 64;
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+/// Gives up on a connection whose data goes unacknowledged this long.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// Where a proxy client wants to go.
+#[derive(Debug, Clone)]
+pub enum Target {
+    Address(SocketAddr),
+    /// Resolved through the tunnel's DNS servers.
+    Domain(String, u16),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Address(address) => write!(formatter, "{}", address),
+            Target::Domain(name, port) => write!(formatter, "{}:{}", name, port),
+        }
+    }
+}
+
+struct ConnectRequest {
+    target: Target,
+    reply: oneshot::Sender<Result<StackConnection>>,
+}
+
+/// Opens connections through a running [`NetStack`].
+#[derive(Clone)]
+pub struct StackHandle {
+    requests: mpsc::Sender<ConnectRequest>,
+}
+
+impl StackHandle {
+    pub async fn connect(&self, target: Target) -> Result<StackConnection> {
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send(ConnectRequest { target, reply })
+            .await
+            .map_err(|_| stack_stopped())?;
+        response.await.map_err(|_| stack_stopped())?
+    }
+}
+
+/// An established TCP connection inside the tunnel.
+pub struct StackConnection {
+    outgoing: mpsc::Sender<Vec<u8>>,
+    incoming: mpsc::Receiver<Vec<u8>>,
+    wakeup: Arc<Notify>,
+}
+
+impl StackConnection {
+    /// Copies bytes both ways between `client` and the remote end until both
+    /// directions have closed.
+    pub async fn relay(self, client: TcpStream) -> io::Result<()> {
+        let StackConnection {
+            outgoing,
+            mut incoming,
+            wakeup,
+            ..
+        } = self;
+        let (mut reader, mut writer) = client.into_split();
+
+        let upload = async {
+            let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+            loop {
+                let length = reader.read(&mut buffer).await?;
+                if length == 0 || outgoing.send(buffer[..length].to_vec()).await.is_err() {
+                    break;
+                }
+                wakeup.notify_one();
+            }
+            // Dropping the sender tells the stack to send a FIN.
+            drop(outgoing);
+            wakeup.notify_one();
+            Ok::<_, io::Error>(())
+        };
+
+        let download = async {
+            while let Some(chunk) = incoming.recv().await {
+                // The stack may be waiting for room in the channel.
+                wakeup.notify_one();
+                writer.write_all(&chunk).await?;
+            }
+            writer.shutdown().await
+        };
+
+        tokio::try_join!(upload, download).map(|_| ())
+    }
+}
+
+fn stack_stopped() -> crate::error::Error {
+    SystemError::ProxyFailed {
+        reason: "network stack stopped".to_string(),
+    }
+    .into()
+}
+
+fn connect_failed(target: impl fmt::Display, reason: impl fmt::Display) -> crate::error::Error {
+    SystemError::ProxyFailed {
+        reason: format!("connecting to {} failed: {}", target, reason),
+    }
+    .into()
+}
+
+/// Packets queued between the interface and the WireGuard session.
+struct TunnelDevice {
+    /// Decrypted packets from the peer, waiting for the interface.
+    inbound: VecDeque<Vec<u8>>,
+    /// Packets from the interface, waiting to be encrypted.
+    outbound: VecDeque<Vec<u8>>,
+    mtu: usize,
+}
+
+struct RxToken(Vec<u8>);
+
+struct TxToken<'a>(&'a mut VecDeque<Vec<u8>>);
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+impl phy::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, length: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0u8; length];
+        let result = f(&mut packet);
+        self.0.push_back(packet);
+        result
+    }
+}
+
+impl Device for TunnelDevice {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(
+        &mut self,
+        _timestamp: smoltcp::time::Instant,
+    ) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let packet = self.inbound.pop_front()?;
+        Some((RxToken(packet), TxToken(&mut self.outbound)))
+    }
+
+    fn transmit(&mut self, _timestamp: smoltcp::time::Instant) -> Option<Self::TxToken<'_>> {
+        Some(TxToken(&mut self.outbound))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut capabilities = DeviceCapabilities::default();
+        capabilities.medium = Medium::Ip;
+        capabilities.max_transmission_unit = self.mtu;
+        capabilities
+    }
+}
+
+struct Connection {
+    handle: SocketHandle,
+    remote: SocketAddr,
+    /// Held until the TCP handshake completes.
+    pending_reply: Option<(oneshot::Sender<Result<StackConnection>>, StackConnection)>,
+    outgoing: mpsc::Receiver<Vec<u8>>,
+    /// Dropped once the remote end has finished sending.
+    incoming: Option<mpsc::Sender<Vec<u8>>>,
+    /// Bytes from the client the socket has not accepted yet.
+    unsent: Vec<u8>,
+    client_finished: bool,
+}
+
+struct Lookup {
+    query: dns::QueryHandle,
+    query_type: DnsQueryType,
+    name: String,
+    port: u16,
+    reply: oneshot::Sender<Result<StackConnection>>,
+}
+
+enum Event {
+    Shutdown,
+    Datagram(io::Result<usize>),
+    Request(ConnectRequest),
+    Timers,
+    Wakeup,
+}
+
+pub struct NetStack {
+    tunn: Tunn,
+    udp: UdpSocket,
+    device: TunnelDevice,
+    interface: Interface,
+    sockets: SocketSet<'static>,
+    dns_socket: Option<SocketHandle>,
+    requests: mpsc::Receiver<ConnectRequest>,
+    wakeup: Arc<Notify>,
+    connections: Vec<Connection>,
+    lookups: Vec<Lookup>,
+    next_local_port: u16,
+    started: Instant,
+    scratch: Vec<u8>,
+    handshake_completed: bool,
+    peer_unreachable: bool,
+}
+
+impl NetStack {
+    /// Builds the stack on the tunnel addresses from the client config, with
+    /// `mtu` as the largest packet it sends. `udp` must already be connected
+    /// to the server.
+    pub fn new(
+        tunn: Tunn,
+        udp: UdpSocket,
+        addresses: &[IpNet],
+        dns_servers: &[IpAddr],
+        mtu: usize,
+    ) -> (Self, StackHandle) {
+        let started = Instant::now();
+        let mut device = TunnelDevice {
+            inbound: VecDeque::new(),
+            outbound: VecDeque::new(),
+            mtu,
+        };
+
+        let mut config = Config::new(HardwareAddress::Ip);
+        config.random_seed = rand::random();
+        let mut interface = Interface::new(config, &mut device, smoltcp::time::Instant::ZERO);
+        interface.update_ip_addrs(|cidrs| {
+            for address in addresses {
+                let cidr = IpCidr::new(IpAddress::from(address.addr()), address.prefix_len());
+                if cidrs.push(cidr).is_err() {
+                    warn!("[Proxy] Ignoring extra tunnel address {}", address);
+                }
+            }
+        });
+        // With an IP-only medium the gateway is never resolved, so any of
+        // our own addresses will do.
+        for address in addresses {
+            let result = match address.addr() {
+                IpAddr::V4(ip) => interface.routes_mut().add_default_ipv4_route(ip).map(|_| ()),
+                IpAddr::V6(ip) => interface.routes_mut().add_default_ipv6_route(ip).map(|_| ()),
+            };
+            if result.is_err() {
+                warn!("[Proxy] Failed to add a default route via {}", address);
+            }
+        }
+
+        let mut sockets = SocketSet::new(Vec::new());
+        let dns_socket = if dns_servers.is_empty() {
+            warn!("[Proxy] No DNS servers in config; only IP targets will work.");
+            None
+        } else {
+            let servers: Vec<IpAddress> = dns_servers.iter().copied().map(IpAddress::from).collect();
+            Some(sockets.add(dns::Socket::new(&servers, Vec::new())))
+        };
+
+        let (requests_tx, requests) = mpsc::channel(CHANNEL_CAPACITY);
+        let stack = NetStack {
+            tunn,
+            udp,
+            device,
+            interface,
+            sockets,
+            dns_socket,
+            requests,
+            wakeup: Arc::new(Notify::new()),
+            connections: Vec::new(),
+            lookups: Vec::new(),
+            next_local_port: FIRST_EPHEMERAL_PORT,
+            started,
+            scratch: vec![0u8; PACKET_BUFFER_SIZE],
+            handshake_completed: false,
+            peer_unreachable: false,
+        };
+        (stack, StackHandle { requests: requests_tx })
+    }
+
+    pub async fn run(mut self, mut shutdown_rx: watch::Receiver<()>) -> Result<()> {
+        let mut datagram = vec![0u8; PACKET_BUFFER_SIZE];
+        let mut timers = tokio::time::interval(TIMER_TICK_INTERVAL);
+        timers.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        info!("[Proxy] Starting network stack...");
+
+        if let TunnResult::WriteToNetwork(packet) = self.tunn.encapsulate(&[], &mut self.scratch)
+            && let Err(error) = self.udp.send(packet).await
+        {
+            warn!("[Proxy] Initial handshake send failed: {}", error);
+        }
+
+        loop {
+            let mut progress = self.poll_interface();
+            progress |= self.service_lookups();
+            progress |= self.service_connections();
+            if progress {
+                self.poll_interface();
+            }
+            self.flush_outbound().await;
+
+            let delay = if progress {
+                Duration::ZERO
+            } else {
+                self.interface
+                    .poll_delay(self.now(), &self.sockets)
+                    .map(|delay| Duration::from_micros(delay.total_micros()))
+                    .unwrap_or(TIMER_TICK_INTERVAL)
+            };
+
+            let event = tokio::select! {
+                _ = shutdown_rx.changed() => Event::Shutdown,
+                result = self.udp.recv(&mut datagram) => Event::Datagram(result),
+                Some(request) = self.requests.recv() => Event::Request(request),
+                _ = timers.tick() => Event::Timers,
+                _ = self.wakeup.notified() => Event::Wakeup,
+                _ = tokio::time::sleep(delay) => Event::Wakeup,
+            };
+
+            match event {
+                Event::Shutdown => {
+                    info!("[Proxy] Shutdown requested.");
+                    break;
+                }
+                Event::Datagram(Ok(length)) => {
+                    self.receive_datagram(&datagram[..length]).await;
+                    // Drain whatever else is already queued on the socket.
+                    while let Ok(length) = self.udp.try_recv(&mut datagram) {
+                        self.receive_datagram(&datagram[..length]).await;
+                    }
+                }
+                // Transient errors such as ICMP port unreachable surface here.
+                Event::Datagram(Err(error)) => debug!("[Proxy] UDP receive failed: {}", error),
+                Event::Request(request) => self.handle_request(request),
+                Event::Timers => self.update_timers().await?,
+                Event::Wakeup => {}
+            }
+        }
+
+        for connection in &self.connections {
+            self.sockets.get_mut::<tcp::Socket>(connection.handle).abort();
+        }
+        self.poll_interface();
+        self.flush_outbound().await;
+        info!("[Proxy] Clean shutdown.");
+        Ok(())
+    }
+
+    fn now(&self) -> smoltcp::time::Instant {
+        smoltcp::time::Instant::from_micros(self.started.elapsed().as_micros() as i64)
+    }
+
+    fn poll_interface(&mut self) -> bool {
+        let now = self.now();
+        let result = self.interface.poll(now, &mut self.device, &mut self.sockets);
+        result == smoltcp::iface::PollResult::SocketStateChanged
+    }
+
+    async fn receive_datagram(&mut self, datagram: &[u8]) {
+        match self.tunn.decapsulate(None, datagram, &mut self.scratch) {
+            TunnResult::WriteToTunnelV4(packet, _) | TunnResult::WriteToTunnelV6(packet, _) => {
+                self.device.inbound.push_back(packet.to_vec());
+            }
+            TunnResult::WriteToNetwork(packet) => {
+                if let Err(error) = self.udp.send(packet).await {
+                    warn!("[Proxy] UDP send failed: {}", error);
+                }
+                // A completed handshake can release packets queued while the
+                // session was being negotiated.
+                while let TunnResult::WriteToNetwork(packet) =
+                    self.tunn.decapsulate(None, &[], &mut self.scratch)
+                {
+                    if let Err(error) = self.udp.send(packet).await {
+                        warn!("[Proxy] UDP send failed: {}", error);
+                    }
+                }
+            }
+            TunnResult::Done => {}
+            TunnResult::Err(error) => debug!("[Proxy] decapsulate error: {:?}", error),
+        }
+    }
+
+    async fn flush_outbound(&mut self) {
+        while let Some(packet) = self.device.outbound.pop_front() {
+            match self.tunn.encapsulate(&packet, &mut self.scratch) {
+                TunnResult::WriteToNetwork(packet) => {
+                    if let Err(error) = self.udp.send(packet).await {
+                        warn!("[Proxy] UDP send failed: {}", error);
+                    }
+                }
+                TunnResult::Err(error) => debug!("[Proxy] encapsulate error: {:?}", error),
+                _ => {}
+            }
+        }
+    }
+
+    async fn update_timers(&mut self) -> Result<()> {
+        match self.tunn.update_timers(&mut self.scratch) {
+            TunnResult::WriteToNetwork(packet) => {
+                self.udp
+                    .send(packet)
+                    .await
+                    .map_err(|error| SystemError::TunnelIoFailed {
+                        reason: error.to_string(),
+                    })?;
+            }
+            TunnResult::Err(WireGuardError::ConnectionExpired) if !self.peer_unreachable => {
+                warn!("[Proxy] Handshake retries exhausted; peer unreachable.");
+                self.peer_unreachable = true;
+            }
+            TunnResult::Err(WireGuardError::ConnectionExpired) => {}
+            TunnResult::Err(error) => error!("[Proxy] timer error: {:?}", error),
+            _ => {}
+        }
+
+        if self.tunn.time_since_last_handshake().is_some() {
+            if !self.handshake_completed {
+                info!("[Proxy] Handshake completed.");
+            }
+            self.handshake_completed = true;
+            self.peer_unreachable = false;
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: ConnectRequest) {
+        let ConnectRequest { target, reply } = request;
+        match target {
+            Target::Address(remote) => self.open_connection(remote, reply),
+            Target::Domain(name, port) => match name.parse::<IpAddr>() {
+                Ok(ip) => self.open_connection(SocketAddr::new(ip, port), reply),
+                Err(_) => self.start_lookup(name, port, DnsQueryType::A, reply),
+            },
+        }
+    }
+
+    fn start_lookup(
+        &mut self,
+        name: String,
+        port: u16,
+        query_type: DnsQueryType,
+        reply: oneshot::Sender<Result<StackConnection>>,
+    ) {
+        let Some(dns_socket) = self.dns_socket else {
+            let _ = reply.send(Err(connect_failed(&name, "no DNS servers configured")));
+            return;
+        };
+        let result = self.sockets.get_mut::<dns::Socket>(dns_socket).start_query(
+            self.interface.context(),
+            &name,
+            query_type,
+        );
+        match result {
+            Ok(query) => self.lookups.push(Lookup {
+                query,
+                query_type,
+                name,
+                port,
+                reply,
+            }),
+            Err(error) => {
+                let _ = reply.send(Err(connect_failed(&name, error)));
+            }
+        }
+    }
+
+    /// Connects finished lookups, falling back from A to AAAA records.
+    fn service_lookups(&mut self) -> bool {
+        let Some(dns_socket) = self.dns_socket else {
+            return false;
+        };
+
+        let mut finished = Vec::new();
+        let mut index = 0;
+        while index < self.lookups.len() {
+            let query = self.lookups[index].query;
+            let result = self
+                .sockets
+                .get_mut::<dns::Socket>(dns_socket)
+                .get_query_result(query);
+            match result {
+                Err(dns::GetQueryResultError::Pending) => index += 1,
+                Ok(addresses) => {
+                    let lookup = self.lookups.swap_remove(index);
+                    finished.push((lookup, addresses.first().copied().map(IpAddr::from)));
+                }
+                Err(dns::GetQueryResultError::Failed) => {
+                    let lookup = self.lookups.swap_remove(index);
+                    finished.push((lookup, None));
+                }
+            }
+        }
+
+        let progress = !finished.is_empty();
+        for (lookup, address) in finished {
+            match address {
+                Some(ip) => {
+                    debug!("[Proxy] Resolved {} to {}", lookup.name, ip);
+                    self.open_connection(SocketAddr::new(ip, lookup.port), lookup.reply);
+                }
+                None if lookup.query_type == DnsQueryType::A => {
+                    self.start_lookup(lookup.name, lookup.port, DnsQueryType::Aaaa, lookup.reply);
+                }
+                None => {
+                    let error = connect_failed(&lookup.name, "name did not resolve");
+                    let _ = lookup.reply.send(Err(error));
+                }
+            }
+        }
+        progress
+    }
+
+    fn open_connection(
+        &mut self,
+        remote: SocketAddr,
+        reply: oneshot::Sender<Result<StackConnection>>,
+    ) {
+        let mut socket = tcp::Socket::new(
+            tcp::SocketBuffer::new(vec![0u8; TCP_BUFFER_SIZE]),
+            tcp::SocketBuffer::new(vec![0u8; TCP_BUFFER_SIZE]),
+        );
+        socket.set_timeout(Some(smoltcp::time::Duration::from_secs(
+            CONNECT_TIMEOUT.as_secs(),
+        )));
+
+        let local_port = self.allocate_local_port();
+        if let Err(error) = socket.connect(self.interface.context(), remote, local_port) {
+            let _ = reply.send(Err(connect_failed(remote, error)));
+            return;
+        }
+        let handle = self.sockets.add(socket);
+
+        let (outgoing_tx, outgoing) = mpsc::channel(CHANNEL_CAPACITY);
+        let (incoming, incoming_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let client = StackConnection {
+            outgoing: outgoing_tx,
+            incoming: incoming_rx,
+            wakeup: self.wakeup.clone(),
+        };
+        self.connections.push(Connection {
+            handle,
+            remote,
+            pending_reply: Some((reply, client)),
+            outgoing,
+            incoming: Some(incoming),
+            unsent: Vec::new(),
+            client_finished: false,
+        });
+    }
+
+    fn allocate_local_port(&mut self) -> u16 {
+        let port = self.next_local_port;
+        self.next_local_port = self.next_local_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+        port
+    }
+
+    /// Moves data between each socket and its client and drops connections
+    /// that have fully closed.
+    fn service_connections(&mut self) -> bool {
+        let mut progress = false;
+        let sockets = &mut self.sockets;
+
+        self.connections.retain_mut(|connection| {
+            let socket = sockets.get_mut::<tcp::Socket>(connection.handle);
+
+            if let Some((reply, client)) = connection.pending_reply.take() {
+                // Established, or already half-closed by the remote.
+                if socket.may_send() {
+                    debug!("[Proxy] Connected to {}", connection.remote);
+                    if reply.send(Ok(client)).is_err() {
+                        socket.abort();
+                    }
+                    progress = true;
+                } else if socket.state() == tcp::State::Closed {
+                    let error = connect_failed(connection.remote, "refused or timed out");
+                    let _ = reply.send(Err(error));
+                    sockets.remove(connection.handle);
+                    return false;
+                } else {
+                    connection.pending_reply = Some((reply, client));
+                    return true;
+                }
+            }
+
+            // Client to remote.
+            while !connection.client_finished && socket.can_send() {
+                if connection.unsent.is_empty() {
+                    match connection.outgoing.try_recv() {
+                        Ok(chunk) => connection.unsent = chunk,
+                        Err(mpsc::error::TryRecvError::Empty) => break,
+                        Err(mpsc::error::TryRecvError::Disconnected) => {
+                            connection.client_finished = true;
+                            socket.close();
+                            break;
+                        }
+                    }
+                }
+                let sent = socket.send_slice(&connection.unsent).unwrap_or(0);
+                if sent == 0 {
+                    break;
+                }
+                connection.unsent.drain(..sent);
+                progress = true;
+            }
+
+            // Remote to client.
+            if let Some(incoming) = &connection.incoming {
+                while socket.can_recv() {
+                    let permit = match incoming.try_reserve() {
+                        Ok(permit) => permit,
+                        Err(mpsc::error::TrySendError::Full(())) => break,
+                        Err(mpsc::error::TrySendError::Closed(())) => {
+                            socket.abort();
+                            break;
+                        }
+                    };
+                    let mut chunk = vec![0u8; READ_CHUNK_SIZE.min(socket.recv_queue())];
+                    let length = socket.recv_slice(&mut chunk).unwrap_or(0);
+                    chunk.truncate(length);
+                    permit.send(chunk);
+                    progress = true;
+                }
+                if incoming.is_closed() {
+                    socket.abort();
+                }
+                if !socket.may_recv() && !socket.can_recv() {
+                    // Closing the channel tells the client the remote is done.
+                    connection.incoming = None;
+                }
+            }
+
+            if socket.state() == tcp::State::Closed {
+                debug!("[Proxy] Closed connection to {}", connection.remote);
+                sockets.remove(connection.handle);
+                return false;
+            }
+            true
+        });
+
+        progress
+    }
+}
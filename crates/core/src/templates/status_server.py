@@ -1,12 +1,122 @@
+import hashlib
+import hmac
+import json
+import os
 import socket
+import subprocess
+
+# Replies are MACed over the client's nonce and the status, so they can be
+# neither forged nor replayed without the per-spawn status key.
+STATUS_KEY = bytes.fromhex(os.environ['BYOCVPN_STATUS_KEY'])
+# open | client-only | shutdown: what happens once the client has connected.
+ACCESS = os.environ.get('BYOCVPN_STATUS_ACCESS', 'open')
+STATUS_VERSION = 2
+MAX_NONCE_SIZE = 128
+
+
+def run(command):
+    try:
+        return subprocess.run(command, capture_output=True, text=True, timeout=2).stdout
+    except Exception:
+        return ''
+
+
+def client_ip():
+    # The peer's endpoint is only known after it has completed a handshake.
+    for line in run(['wg', 'show', 'wg0', 'endpoints']).splitlines():
+        fields = line.split()
+        if len(fields) == 2 and fields[1] != '(none)':
+            return fields[1].rsplit(':', 1)[0].strip('[]')
+    return None
+
+
+def read_stages():
+    # The startup script appends "<stage> <unix time>" as it enters each stage.
+    stages = []
+    try:
+        with open('/tmp/byocvpn-stages') as stages_file:
+            for line in stages_file:
+                fields = line.split()
+                if len(fields) == 2:
+                    stages.append({'name': fields[0], 'started_at': int(fields[1])})
+    except (OSError, ValueError):
+        pass
+    return stages
+
+
+def wireguard_stats():
+    transfer = run(['wg', 'show', 'wg0', 'transfer']).split()
+    handshakes = run(['wg', 'show', 'wg0', 'latest-handshakes']).split()
+    if len(transfer) < 3:
+        return None
+    latest_handshake = int(handshakes[1]) if len(handshakes) >= 2 else 0
+    return {
+        'rx_bytes': int(transfer[1]),
+        'tx_bytes': int(transfer[2]),
+        'latest_handshake': latest_handshake or None,
+    }
+
+
+def status_document():
+    try:
+        with open('/tmp/byocvpn-status') as status_file:
+            document = json.load(status_file)
+    except (OSError, ValueError):
+        document = {'status': 'pending'}
+    stages = read_stages()
+    document.update(
+        version=STATUS_VERSION,
+        stage=stages[-1]['name'] if stages else None,
+        stages=stages,
+        wireguard=wireguard_stats(),
+    )
+    try:
+        with open('/proc/uptime') as uptime_file:
+            document['uptime_seconds'] = float(uptime_file.read().split()[0])
+        with open('/proc/loadavg') as load_file:
+            document['load_average'] = [float(load) for load in load_file.read().split()[:3]]
+    except (OSError, ValueError):
+        pass
+    return json.dumps(document).encode()
+
+
+def read_nonce(connection):
+    nonce = b''
+    while not nonce.endswith(b'\n') and len(nonce) < MAX_NONCE_SIZE:
+        chunk = connection.recv(MAX_NONCE_SIZE - len(nonce))
+        if not chunk:
+            break
+        nonce += chunk
+    return nonce.strip()
+
+
 server = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
 server.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
 server.bind(('', 51820))
 server.listen(10)
+server.settimeout(10)
 while True:
-    connection, _ = server.accept()
+    allowed_ip = None
+    if ACCESS != 'open':
+        allowed_ip = client_ip()
+        if allowed_ip is not None and ACCESS == 'shutdown':
+            break
+    try:
+        connection, (peer_ip, _) = server.accept()
+    except socket.timeout:
+        continue
     try:
-        with open('/tmp/byocvpn-status', 'rb') as status_file:
-            connection.sendall(status_file.read())
+        if allowed_ip is not None and peer_ip != allowed_ip:
+            continue
+        connection.settimeout(3)
+        nonce = read_nonce(connection)
+        if not nonce:
+            continue
+        status = status_document()
+        mac = hmac.new(STATUS_KEY, nonce + b'\n' + status, hashlib.sha256).hexdigest()
+        connection.sendall(status + b'\n' + mac.encode())
+    except OSError:
+        pass
     finally:
         connection.close()
+server.close()
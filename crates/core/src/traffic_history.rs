@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cloud_provider::CloudProviderName;
+
+/// Samples newer than this are kept at one-second resolution.
+const SECOND_RETENTION: u64 = 60 * 60;
+/// Samples newer than this (and older than an hour) are kept per minute;
+/// anything older is folded into hourly buckets.
+const MINUTE_RETENTION: u64 = 7 * 24 * 60 * 60;
+
+/// Bytes moved during the bucket starting at `timestamp` (Unix seconds).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficSample {
+    pub timestamp: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Traffic recorded for one connection, downsampled as it ages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionHistory {
+    pub instance_id: String,
+    pub provider: CloudProviderName,
+    pub region: String,
+    pub connected_at: u64,
+    pub disconnected_at: Option<u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub seconds: VecDeque<TrafficSample>,
+    pub minutes: VecDeque<TrafficSample>,
+    pub hours: VecDeque<TrafficSample>,
+}
+
+impl SessionHistory {
+    pub fn new(
+        instance_id: String,
+        provider: CloudProviderName,
+        region: String,
+        connected_at: u64,
+    ) -> Self {
+        Self {
+            instance_id,
+            provider,
+            region,
+            connected_at,
+            disconnected_at: None,
+            bytes_sent: 0,
+            bytes_received: 0,
+            seconds: VecDeque::new(),
+            minutes: VecDeque::new(),
+            hours: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, sample: TrafficSample) {
+        self.bytes_sent += sample.bytes_sent;
+        self.bytes_received += sample.bytes_received;
+        add_to_bucket(&mut self.seconds, sample, 1);
+        self.compact(sample.timestamp);
+    }
+
+    /// Stamps the end of the session and downsamples what it recorded.
+    pub fn finish(&mut self, disconnected_at: u64) {
+        self.disconnected_at = Some(disconnected_at);
+        self.compact(disconnected_at);
+    }
+
+    /// Start of the newest bucket, or the connect time if nothing was
+    /// recorded.
+    pub fn last_sample_at(&self) -> u64 {
+        [&self.seconds, &self.minutes, &self.hours]
+            .into_iter()
+            .filter_map(|buckets| buckets.back().map(|sample| sample.timestamp))
+            .max()
+            .unwrap_or(self.connected_at)
+    }
+
+    /// Whether any part of the session falls at or after `since`.
+    pub fn is_active_since(&self, since: u64) -> bool {
+        self.disconnected_at
+            .is_none_or(|disconnected_at| disconnected_at >= since)
+    }
+
+    /// Folds samples that have aged past their resolution into coarser
+    /// buckets. Returns whether anything moved.
+    pub fn compact(&mut self, now: u64) -> bool {
+        let bucket_counts = (self.seconds.len(), self.minutes.len());
+        let second_cutoff = now.saturating_sub(SECOND_RETENTION);
+        while let Some(sample) = self.seconds.front().copied() {
+            if sample.timestamp >= second_cutoff {
+                break;
+            }
+            self.seconds.pop_front();
+            add_to_bucket(&mut self.minutes, sample, 60);
+        }
+
+        let minute_cutoff = now.saturating_sub(MINUTE_RETENTION);
+        while let Some(sample) = self.minutes.front().copied() {
+            if sample.timestamp >= minute_cutoff {
+                break;
+            }
+            self.minutes.pop_front();
+            add_to_bucket(&mut self.hours, sample, 60 * 60);
+        }
+
+        (self.seconds.len(), self.minutes.len()) != bucket_counts
+    }
+}
+
+fn add_to_bucket(buckets: &mut VecDeque<TrafficSample>, sample: TrafficSample, width: u64) {
+    let timestamp = sample.timestamp - sample.timestamp % width;
+    match buckets.back_mut() {
+        Some(bucket) if bucket.timestamp == timestamp => {
+            bucket.bytes_sent += sample.bytes_sent;
+            bucket.bytes_received += sample.bytes_received;
+        }
+        _ => buckets.push_back(TrafficSample {
+            timestamp,
+            ..sample
+        }),
+    }
+}
@@ -1,19 +1,56 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use boringtun::noise::{Tunn, TunnResult};
+use boringtun::noise::{Tunn, TunnResult, errors::WireGuardError};
 use serde::{Deserialize, Serialize};
 
 use crate::cloud_provider::CloudProviderName;
 use tokio::{
     net::UdpSocket,
     sync::{RwLock, watch},
-    time::{Duration, Instant},
+    task::{JoinError, JoinHandle},
+    time::{Duration, Instant, MissedTickBehavior},
 };
 use tun_rs::AsyncDevice;
 
-use crate::error::{Result, SystemError};
+use crate::{
+    error::{Result, SystemError},
+    udp_batch::{self, RecvBatch},
+};
 use log::*;
 
+/// How often boringtun's timers are driven, matching the interval used by
+/// boringtun's own device implementation.
+const TIMER_TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A session older than this is rejected by WireGuard (`REJECT_AFTER_TIME`).
+const SESSION_REJECT_AFTER: Duration = Duration::from_secs(180);
+
+/// Room for a jumbo-frame MTU plus WireGuard's framing overhead.
+const PACKET_BUFFER_SIZE: usize = 16 * 1024;
+
+const HANDSHAKE_INITIATION_MESSAGE_TYPE: u8 = 1;
+const HANDSHAKE_INITIATION_MESSAGE_SIZE: usize = 148;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PeerState {
+    /// No valid session yet, a handshake is in flight.
+    #[default]
+    Handshaking,
+    /// A handshake completed within the session lifetime.
+    Established,
+    /// Handshake retries gave up without an answer from the server.
+    Unreachable,
+    /// The daemon is rebuilding the tunnel after a failure or a resume.
+    Reconnecting,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TunnelMetrics {
@@ -23,6 +60,17 @@ pub struct TunnelMetrics {
     pub packets_received: u64,
     pub upload_rate: u64,
     pub download_rate: u64,
+    /// Unix timestamp (seconds) of the last completed handshake.
+    #[serde(default)]
+    pub last_handshake_at: Option<u64>,
+    /// Handshake initiations sent since the last completed handshake.
+    #[serde(default)]
+    pub handshake_attempts: u64,
+    #[serde(default)]
+    pub peer_state: PeerState,
+    /// Times the daemon rebuilt the tunnel since connecting.
+    #[serde(default)]
+    pub reconnect_count: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,14 +92,83 @@ pub struct VpnStatus {
     pub connected_at: Option<u64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub connection_error: Option<String>,
+    #[serde(default)]
+    pub reconnecting: bool,
+    /// Which WireGuard implementation carries the tunnel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<TunnelBackend>,
+    /// MTU of the tunnel interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    /// Set when the path MTU probe failed and `mtu` is the fallback.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu_probe_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TunnelBackend {
+    /// boringtun behind a TUN device, driven by the daemon.
+    #[default]
+    Userspace,
+    /// The Linux kernel's `wireguard` module, configured over netlink.
+    Kernel,
+}
+
+/// Traffic counters bumped on the hot path without locking. They are copied
+/// into `Tunnel::metrics` on every timer tick.
+#[derive(Debug, Default)]
+struct TrafficCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    packets_sent: AtomicU64,
+    packets_received: AtomicU64,
+    handshake_attempts: AtomicU64,
+}
+
+impl TrafficCounters {
+    fn record_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_received(&self, bytes: usize) {
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_handshake_initiation(&self, packet: &[u8]) {
+        if packet.len() == HANDSHAKE_INITIATION_MESSAGE_SIZE
+            && packet[0] == HANDSHAKE_INITIATION_MESSAGE_TYPE
+        {
+            self.handshake_attempts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Aborts the data plane tasks if `Tunnel::run` is dropped mid-flight, e.g.
+/// when the supervisor gives up on the session.
+struct DataPlaneTasks {
+    outbound: JoinHandle<Result<()>>,
+    inbound: JoinHandle<Result<()>>,
+}
+
+impl Drop for DataPlaneTasks {
+    fn drop(&mut self) {
+        self.outbound.abort();
+        self.inbound.abort();
+    }
 }
 
 pub struct Tunnel {
-    tun: AsyncDevice,
-    udp: UdpSocket,
-    wg: Tunn,
+    tun: Arc<AsyncDevice>,
+    udp: Arc<UdpSocket>,
+    wg: Arc<Mutex<Tunn>>,
     shutdown_rx: watch::Receiver<()>,
     pub metrics: Arc<RwLock<TunnelMetrics>>,
+    counters: Arc<TrafficCounters>,
+    last_handshake: Option<Instant>,
+    peer_unreachable: bool,
 }
 
 impl Tunnel {
@@ -62,21 +179,67 @@ impl Tunnel {
         shutdown_rx: watch::Receiver<()>,
     ) -> Self {
         Tunnel {
-            tun,
-            udp,
-            wg,
+            tun: Arc::new(tun),
+            udp: Arc::new(udp),
+            wg: Arc::new(Mutex::new(wg)),
             shutdown_rx,
             metrics: Arc::new(RwLock::new(TunnelMetrics::default())),
+            counters: Arc::new(TrafficCounters::default()),
+            last_handshake: None,
+            peer_unreachable: false,
         }
     }
 
+    /// Swaps in a freshly bound socket and WireGuard state while keeping the
+    /// TUN device, so routes and the kill switch stay in place.
+    pub fn reset(&mut self, udp: UdpSocket, wg: Tunn) {
+        self.udp = Arc::new(udp);
+        self.wg = Arc::new(Mutex::new(wg));
+        self.last_handshake = None;
+        self.peer_unreachable = false;
+    }
+
+    /// Runs encryption and decryption as separate tasks so the two directions
+    /// do not wait on each other; this task only drives the WireGuard timers.
     pub async fn run(&mut self) -> Result<()> {
-        let mut tun_buf = [0u8; 1500];
-        let mut udp_buf = [0u8; 1500];
-        let mut out_buf = [0u8; 1500];
-        let mut last_keepalive = Instant::now();
+        let mut out_buf = vec![0u8; PACKET_BUFFER_SIZE];
+        let mut timers = tokio::time::interval(TIMER_TICK_INTERVAL);
+        timers.set_missed_tick_behavior(MissedTickBehavior::Delay);
         info!("[Tunnel] Starting tunnel...");
 
+        if udp_batch::enable_gro(&self.udp) {
+            debug!("[Tunnel] UDP GRO enabled.");
+        }
+
+        let handshake = {
+            let mut wg = lock_tunn(&self.wg)?;
+            match wg.encapsulate(&[], &mut out_buf) {
+                TunnResult::WriteToNetwork(packet) => Some(packet.len()),
+                _ => None,
+            }
+        };
+        if let Some(length) = handshake {
+            self.counters.record_handshake_initiation(&out_buf[..length]);
+            if let Err(error) = self.udp.send(&out_buf[..length]).await {
+                warn!("[Tunnel] Initial handshake send failed: {}", error);
+            }
+        }
+
+        let mut tasks = DataPlaneTasks {
+            outbound: tokio::spawn(run_outbound(
+                self.tun.clone(),
+                self.udp.clone(),
+                self.wg.clone(),
+                self.counters.clone(),
+            )),
+            inbound: tokio::spawn(run_inbound(
+                self.tun.clone(),
+                self.udp.clone(),
+                self.wg.clone(),
+                self.counters.clone(),
+            )),
+        };
+
         loop {
             tokio::select! {
                 _ = self.shutdown_rx.changed() => {
@@ -84,76 +247,239 @@ impl Tunnel {
                     break;
                 }
 
-                result = self.tun.recv(&mut tun_buf) => {
-                    match result {
-                        Ok(n) => {
-                            match self.wg.encapsulate(&tun_buf[..n], &mut out_buf) {
-                                TunnResult::WriteToNetwork(packet) => {
-                                    match self.udp.send(packet).await {
-                                        Ok(sent) => {
-                                            let mut metrics = self.metrics.write().await;
-                                            metrics.bytes_sent += sent as u64;
-                                            metrics.packets_sent += 1;
-                                        }
-                                        Err(error) => {
-                                            warn!("[Tunnel] UDP send failed: {}", error);
-                                        }
-                                    }
-                                },
-                                TunnResult::Done => {},
-                                TunnResult::Err(error) => {
-                                    error!("encapsulate error: {:?}", error);
-                                },
-                                _ => {}
-                            }
-                        }
-                        Err(error) => {
-                            error!("[Tunnel] TUN device read failed: {}", error);
-                            return Err(SystemError::TunnelIoFailed { reason: error.to_string() }.into());
-                        }
-                    }
+                result = &mut tasks.outbound => {
+                    return data_plane_exit("outbound", result);
                 }
 
-                Ok((n, src)) = self.udp.recv_from(&mut udp_buf) => {
-                    {
-                        let mut metrics = self.metrics.write().await;
-                        metrics.bytes_received += n as u64;
-                        metrics.packets_received += 1;
-                    }
+                result = &mut tasks.inbound => {
+                    return data_plane_exit("inbound", result);
+                }
 
-                    match self.wg.decapsulate(Some(src.ip()), &udp_buf[..n], &mut out_buf) {
-                        TunnResult::WriteToTunnelV4(packet, _src_ip) => {
-                            self.tun.send(packet).await.map_err(|error| SystemError::TunnelIoFailed { reason: error.to_string() })?;
-                        },
-                        TunnResult::WriteToTunnelV6(packet, _src_ip) => {
-                            self.tun.send(packet).await.map_err(|error| SystemError::TunnelIoFailed { reason: error.to_string() })?;
-                        },
+                _ = timers.tick() => {
+                    let result = lock_tunn(&self.wg)?.update_timers(&mut out_buf);
+                    match result {
                         TunnResult::WriteToNetwork(packet) => {
+                            self.counters.record_handshake_initiation(packet);
                             self.udp.send(packet).await.map_err(|error| SystemError::TunnelIoFailed { reason: error.to_string() })?;
                         },
-                        TunnResult::Done => {},
+                        TunnResult::Err(WireGuardError::ConnectionExpired) if !self.peer_unreachable => {
+                            warn!("[Tunnel] Handshake retries exhausted; peer unreachable.");
+                            self.peer_unreachable = true;
+                        },
+                        TunnResult::Err(WireGuardError::ConnectionExpired) => {},
                         TunnResult::Err(error) => {
-                            error!("decapsulate error: {:?}", error);
+                            error!("timer error: {:?}", error);
                         },
+                        _ => {}
+                    }
+                    self.update_handshake_state().await?;
+                }
+            }
+        }
+
+        info!("[Tunnel] Clean shutdown.");
+        Ok(())
+    }
+
+    async fn update_handshake_state(&mut self) -> Result<()> {
+        let handshake_age = lock_tunn(&self.wg)?.time_since_last_handshake();
+        let now = Instant::now();
+
+        let mut last_handshake_at = None;
+        if let Some(age) = handshake_age {
+            let handshake_instant = now - age;
+            let is_new_handshake = self.last_handshake.is_none_or(|previous| {
+                handshake_instant > previous + Duration::from_secs(1)
+            });
+            if is_new_handshake {
+                debug!("[Tunnel] Handshake completed.");
+                self.last_handshake = Some(handshake_instant);
+                self.peer_unreachable = false;
+                self.counters.handshake_attempts.store(0, Ordering::Relaxed);
+                last_handshake_at = SystemTime::now()
+                    .checked_sub(age)
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs());
+            }
+        }
+
+        let peer_state = if self.peer_unreachable {
+            PeerState::Unreachable
+        } else if handshake_age.is_some_and(|age| age < SESSION_REJECT_AFTER) {
+            PeerState::Established
+        } else {
+            PeerState::Handshaking
+        };
+
+        let mut metrics = self.metrics.write().await;
+        metrics.bytes_sent = self.counters.bytes_sent.load(Ordering::Relaxed);
+        metrics.bytes_received = self.counters.bytes_received.load(Ordering::Relaxed);
+        metrics.packets_sent = self.counters.packets_sent.load(Ordering::Relaxed);
+        metrics.packets_received = self.counters.packets_received.load(Ordering::Relaxed);
+        metrics.handshake_attempts = self.counters.handshake_attempts.load(Ordering::Relaxed);
+        if last_handshake_at.is_some() {
+            metrics.last_handshake_at = last_handshake_at;
+        }
+        if metrics.peer_state != peer_state {
+            info!("[Tunnel] Peer state changed: {:?} -> {:?}", metrics.peer_state, peer_state);
+            metrics.peer_state = peer_state;
+        }
+        Ok(())
+    }
+}
+
+fn lock_tunn(wg: &Mutex<Tunn>) -> Result<MutexGuard<'_, Tunn>> {
+    wg.lock()
+        .map_err(|_| SystemError::MutexPoisoned("Tunn".to_string()).into())
+}
+
+fn data_plane_exit(
+    direction: &str,
+    result: std::result::Result<Result<()>, JoinError>,
+) -> Result<()> {
+    match result {
+        Ok(Ok(())) => Err(SystemError::TunnelIoFailed {
+            reason: format!("{} data plane stopped", direction),
+        }
+        .into()),
+        Ok(Err(error)) => Err(error),
+        Err(error) => Err(SystemError::TunnelIoFailed {
+            reason: format!("{} data plane task failed: {}", direction, error),
+        }
+        .into()),
+    }
+}
+
+/// Reads packets from the TUN device, encrypts them and sends them to the
+/// peer. Packets already queued on the device are drained into one batch.
+async fn run_outbound(
+    tun: Arc<AsyncDevice>,
+    udp: Arc<UdpSocket>,
+    wg: Arc<Mutex<Tunn>>,
+    counters: Arc<TrafficCounters>,
+) -> Result<()> {
+    let mut tun_bufs = vec![vec![0u8; PACKET_BUFFER_SIZE]; udp_batch::BATCH_SIZE];
+    let mut out_bufs = vec![vec![0u8; PACKET_BUFFER_SIZE]; udp_batch::BATCH_SIZE];
+    let mut lengths = [0usize; udp_batch::BATCH_SIZE];
+
+    loop {
+        let mut count = 0;
+        lengths[0] = tun.recv(&mut tun_bufs[0]).await.map_err(|error| {
+            error!("[Tunnel] TUN device read failed: {}", error);
+            SystemError::TunnelIoFailed { reason: error.to_string() }
+        })?;
+        count += 1;
+        while count < udp_batch::BATCH_SIZE {
+            match tun.try_recv(&mut tun_bufs[count]) {
+                Ok(length) => {
+                    lengths[count] = length;
+                    count += 1;
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    error!("[Tunnel] TUN device read failed: {}", error);
+                    return Err(SystemError::TunnelIoFailed { reason: error.to_string() }.into());
+                }
+            }
+        }
+
+        let mut packets: [&[u8]; udp_batch::BATCH_SIZE] = [&[]; udp_batch::BATCH_SIZE];
+        let mut packet_count = 0;
+        {
+            let mut wg = lock_tunn(&wg)?;
+            for ((tun_buf, length), out_buf) in
+                tun_bufs.iter().zip(lengths).zip(out_bufs.iter_mut()).take(count)
+            {
+                match wg.encapsulate(&tun_buf[..length], out_buf) {
+                    TunnResult::WriteToNetwork(packet) => {
+                        counters.record_handshake_initiation(packet);
+                        packets[packet_count] = packet;
+                        packet_count += 1;
                     }
+                    TunnResult::Err(error) => error!("encapsulate error: {:?}", error),
+                    _ => {}
                 }
+            }
+        }
+
+        let packets = &packets[..packet_count];
+        match udp_batch::send_batch(&udp, packets).await {
+            Ok(()) => {
+                for packet in packets {
+                    counters.record_sent(packet.len());
+                }
+            }
+            Err(error) => warn!("[Tunnel] UDP send failed: {}", error),
+        }
+    }
+}
 
-                _ = tokio::time::sleep(Duration::from_secs(15)) => {
-                    if last_keepalive.elapsed() >= Duration::from_secs(15) {
+/// Receives batches of datagrams from the peer, decrypts them and writes the
+/// packets to the TUN device.
+async fn run_inbound(
+    tun: Arc<AsyncDevice>,
+    udp: Arc<UdpSocket>,
+    wg: Arc<Mutex<Tunn>>,
+    counters: Arc<TrafficCounters>,
+) -> Result<()> {
+    let mut batch = RecvBatch::new();
+    let mut out_buf = vec![0u8; PACKET_BUFFER_SIZE];
+    let peer_ip = udp.peer_addr().ok().map(|address| address.ip());
 
-                        match self.wg.encapsulate(&[], &mut out_buf) {
-                            TunnResult::WriteToNetwork(packet) => {
-                                self.udp.send(packet).await.map_err(|error| SystemError::TunnelIoFailed { reason: error.to_string() })?;
-                                last_keepalive = Instant::now();
-                            },
-                            _ => {}
-                        }
+    loop {
+        if let Err(error) = batch.recv(&udp).await {
+            match error.kind() {
+                // ICMP port unreachable surfaces as ConnectionRefused and is
+                // expected while the server is still booting.
+                std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::WouldBlock
+                | std::io::ErrorKind::Interrupted => {
+                    debug!("[Tunnel] UDP receive failed: {}", error);
+                    continue;
+                }
+                // Anything else will keep failing; let the supervisor rebuild
+                // the socket instead of spinning on it.
+                _ => {
+                    error!("[Tunnel] UDP receive failed: {}", error);
+                    return Err(SystemError::TunnelIoFailed {
+                        reason: error.to_string(),
                     }
+                    .into());
                 }
             }
         }
 
-        info!("[Tunnel] Clean shutdown.");
-        Ok(())
+        for datagram in batch.datagrams() {
+            counters.record_received(datagram.len());
+
+            let result = lock_tunn(&wg)?.decapsulate(peer_ip, datagram, &mut out_buf);
+            match result {
+                TunnResult::WriteToTunnelV4(packet, _) | TunnResult::WriteToTunnelV6(packet, _) => {
+                    tun.send(packet).await.map_err(|error| SystemError::TunnelIoFailed {
+                        reason: error.to_string(),
+                    })?;
+                }
+                TunnResult::WriteToNetwork(packet) => {
+                    udp.send(packet).await.map_err(|error| SystemError::TunnelIoFailed {
+                        reason: error.to_string(),
+                    })?;
+                    // A completed handshake can release packets queued while
+                    // the session was being negotiated.
+                    loop {
+                        let result = lock_tunn(&wg)?.decapsulate(None, &[], &mut out_buf);
+                        let TunnResult::WriteToNetwork(packet) = result else {
+                            break;
+                        };
+                        udp.send(packet).await.map_err(|error| SystemError::TunnelIoFailed {
+                            reason: error.to_string(),
+                        })?;
+                    }
+                }
+                TunnResult::Done => {}
+                TunnResult::Err(error) => {
+                    error!("decapsulate error: {:?}", error);
+                }
+            }
+        }
     }
 }
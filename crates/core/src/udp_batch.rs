@@ -0,0 +1,220 @@
+//! Moves several datagrams per syscall on the tunnel's UDP socket. On Linux
+//! this uses `recvmmsg`/`sendmmsg` and, where the kernel supports it, UDP GRO
+//! so a single receive can return many coalesced WireGuard packets. Other
+//! platforms fall back to one `recv`/`send` per datagram.
+
+use std::io;
+
+use tokio::net::UdpSocket;
+
+/// Datagrams read or written per syscall.
+pub const BATCH_SIZE: usize = 32;
+
+/// Large enough for one GRO-coalesced receive.
+const RECV_BUFFER_SIZE: usize = u16::MAX as usize;
+
+/// Reusable receive buffers for a batch of datagrams.
+pub struct RecvBatch {
+    buffers: Vec<Vec<u8>>,
+    lengths: Vec<usize>,
+    /// Size of the datagrams coalesced into each buffer by GRO.
+    segment_sizes: Vec<usize>,
+    count: usize,
+}
+
+impl RecvBatch {
+    pub fn new() -> Self {
+        Self {
+            buffers: vec![vec![0u8; RECV_BUFFER_SIZE]; BATCH_SIZE],
+            lengths: vec![0; BATCH_SIZE],
+            segment_sizes: vec![0; BATCH_SIZE],
+            count: 0,
+        }
+    }
+
+    /// Waits for at least one datagram and reads as many as are queued.
+    pub async fn recv(&mut self, socket: &UdpSocket) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use tokio::io::Interest;
+
+            socket
+                .async_io(Interest::READABLE, || linux::recv_batch(socket, self))
+                .await?;
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let length = socket.recv(&mut self.buffers[0]).await?;
+            self.lengths[0] = length;
+            self.segment_sizes[0] = length;
+            self.count = 1;
+        }
+
+        Ok(())
+    }
+
+    /// The received datagrams, with GRO-coalesced buffers split back apart.
+    pub fn datagrams(&self) -> impl Iterator<Item = &[u8]> {
+        (0..self.count).flat_map(move |index| {
+            self.buffers[index][..self.lengths[index]].chunks(self.segment_sizes[index].max(1))
+        })
+    }
+}
+
+impl Default for RecvBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends every packet, in order, using as few syscalls as possible.
+pub async fn send_batch(socket: &UdpSocket, packets: &[&[u8]]) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use tokio::io::Interest;
+
+        let mut sent = 0;
+        while sent < packets.len() {
+            sent += socket
+                .async_io(Interest::WRITABLE, || {
+                    linux::send_batch(socket, &packets[sent..])
+                })
+                .await?;
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    for packet in packets {
+        socket.send(packet).await?;
+    }
+
+    Ok(())
+}
+
+/// Asks the kernel to coalesce received datagrams. Returns whether GRO is on.
+pub fn enable_gro(socket: &UdpSocket) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        linux::enable_gro(socket)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = socket;
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        io, mem,
+        os::fd::{AsRawFd, RawFd},
+        ptr,
+    };
+
+    use tokio::net::UdpSocket;
+
+    use super::{BATCH_SIZE, RecvBatch};
+
+    /// Room for one `UDP_GRO` control message, aligned for `cmsghdr`.
+    type ControlBuffer = [u64; 8];
+
+    pub fn enable_gro(socket: &UdpSocket) -> bool {
+        let enable: libc::c_int = 1;
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_UDP,
+                libc::UDP_GRO,
+                &enable as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        result == 0
+    }
+
+    pub fn recv_batch(socket: &UdpSocket, batch: &mut RecvBatch) -> io::Result<()> {
+        let fd: RawFd = socket.as_raw_fd();
+        let mut iovecs: [libc::iovec; BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut headers: [libc::mmsghdr; BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut control: [ControlBuffer; BATCH_SIZE] = [[0; 8]; BATCH_SIZE];
+
+        for index in 0..BATCH_SIZE {
+            iovecs[index] = libc::iovec {
+                iov_base: batch.buffers[index].as_mut_ptr() as *mut libc::c_void,
+                iov_len: batch.buffers[index].len(),
+            };
+            headers[index].msg_hdr.msg_iov = &mut iovecs[index];
+            headers[index].msg_hdr.msg_iovlen = 1;
+            headers[index].msg_hdr.msg_control = control[index].as_mut_ptr() as *mut libc::c_void;
+            headers[index].msg_hdr.msg_controllen = mem::size_of::<ControlBuffer>() as _;
+        }
+
+        let received = unsafe {
+            libc::recvmmsg(
+                fd,
+                headers.as_mut_ptr(),
+                BATCH_SIZE as libc::c_uint,
+                libc::MSG_DONTWAIT,
+                ptr::null_mut(),
+            )
+        };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let received = received as usize;
+        for (index, header) in headers.iter().enumerate().take(received) {
+            let length = header.msg_len as usize;
+            batch.lengths[index] = length;
+            batch.segment_sizes[index] = gro_segment_size(&header.msg_hdr).unwrap_or(length);
+        }
+        batch.count = received;
+        Ok(())
+    }
+
+    pub fn send_batch(socket: &UdpSocket, packets: &[&[u8]]) -> io::Result<usize> {
+        let count = packets.len().min(BATCH_SIZE);
+        let mut iovecs: [libc::iovec; BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut headers: [libc::mmsghdr; BATCH_SIZE] = unsafe { mem::zeroed() };
+
+        for (index, packet) in packets.iter().take(count).enumerate() {
+            iovecs[index] = libc::iovec {
+                iov_base: packet.as_ptr() as *mut libc::c_void,
+                iov_len: packet.len(),
+            };
+            headers[index].msg_hdr.msg_iov = &mut iovecs[index];
+            headers[index].msg_hdr.msg_iovlen = 1;
+        }
+
+        let sent = unsafe {
+            libc::sendmmsg(
+                socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                count as libc::c_uint,
+                libc::MSG_DONTWAIT,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sent as usize)
+    }
+
+    fn gro_segment_size(header: &libc::msghdr) -> Option<usize> {
+        unsafe {
+            let mut message = libc::CMSG_FIRSTHDR(header);
+            while !message.is_null() {
+                if (*message).cmsg_level == libc::SOL_UDP && (*message).cmsg_type == libc::UDP_GRO {
+                    let segment_size =
+                        ptr::read_unaligned(libc::CMSG_DATA(message) as *const libc::c_int);
+                    return usize::try_from(segment_size).ok().filter(|size| *size > 0);
+                }
+                message = libc::CMSG_NXTHDR(header, message);
+            }
+        }
+        None
+    }
+}
@@ -11,6 +11,8 @@ pub struct WireguardConfig {
     pub instance_id: String,
     pub private_key: Vec<u8>,
     pub public_key: Vec<u8>,
+    /// Absent in configs written before preshared keys were introduced.
+    pub preshared_key: Option<Vec<u8>>,
     pub server_endpoint: SocketAddr,
     pub private_ipv4: IpNet,
     pub private_ipv6: IpNet,
@@ -76,6 +78,10 @@ pub async fn parse_wireguard_config(config_path: &str) -> Result<WireguardConfig
 
     let private_key = decode_base64(require_field(interface, "PrivateKey")?, "PrivateKey")?;
     let public_key = decode_base64(require_field(peer, "PublicKey")?, "PublicKey")?;
+    let preshared_key = peer
+        .get("PresharedKey")
+        .map(|value| decode_base64(value, "PresharedKey"))
+        .transpose()?;
     let server_endpoint: SocketAddr = parse_value(require_field(peer, "Endpoint")?, "Endpoint")?;
 
     let addresses = require_field(interface, "Address")?
@@ -109,6 +115,7 @@ pub async fn parse_wireguard_config(config_path: &str) -> Result<WireguardConfig
         instance_id,
         private_key,
         public_key,
+        preshared_key,
         server_endpoint,
         private_ipv4,
         private_ipv6,
@@ -0,0 +1,192 @@
+use std::ffi::{CStr, CString};
+
+use byocvpn_core::{daemon_client::DaemonCommand, error::DaemonError, ipc::PeerCredentials};
+use log::*;
+
+use crate::constants;
+
+/// Starting size for the buffers the reentrant NSS lookups fill in; doubled
+/// while they report `ERANGE`.
+const INITIAL_LOOKUP_BUFFER_SIZE: usize = 1024;
+const MAX_LOOKUP_BUFFER_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandAccess {
+    /// Read-only commands any local user may send.
+    Anyone,
+    /// Commands that change routes, DNS or firewall rules.
+    Privileged,
+}
+
+pub fn required_access(command: &DaemonCommand) -> CommandAccess {
+    match command {
+        DaemonCommand::Status
+        | DaemonCommand::Stats
+        | DaemonCommand::HealthCheck
+        | DaemonCommand::Subscribe
+        | DaemonCommand::Hello { .. }
+        | DaemonCommand::History { .. } => CommandAccess::Anyone,
+        DaemonCommand::Connect(_)
+        | DaemonCommand::Disconnect
+        | DaemonCommand::SetAllowLan { .. }
+        | DaemonCommand::OpenCaptivePortal { .. }
+        | DaemonCommand::CloseCaptivePortal
+        | DaemonCommand::SetLockdown { .. } => CommandAccess::Privileged,
+    }
+}
+
+/// Privileged commands are limited to root and members of the IPC group.
+/// Callers whose credentials could not be read only get unprivileged access.
+pub fn authorize(
+    peer: Option<&PeerCredentials>,
+    command: &DaemonCommand,
+) -> std::result::Result<(), DaemonError> {
+    if required_access(command) == CommandAccess::Anyone {
+        return Ok(());
+    }
+
+    let Some(peer) = peer else {
+        return Err(DaemonError::PermissionDenied {
+            command: command.name().to_string(),
+            reason: "caller credentials unavailable".to_string(),
+        });
+    };
+    if peer.uid == 0 {
+        return Ok(());
+    }
+
+    let group_name = constants::ipc_group();
+    if is_group_member(peer, &group_name) {
+        return Ok(());
+    }
+
+    warn!(
+        "Denied {} from uid={} gid={} pid={:?}",
+        command.name(),
+        peer.uid,
+        peer.gid,
+        peer.pid
+    );
+    Err(DaemonError::PermissionDenied {
+        command: command.name().to_string(),
+        reason: format!("caller must be root or in the {} group", group_name),
+    })
+}
+
+/// Membership comes from NSS, so LDAP and SSSD users and groups count too.
+fn is_group_member(peer: &PeerCredentials, group_name: &str) -> bool {
+    let Some(group_id) = group_id(group_name) else {
+        return false;
+    };
+    if peer.gid == group_id {
+        return true;
+    }
+
+    let Some(user) = user_entry(peer.uid) else {
+        return false;
+    };
+    group_list(&user.name, user.primary_group_id).contains(&group_id)
+}
+
+/// Resolves a group name to its id, or `None` if no such group exists.
+pub fn group_id(group_name: &str) -> Option<u32> {
+    let group_name = CString::new(group_name).ok()?;
+    let mut buffer = vec![0 as libc::c_char; INITIAL_LOOKUP_BUFFER_SIZE];
+    loop {
+        let mut group: libc::group = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::group = std::ptr::null_mut();
+        let status = unsafe {
+            libc::getgrnam_r(
+                group_name.as_ptr(),
+                &mut group,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            )
+        };
+        if status == libc::ERANGE && buffer.len() < MAX_LOOKUP_BUFFER_SIZE {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        if status != 0 || result.is_null() {
+            return None;
+        }
+        return Some(group.gr_gid);
+    }
+}
+
+/// The parts of a passwd entry the daemon uses.
+pub(crate) struct UserEntry {
+    pub name: CString,
+    pub primary_group_id: libc::gid_t,
+    pub home: CString,
+}
+
+pub(crate) fn user_entry(uid: u32) -> Option<UserEntry> {
+    let mut buffer = vec![0 as libc::c_char; INITIAL_LOOKUP_BUFFER_SIZE];
+    loop {
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let status = unsafe {
+            libc::getpwuid_r(
+                uid,
+                &mut passwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            )
+        };
+        if status == libc::ERANGE && buffer.len() < MAX_LOOKUP_BUFFER_SIZE {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        if status != 0 || result.is_null() || passwd.pw_name.is_null() {
+            return None;
+        }
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) }.to_owned();
+        let home = if passwd.pw_dir.is_null() {
+            CString::from(c"/")
+        } else {
+            unsafe { CStr::from_ptr(passwd.pw_dir) }.to_owned()
+        };
+        return Some(UserEntry {
+            name,
+            primary_group_id: passwd.pw_gid,
+            home,
+        });
+    }
+}
+
+/// Every group the user belongs to, primary group included.
+// The casts are only needed on macOS, whose group ids are `int` here.
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn group_list(user_name: &CStr, primary_group_id: libc::gid_t) -> Vec<u32> {
+    #[cfg(target_os = "macos")]
+    type GroupId = libc::c_int;
+    #[cfg(not(target_os = "macos"))]
+    type GroupId = libc::gid_t;
+
+    let mut capacity: libc::c_int = 64;
+    loop {
+        let mut groups: Vec<GroupId> = vec![0; capacity as usize];
+        let mut count = capacity;
+        let status = unsafe {
+            libc::getgrouplist(
+                user_name.as_ptr(),
+                primary_group_id as GroupId,
+                groups.as_mut_ptr(),
+                &mut count,
+            )
+        };
+        if status >= 0 {
+            groups.truncate(count.max(0) as usize);
+            return groups.into_iter().map(|group| group as u32).collect();
+        }
+        // A short buffer reports -1; Linux also sets the needed count.
+        let needed = count.max(capacity * 2);
+        if needed as usize > MAX_LOOKUP_BUFFER_SIZE {
+            return Vec::new();
+        }
+        capacity = needed;
+    }
+}
@@ -1,6 +1,37 @@
 use std::path::PathBuf;
 
 pub const TUNNEL_MTU: u16 = 1280;
+/// The tunnel carries IPv6, which needs at least 1280.
+pub const MIN_TUNNEL_MTU: u16 = 1280;
+pub const MAX_TUNNEL_MTU: u16 = 9000;
+
+pub const DEFAULT_NETWORK_NAMESPACE: &str = "byocvpn";
+
+#[cfg(unix)]
+const DEFAULT_IPC_GROUP: &str = "byocvpn";
+
+/// Group whose members may send privileged daemon commands. Override with
+/// `BYOCVPN_IPC_GROUP`.
+#[cfg(unix)]
+pub fn ipc_group() -> String {
+    std::env::var("BYOCVPN_IPC_GROUP").unwrap_or_else(|_| DEFAULT_IPC_GROUP.to_string())
+}
+
+/// Localhost port for the Prometheus `/metrics` endpoint, from
+/// `BYOCVPN_METRICS_PORT`. The exporter is off when this is unset.
+pub fn metrics_exporter_port() -> Option<u16> {
+    std::env::var("BYOCVPN_METRICS_PORT")
+        .ok()
+        .and_then(|port| port.trim().parse().ok())
+}
+
+/// Whether the daemon may use the kernel's WireGuard module on Linux. Set
+/// `BYOCVPN_WIREGUARD_BACKEND=userspace` to always use boringtun.
+#[cfg(target_os = "linux")]
+pub fn kernel_wireguard_allowed() -> bool {
+    !std::env::var("BYOCVPN_WIREGUARD_BACKEND")
+        .is_ok_and(|backend| backend.trim().eq_ignore_ascii_case("userspace"))
+}
 
 #[cfg(unix)]
 fn socket_dir() -> PathBuf {
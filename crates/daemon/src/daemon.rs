@@ -1,21 +1,43 @@
 use byocvpn_core::{
-    daemon_client::{DaemonCommand, DaemonResponse},
+    daemon_client::{
+        DaemonCommand, DaemonEvent, DaemonHello, DaemonReply, DaemonResponse, MIN_PROTOCOL_VERSION,
+    },
     error::{DaemonError, Result},
-    ipc::IpcSocket,
+    ipc::{IpcSocket, IpcStream},
 };
 use log::*;
 use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 
+#[cfg(unix)]
+use crate::authorization;
 use crate::{
-    constants,
+    constants, events,
+    metrics_exporter::spawn_metrics_exporter,
+    routing::dns::restore_stale_dns_configuration,
+    tunnel_manager::STATE_CHANGE_LOCK,
     vpn::{
-        connect::connect_vpn, disconnect::disconnect_vpn, metrics::get_current_metrics,
-        restore::try_restore_session, status::get_vpn_status,
+        connect::connect_vpn,
+        disconnect::disconnect_vpn,
+        history::{close_stale_sessions, prune_and_compact, query_history},
+        kill_switch::{close_captive_portal, open_captive_portal, set_allow_lan},
+        lockdown::{self, set_lockdown},
+        metrics::get_current_metrics,
+        restore::try_restore_session,
+        status::get_vpn_status,
     },
 };
 
 pub async fn run_daemon() -> Result<()> {
     env_logger::init();
+
+    // Block traffic before anything else runs, including session restore.
+    if let Err(error) = lockdown::engage().await {
+        error!("Failed to install lockdown rules: {}", error);
+    }
+    restore_stale_dns_configuration();
+
     let socket_path = constants::socket_path();
 
     #[cfg(unix)]
@@ -29,8 +51,13 @@ pub async fn run_daemon() -> Result<()> {
     }
 
     let mut listener = IpcSocket::bind(socket_path.clone()).await?;
+    #[cfg(unix)]
+    warn_if_ipc_group_missing();
 
+    close_stale_sessions();
+    prune_and_compact();
     try_restore_session().await;
+    spawn_metrics_exporter(constants::metrics_exporter_port());
 
     info!(
         "Daemon listening on {} (pid: {})",
@@ -39,50 +66,215 @@ pub async fn run_daemon() -> Result<()> {
     );
 
     loop {
-        let mut stream = listener.accept().await?;
+        let stream = listener.accept().await?;
+        tokio::spawn(serve_connection(stream));
+    }
+}
 
-        while let Ok(Some(line)) = stream.read_message().await {
-            debug!("Daemon received: {line}");
+#[cfg(unix)]
+fn warn_if_ipc_group_missing() {
+    let group_name = constants::ipc_group();
+    if authorization::group_id(&group_name).is_none() {
+        warn!(
+            "Group {} not found; only root may send privileged commands",
+            group_name
+        );
+    }
+}
 
-            let response = match serde_json::from_str::<DaemonCommand>(&line) {
-                Ok(command) => handle_command(command).await,
-                Err(error) => {
-                    error!("Invalid command: {}", error);
+async fn serve_connection(mut stream: IpcStream) {
+    #[cfg(unix)]
+    let peer = match stream.peer_credentials() {
+        Ok(peer) => Some(peer),
+        Err(error) => {
+            warn!("{}", error);
+            None
+        }
+    };
+
+    while let Ok(Some(line)) = stream.read_message().await {
+        debug!("Daemon received: {line}");
+
+        let (id, command) = parse_request(&line);
+        let command = match command {
+            Ok(command) => command,
+            Err(error) => {
+                error!("Invalid command: {}", error);
+                send_response(
+                    &mut stream,
+                    id,
                     DaemonResponse::Err(DaemonError::CommandFailed {
                         command: error.to_string(),
-                    })
-                }
-            };
+                    }),
+                )
+                .await;
+                continue;
+            }
+        };
 
-            match serde_json::to_string(&response) {
-                Ok(json) => {
-                    if stream.send_message(&json).await.is_err() {
-                        error!("Failed to send response to client");
-                    }
-                }
-                Err(error) => {
-                    error!("Failed to serialize response: {}", error);
-                }
+        #[cfg(unix)]
+        if let Err(error) = authorization::authorize(peer.as_ref(), &command) {
+            send_response(&mut stream, id, DaemonResponse::Err(error)).await;
+            continue;
+        }
+
+        if let DaemonCommand::Subscribe = command {
+            send_response(&mut stream, id, DaemonResponse::Ok(Value::Null)).await;
+            stream_events(stream).await;
+            return;
+        }
+
+        let response = handle_command(command).await;
+        send_response(&mut stream, id, response).await;
+    }
+}
+
+/// Accepts both `DaemonRequest` envelopes and the bare `DaemonCommand` lines
+/// sent by clients that predate protocol versioning. The ID is recovered even
+/// when the command itself does not parse, so the error can still be matched.
+fn parse_request(line: &str) -> (Option<u64>, serde_json::Result<DaemonCommand>) {
+    let value = match serde_json::from_str::<Value>(line) {
+        Ok(value) => value,
+        Err(error) => return (None, Err(error)),
+    };
+
+    match value.get("id").and_then(Value::as_u64) {
+        Some(id) => (
+            Some(id),
+            serde_json::from_value(value.get("command").cloned().unwrap_or(Value::Null)),
+        ),
+        None => (None, serde_json::from_value(value)),
+    }
+}
+
+async fn send_response(stream: &mut IpcStream, id: Option<u64>, response: DaemonResponse) {
+    match serde_json::to_string(&DaemonReply { id, response }) {
+        Ok(json) => {
+            if stream.send_message(&json).await.is_err() {
+                error!("Failed to send response to client");
             }
         }
+        Err(error) => {
+            error!("Failed to serialize response: {}", error);
+        }
     }
 }
 
+async fn stream_events(mut stream: IpcStream) {
+    let mut receiver = events::subscribe();
+    debug!("Event subscriber connected");
+
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Event subscriber lagged, skipped {} events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let json = match serde_json::to_string(&event) {
+            Ok(json) => json,
+            Err(error) => {
+                error!("Failed to serialize event: {}", error);
+                continue;
+            }
+        };
+        if stream.send_message(&json).await.is_err() {
+            break;
+        }
+    }
+
+    debug!("Event subscriber disconnected");
+}
+
+/// Whether the command reprograms the tunnel, routes, DNS or firewall.
+fn changes_state(command: &DaemonCommand) -> bool {
+    matches!(
+        command,
+        DaemonCommand::Connect(_)
+            | DaemonCommand::Disconnect
+            | DaemonCommand::SetAllowLan { .. }
+            | DaemonCommand::OpenCaptivePortal { .. }
+            | DaemonCommand::CloseCaptivePortal
+            | DaemonCommand::SetLockdown { .. }
+    )
+}
+
 async fn handle_command(command: DaemonCommand) -> DaemonResponse {
+    // A second connect waits here, then fails on the running tunnel instead
+    // of replacing it.
+    let _state_change_guard = if changes_state(&command) {
+        Some(STATE_CHANGE_LOCK.lock().await)
+    } else {
+        None
+    };
+
     match command {
         DaemonCommand::Connect(params) => match connect_vpn(params).await {
-            Ok(()) => DaemonResponse::Ok(Value::Null),
+            Ok(()) => {
+                events::publish_state().await;
+                DaemonResponse::Ok(Value::Null)
+            }
             Err(error) => {
                 error!("Connect error: {}", error);
+                events::publish(DaemonEvent::Error {
+                    message: error.to_string(),
+                });
                 DaemonResponse::Err(DaemonError::CommandFailed {
                     command: error.to_string(),
                 })
             }
         },
         DaemonCommand::Disconnect => match disconnect_vpn().await {
-            Ok(()) => DaemonResponse::Ok(Value::Null),
+            Ok(()) => {
+                events::publish_state().await;
+                DaemonResponse::Ok(Value::Null)
+            }
             Err(error) => {
                 error!("Disconnect error: {}", error);
+                events::publish(DaemonEvent::Error {
+                    message: error.to_string(),
+                });
+                DaemonResponse::Err(DaemonError::CommandFailed {
+                    command: error.to_string(),
+                })
+            }
+        },
+        DaemonCommand::SetAllowLan { enabled } => match set_allow_lan(enabled).await {
+            Ok(()) => DaemonResponse::Ok(Value::Null),
+            Err(error) => {
+                error!("Allow LAN error: {}", error);
+                DaemonResponse::Err(DaemonError::CommandFailed {
+                    command: error.to_string(),
+                })
+            }
+        },
+        DaemonCommand::OpenCaptivePortal { duration_secs } => {
+            match open_captive_portal(Duration::from_secs(duration_secs)).await {
+                Ok(()) => DaemonResponse::Ok(Value::Null),
+                Err(error) => {
+                    error!("Captive portal error: {}", error);
+                    DaemonResponse::Err(DaemonError::CommandFailed {
+                        command: error.to_string(),
+                    })
+                }
+            }
+        }
+        DaemonCommand::CloseCaptivePortal => match close_captive_portal() {
+            Ok(()) => DaemonResponse::Ok(Value::Null),
+            Err(error) => {
+                error!("Captive portal error: {}", error);
+                DaemonResponse::Err(DaemonError::CommandFailed {
+                    command: error.to_string(),
+                })
+            }
+        },
+        DaemonCommand::SetLockdown { enabled } => match set_lockdown(enabled).await {
+            Ok(()) => DaemonResponse::Ok(Value::Null),
+            Err(error) => {
+                error!("Lockdown error: {}", error);
                 DaemonResponse::Err(DaemonError::CommandFailed {
                     command: error.to_string(),
                 })
@@ -117,7 +309,44 @@ async fn handle_command(command: DaemonCommand) -> DaemonResponse {
                 }
             }
         }
+        DaemonCommand::History { since } => match query_history(since) {
+            Ok(sessions) => match serde_json::to_value(&sessions) {
+                Ok(value) => DaemonResponse::Ok(value),
+                Err(error) => {
+                    error!("History serialization error: {}", error);
+                    DaemonResponse::Err(DaemonError::CommandFailed {
+                        command: error.to_string(),
+                    })
+                }
+            },
+            Err(error) => {
+                error!("History error: {}", error);
+                DaemonResponse::Err(DaemonError::CommandFailed {
+                    command: error.to_string(),
+                })
+            }
+        },
         DaemonCommand::HealthCheck => DaemonResponse::Ok(Value::Null),
+        DaemonCommand::Hello { protocol_version } => {
+            debug!("Client speaks protocol {}", protocol_version);
+            if protocol_version < MIN_PROTOCOL_VERSION {
+                return DaemonResponse::Err(DaemonError::DaemonTooNew {
+                    client_version: protocol_version,
+                    required: MIN_PROTOCOL_VERSION,
+                });
+            }
+            match serde_json::to_value(DaemonHello::current()) {
+                Ok(value) => DaemonResponse::Ok(value),
+                Err(error) => {
+                    error!("Hello serialization error: {}", error);
+                    DaemonResponse::Err(DaemonError::CommandFailed {
+                        command: error.to_string(),
+                    })
+                }
+            }
+        }
+        DaemonCommand::Subscribe => DaemonResponse::Err(DaemonError::CommandFailed {
+            command: "subscribe is handled by the connection".to_string(),
+        }),
     }
 }
-
@@ -1,6 +1,9 @@
 use async_trait::async_trait;
 use byocvpn_core::{
-    daemon_client::{DaemonClient, DaemonCommand, DaemonResponse},
+    daemon_client::{
+        DaemonClient, DaemonCommand, DaemonHello, DaemonReply, DaemonRequest, DaemonResponse,
+        MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+    },
     error::{DaemonError, Result},
     ipc::IpcStream,
 };
@@ -21,30 +24,33 @@ impl DaemonClient for UnixDaemonClient {
         let mut stream = IpcStream::connect(&socket_path).await?;
         info!("Connected to daemon at {}", socket_path.to_string_lossy());
 
-        let serialized_command =
-            serde_json::to_string(&command).map_err(|error| DaemonError::SocketError {
-                reason: format!("failed to serialize command: {}", error),
-            })?;
-        stream.send_message(&serialized_command).await?;
-
-        let raw_response =
-            stream
-                .read_message()
-                .await?
-                .ok_or_else(|| DaemonError::ConnectionFailed {
-                    reason: "daemon closed connection without response".to_string(),
-                })?;
-
-        let response = serde_json::from_str::<DaemonResponse>(&raw_response).map_err(|error| {
+        let hello = send_request(
+            &mut stream,
+            1,
+            DaemonCommand::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            },
+        )
+        .await?;
+        let hello = serde_json::from_value::<DaemonHello>(hello).map_err(|error| {
             DaemonError::InvalidResponse {
-                reason: format!("failed to parse daemon response: {}", error),
+                reason: format!("failed to parse daemon hello: {}", error),
             }
         })?;
+        debug!(
+            "Daemon {} speaks protocol {}",
+            hello.daemon_version, hello.protocol_version
+        );
+        hello.check_compatible()?;
 
-        match response {
-            DaemonResponse::Ok(value) => Ok(value),
-            DaemonResponse::Err(error) => Err(error.into()),
+        if !hello.supports(&command) {
+            return Err(DaemonError::UnsupportedCommand {
+                command: command.name().to_string(),
+            }
+            .into());
         }
+
+        send_request(&mut stream, 2, command).await
     }
 
     async fn is_daemon_running(&self) -> bool {
@@ -80,3 +86,48 @@ impl DaemonClient for UnixDaemonClient {
         }
     }
 }
+
+/// Sends one request and waits for the reply carrying the same ID. A reply
+/// without an ID comes from a daemon that predates protocol versioning.
+async fn send_request(stream: &mut IpcStream, id: u64, command: DaemonCommand) -> Result<Value> {
+    let serialized_request =
+        serde_json::to_string(&DaemonRequest { id, command }).map_err(|error| {
+            DaemonError::SocketError {
+                reason: format!("failed to serialize command: {}", error),
+            }
+        })?;
+    stream.send_message(&serialized_request).await?;
+
+    loop {
+        let raw_reply =
+            stream
+                .read_message()
+                .await?
+                .ok_or_else(|| DaemonError::ConnectionFailed {
+                    reason: "daemon closed connection without response".to_string(),
+                })?;
+
+        let reply = serde_json::from_str::<DaemonReply>(&raw_reply).map_err(|error| {
+            DaemonError::InvalidResponse {
+                reason: format!("failed to parse daemon response: {}", error),
+            }
+        })?;
+
+        match reply.id {
+            Some(reply_id) if reply_id == id => {
+                return match reply.response {
+                    DaemonResponse::Ok(value) => Ok(value),
+                    DaemonResponse::Err(error) => Err(error.into()),
+                };
+            }
+            Some(reply_id) => debug!("Skipping reply to request {}", reply_id),
+            None => {
+                return Err(DaemonError::DaemonTooOld {
+                    daemon_version: 0,
+                    required: MIN_PROTOCOL_VERSION,
+                }
+                .into());
+            }
+        }
+    }
+}
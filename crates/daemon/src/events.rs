@@ -0,0 +1,33 @@
+use byocvpn_core::daemon_client::DaemonEvent;
+use log::*;
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast;
+
+use crate::vpn::status::get_vpn_status;
+
+/// Events a slow subscriber may fall behind by before it starts missing some.
+const EVENT_BUFFER: usize = 64;
+
+static EVENTS: Lazy<broadcast::Sender<DaemonEvent>> =
+    Lazy::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+pub fn publish(event: DaemonEvent) {
+    // Sending only fails when nobody is subscribed.
+    if EVENTS.send(event).is_err() {
+        trace!("No event subscribers");
+    }
+}
+
+pub fn subscribe() -> broadcast::Receiver<DaemonEvent> {
+    EVENTS.subscribe()
+}
+
+/// Publishes the current tunnel status after a connect or disconnect.
+pub async fn publish_state() {
+    match get_vpn_status().await {
+        Ok(status) => publish(DaemonEvent::StateChanged(status)),
+        Err(error) => publish(DaemonEvent::Error {
+            message: error.to_string(),
+        }),
+    }
+}
@@ -1,58 +1,67 @@
-use byocvpn_core::error::{Result, SystemError};
+use byocvpn_core::{daemon_client::SplitTunnelConfig, error::Result};
 use log::*;
 use std::process::Command;
 
-const CHAIN: &str = "BYOCVPN_KS";
+use super::{KillSwitchExceptions, TunnelEndpoint};
 
-pub fn apply(server_ip: &str, tun_name: &str) -> Result<()> {
-    remove_if_exists();
+mod iptables;
+mod nftables;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Iptables(iptables::Binaries),
+    Nftables,
+}
 
-    run_iptables(&["-N", CHAIN])?;
-    run_iptables(&["-A", CHAIN, "-o", "lo", "-j", "ACCEPT"])?;
-    run_iptables(&["-A", CHAIN, "-i", "lo", "-j", "ACCEPT"])?;
-    run_iptables(&[
-        "-A", CHAIN, "-d", &format!("{}/32", server_ip),
-        "-p", "udp", "--dport", "51820", "-j", "ACCEPT",
-    ])?;
-    run_iptables(&["-A", CHAIN, "-o", tun_name, "-j", "ACCEPT"])?;
-    run_iptables(&["-A", CHAIN, "-i", tun_name, "-j", "ACCEPT"])?;
-    run_iptables(&["-A", CHAIN, "-j", "DROP"])?;
-
-    run_iptables(&["-I", "OUTPUT", "1", "-j", CHAIN])?;
-    run_iptables(&["-I", "INPUT", "1", "-j", CHAIN])?;
-
-    info!("[KillSwitch] iptables chain applied (server={}, tun={})", server_ip, tun_name);
+pub fn apply(
+    tunnel: Option<&TunnelEndpoint>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    // Each backend replaces its own rules without a gap; only the other
+    // backend's leftovers are cleared, once the new rules are in place.
+    match detect_backend() {
+        Backend::Iptables(binaries) => {
+            iptables::apply(binaries, tunnel, split_tunnel, exceptions)?;
+            nftables::remove_if_exists();
+        }
+        Backend::Nftables => {
+            nftables::apply(tunnel, split_tunnel, exceptions)?;
+            iptables::remove_if_exists();
+        }
+    }
     Ok(())
 }
 
 pub fn remove() -> Result<()> {
     remove_if_exists();
-    info!("[KillSwitch] iptables chain removed");
+    info!("[KillSwitch] firewall rules removed");
     Ok(())
 }
 
 fn remove_if_exists() {
-    let _ = run_iptables(&["-D", "OUTPUT", "-j", CHAIN]);
-    let _ = run_iptables(&["-D", "INPUT", "-j", CHAIN]);
-    let _ = run_iptables(&["-F", CHAIN]);
-    let _ = run_iptables(&["-X", CHAIN]);
+    iptables::remove_if_exists();
+    nftables::remove_if_exists();
 }
 
-fn run_iptables(args: &[&str]) -> Result<()> {
-    let output =
-        Command::new("iptables")
-            .args(args)
-            .output()
-            .map_err(|error| SystemError::KillSwitchFailed {
-                reason: format!("iptables exec failed: {}", error),
-            })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(SystemError::KillSwitchFailed {
-            reason: format!("iptables {}: {}", args.join(" "), stderr.trim()),
-        }
-        .into());
-    }
-    Ok(())
+// Prefer iptables while the legacy tool is installed; on nft-only systems the
+// iptables shim may be missing or incomplete, so program nftables directly.
+fn detect_backend() -> Backend {
+    let backend = if command_available("iptables-legacy") {
+        Backend::Iptables(iptables::Binaries::LEGACY)
+    } else if command_available("nft") {
+        Backend::Nftables
+    } else {
+        Backend::Iptables(iptables::Binaries::DEFAULT)
+    };
+    debug!("[KillSwitch] Using {:?} backend", backend);
+    backend
+}
+
+fn command_available(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
 }
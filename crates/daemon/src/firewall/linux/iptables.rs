@@ -0,0 +1,228 @@
+use byocvpn_core::{
+    daemon_client::SplitTunnelConfig,
+    error::{Result, SystemError},
+};
+use ipnet::IpNet;
+use log::*;
+use std::{net::IpAddr, process::Command};
+
+use crate::firewall::{CAPTIVE_PORTAL_PORTS, KillSwitchExceptions, TunnelEndpoint};
+
+/// Two slots: rules are built in the idle one and the jumps swapped over, so
+/// the host never goes without a kill switch chain while it is reprogrammed.
+const CHAINS: [&str; 2] = ["BYOCVPN_KS", "BYOCVPN_KS_B"];
+
+/// The iptables/ip6tables pair to program, matching what was detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binaries {
+    ipv4: &'static str,
+    ipv6: &'static str,
+}
+
+impl Binaries {
+    pub const LEGACY: Self = Self {
+        ipv4: "iptables-legacy",
+        ipv6: "ip6tables-legacy",
+    };
+    pub const DEFAULT: Self = Self {
+        ipv4: "iptables",
+        ipv6: "ip6tables",
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    fn binary(self, binaries: Binaries) -> &'static str {
+        match self {
+            Family::V4 => binaries.ipv4,
+            Family::V6 => binaries.ipv6,
+        }
+    }
+
+    fn contains(self, network: &IpNet) -> bool {
+        match self {
+            Family::V4 => network.addr().is_ipv4(),
+            Family::V6 => network.addr().is_ipv6(),
+        }
+    }
+}
+
+pub fn apply(
+    binaries: Binaries,
+    tunnel: Option<&TunnelEndpoint>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    let server_address = tunnel
+        .map(|tunnel| {
+            tunnel
+                .server_ip
+                .parse::<IpAddr>()
+                .map_err(|error| SystemError::KillSwitchFailed {
+                    reason: format!("invalid server address {}: {}", tunnel.server_ip, error),
+                })
+        })
+        .transpose()?;
+    let tun_name = tunnel.and_then(|tunnel| tunnel.tun_name);
+
+    for family in [Family::V4, Family::V6] {
+        apply_family(
+            family.binary(binaries),
+            family,
+            server_address,
+            tun_name,
+            split_tunnel,
+            exceptions,
+        )?;
+    }
+
+    info!("[KillSwitch] iptables/ip6tables chains applied (server={:?}, tun={:?})", server_address, tun_name);
+    Ok(())
+}
+
+fn apply_family(
+    binary: &str,
+    family: Family,
+    server_address: Option<IpAddr>,
+    tun_name: Option<&str>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    let active_chain = CHAINS
+        .into_iter()
+        .find(|chain| run(binary, &["-C", "OUTPUT", "-j", chain]).is_ok());
+    let chain = if active_chain == Some(CHAINS[0]) {
+        CHAINS[1]
+    } else {
+        CHAINS[0]
+    };
+    // Clears anything an interrupted run left in the idle slot.
+    delete_chain(binary, chain);
+
+    run(binary, &["-N", chain])?;
+    run(binary, &["-A", chain, "-o", "lo", "-j", "ACCEPT"])?;
+    run(binary, &["-A", chain, "-i", "lo", "-j", "ACCEPT"])?;
+
+    if let Some(server_network) = server_address
+        .map(IpNet::from)
+        .filter(|server_network| family.contains(server_network))
+    {
+        let server_network = server_network.to_string();
+        run(binary, &[
+            "-A", chain, "-d", &server_network,
+            "-p", "udp", "--dport", "51820", "-j", "ACCEPT",
+        ])?;
+        run(binary, &[
+            "-A", chain, "-s", &server_network,
+            "-p", "udp", "--sport", "51820", "-j", "ACCEPT",
+        ])?;
+    }
+
+    if let Some(tun_name) = tun_name {
+        run(binary, &["-A", chain, "-o", tun_name, "-j", "ACCEPT"])?;
+        run(binary, &["-A", chain, "-i", tun_name, "-j", "ACCEPT"])?;
+    }
+
+    // Without neighbour discovery the IPv6 link itself stops working.
+    if let Family::V6 = family {
+        for icmp_type in [
+            "router-solicitation",
+            "router-advertisement",
+            "neighbour-solicitation",
+            "neighbour-advertisement",
+        ] {
+            run(binary, &[
+                "-A", chain, "-p", "ipv6-icmp", "--icmpv6-type", icmp_type, "-j", "ACCEPT",
+            ])?;
+        }
+    }
+
+    let allowed_networks = exceptions.allowed_networks();
+    for network in split_tunnel
+        .exclude_routes
+        .iter()
+        .chain(allowed_networks.iter())
+        .filter(|network| family.contains(network))
+    {
+        let network = network.to_string();
+        run(binary, &["-A", chain, "-d", &network, "-j", "ACCEPT"])?;
+        run(binary, &["-A", chain, "-s", &network, "-j", "ACCEPT"])?;
+    }
+
+    if let Some(gateway) = exceptions.captive_portal_gateway() {
+        let gateway = IpNet::from(gateway);
+        if family.contains(&gateway) {
+            let gateway = gateway.to_string();
+            for (protocol, port) in CAPTIVE_PORTAL_PORTS {
+                let port = port.to_string();
+                run(binary, &[
+                    "-A", chain, "-d", &gateway, "-p", protocol, "--dport", &port, "-j", "ACCEPT",
+                ])?;
+                run(binary, &[
+                    "-A", chain, "-s", &gateway, "-p", protocol, "--sport", &port, "-j", "ACCEPT",
+                ])?;
+            }
+        }
+    }
+
+    if split_tunnel.is_full_tunnel() {
+        run(binary, &["-A", chain, "-j", "DROP"])?;
+    } else {
+        for network in split_tunnel.include_routes.iter().filter(|network| family.contains(network)) {
+            let network = network.to_string();
+            run(binary, &["-A", chain, "-d", &network, "-j", "DROP"])?;
+            run(binary, &["-A", chain, "-s", &network, "-j", "DROP"])?;
+        }
+    }
+
+    // The new chain is hooked in ahead of the old one before that is removed.
+    run(binary, &["-I", "OUTPUT", "1", "-j", chain])?;
+    run(binary, &["-I", "INPUT", "1", "-j", chain])?;
+    if let Some(active_chain) = active_chain {
+        delete_chain(binary, active_chain);
+    }
+    Ok(())
+}
+
+/// Removes the chains from both the legacy and default tools, since either
+/// may have been the one in use.
+pub fn remove_if_exists() {
+    for binaries in [Binaries::LEGACY, Binaries::DEFAULT] {
+        for family in [Family::V4, Family::V6] {
+            for chain in CHAINS {
+                delete_chain(family.binary(binaries), chain);
+            }
+        }
+    }
+}
+
+fn delete_chain(binary: &str, chain: &str) {
+    while run(binary, &["-D", "OUTPUT", "-j", chain]).is_ok() {}
+    while run(binary, &["-D", "INPUT", "-j", chain]).is_ok() {}
+    let _ = run(binary, &["-F", chain]);
+    let _ = run(binary, &["-X", chain]);
+}
+
+fn run(binary: &str, args: &[&str]) -> Result<()> {
+    let output =
+        Command::new(binary)
+            .args(args)
+            .output()
+            .map_err(|error| SystemError::KillSwitchFailed {
+                reason: format!("{} exec failed: {}", binary, error),
+            })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SystemError::KillSwitchFailed {
+            reason: format!("{} {}: {}", binary, args.join(" "), stderr.trim()),
+        }
+        .into());
+    }
+    Ok(())
+}
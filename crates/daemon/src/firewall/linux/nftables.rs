@@ -0,0 +1,166 @@
+use byocvpn_core::{
+    daemon_client::SplitTunnelConfig,
+    error::{Result, SystemError},
+};
+use ipnet::IpNet;
+use log::*;
+use std::{
+    fmt::Write as _,
+    io::Write as _,
+    net::IpAddr,
+    process::{Command, Stdio},
+};
+
+use crate::firewall::{CAPTIVE_PORTAL_PORTS, KillSwitchExceptions, TunnelEndpoint};
+
+const TABLE: &str = "byocvpn_ks";
+
+pub fn apply(
+    tunnel: Option<&TunnelEndpoint>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    let server_address = tunnel
+        .map(|tunnel| {
+            tunnel
+                .server_ip
+                .parse::<IpAddr>()
+                .map_err(|error| SystemError::KillSwitchFailed {
+                    reason: format!("invalid server address {}: {}", tunnel.server_ip, error),
+                })
+        })
+        .transpose()?;
+    let tun_name = tunnel.and_then(|tunnel| tunnel.tun_name);
+
+    run_nft_script(&build_ruleset(server_address, tun_name, split_tunnel, exceptions))?;
+
+    info!("[KillSwitch] nftables table applied (server={:?}, tun={:?})", server_address, tun_name);
+    Ok(())
+}
+
+pub fn remove_if_exists() {
+    let _ = Command::new("nft")
+        .args(["delete", "table", "inet", TABLE])
+        .output();
+}
+
+// The leading `table` + `delete table` pair makes the script replace any
+// existing table in the same transaction, so the swap is atomic.
+fn build_ruleset(
+    server_address: Option<IpAddr>,
+    tun_name: Option<&str>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> String {
+    let mut output_rules = String::from("oifname \"lo\" accept\n");
+    let mut input_rules = String::from("iifname \"lo\" accept\n");
+
+    if let Some(server_address) = server_address {
+        let family = family_keyword(&IpNet::from(server_address));
+        let _ = writeln!(output_rules, "{family} daddr {server_address} udp dport 51820 accept");
+        let _ = writeln!(input_rules, "{family} saddr {server_address} udp sport 51820 accept");
+    }
+    if let Some(tun_name) = tun_name {
+        let _ = writeln!(output_rules, "oifname \"{tun_name}\" accept");
+        let _ = writeln!(input_rules, "iifname \"{tun_name}\" accept");
+    }
+
+    output_rules.push_str(
+        "icmpv6 type { nd-router-solicit, nd-neighbor-solicit, nd-neighbor-advert } accept\n",
+    );
+    input_rules.push_str(
+        "icmpv6 type { nd-router-advert, nd-neighbor-solicit, nd-neighbor-advert } accept\n",
+    );
+
+    for network in split_tunnel
+        .exclude_routes
+        .iter()
+        .chain(exceptions.allowed_networks().iter())
+    {
+        let _ = writeln!(output_rules, "{} daddr {network} accept", family_keyword(network));
+        let _ = writeln!(input_rules, "{} saddr {network} accept", family_keyword(network));
+    }
+
+    if let Some(gateway) = exceptions.captive_portal_gateway() {
+        let gateway = IpNet::from(gateway);
+        let family = family_keyword(&gateway);
+        let gateway = gateway.addr();
+        for (protocol, port) in CAPTIVE_PORTAL_PORTS {
+            let _ = writeln!(
+                output_rules,
+                "{family} daddr {gateway} {protocol} dport {port} accept"
+            );
+            let _ = writeln!(
+                input_rules,
+                "{family} saddr {gateway} {protocol} sport {port} accept"
+            );
+        }
+    }
+
+    if split_tunnel.is_full_tunnel() {
+        output_rules.push_str("drop\n");
+        input_rules.push_str("drop\n");
+    } else {
+        for network in &split_tunnel.include_routes {
+            let _ = writeln!(output_rules, "{} daddr {network} drop", family_keyword(network));
+            let _ = writeln!(input_rules, "{} saddr {network} drop", family_keyword(network));
+        }
+    }
+
+    format!(
+        "table inet {TABLE}\n\
+         delete table inet {TABLE}\n\
+         table inet {TABLE} {{\n\
+         chain output {{\n\
+         type filter hook output priority 0; policy accept;\n\
+         {output_rules}}}\n\
+         chain input {{\n\
+         type filter hook input priority 0; policy accept;\n\
+         {input_rules}}}\n\
+         }}\n"
+    )
+}
+
+fn family_keyword(network: &IpNet) -> &'static str {
+    match network {
+        IpNet::V4(_) => "ip",
+        IpNet::V6(_) => "ip6",
+    }
+}
+
+fn run_nft_script(script: &str) -> Result<()> {
+    debug!("[KillSwitch] nft ruleset:\n{}", script);
+
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| SystemError::KillSwitchFailed {
+            reason: format!("nft exec failed: {}", error),
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(script.as_bytes())
+            .map_err(|error| SystemError::KillSwitchFailed {
+                reason: format!("failed to write nft ruleset: {}", error),
+            })?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| SystemError::KillSwitchFailed {
+            reason: format!("nft exec failed: {}", error),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SystemError::KillSwitchFailed {
+            reason: format!("nft -f: {}", stderr.trim()),
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -1,15 +1,58 @@
-use byocvpn_core::error::{Result, SystemError};
+use byocvpn_core::{
+    daemon_client::SplitTunnelConfig,
+    error::{Result, SystemError},
+};
 use log::*;
 use std::process::Command;
 
+use super::{CAPTIVE_PORTAL_PORTS, KillSwitchExceptions, TunnelEndpoint};
+
 const ANCHOR_NAME: &str = "byocvpn";
 const ANCHOR_FILE: &str = "/tmp/byocvpn-killswitch.pf";
 const ANCHOR_DIRECTIVE: &str = "anchor \"byocvpn\"";
 const PF_CONF: &str = "/etc/pf.conf";
 
-pub fn apply(server_ip: &str, tun_name: &str) -> Result<()> {
+pub fn apply(
+    tunnel: Option<&TunnelEndpoint>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    let block_rules = if split_tunnel.is_full_tunnel() {
+        "block out all\nblock in all\n".to_string()
+    } else {
+        split_tunnel
+            .include_routes
+            .iter()
+            .map(|network| format!("block out to {network}\nblock in from {network}\n"))
+            .collect()
+    };
+    let mut bypass_rules: String = split_tunnel
+        .exclude_routes
+        .iter()
+        .chain(exceptions.allowed_networks().iter())
+        .map(|network| format!("pass out to {network}\npass in from {network}\n"))
+        .collect();
+    if let Some(gateway) = exceptions.captive_portal_gateway() {
+        for (protocol, port) in CAPTIVE_PORTAL_PORTS {
+            bypass_rules.push_str(&format!(
+                "pass out proto {protocol} to {gateway} port {port}\n"
+            ));
+        }
+    }
+
+    let mut tunnel_rules = String::new();
+    if let Some(tunnel) = tunnel {
+        tunnel_rules.push_str(&format!(
+            "pass out proto udp to {} port 51820\n",
+            tunnel.server_ip
+        ));
+        if let Some(tun_name) = tunnel.tun_name {
+            tunnel_rules.push_str(&format!("pass on {tun_name}\n"));
+        }
+    }
+
     let anchor_rules = format!(
-        "pass quick on lo0 all no state\nblock out all\nblock in all\npass out proto udp to {server_ip} port 51820\npass on {tun_name}\n"
+        "pass quick on lo0 all no state\n{block_rules}{bypass_rules}{tunnel_rules}"
     );
 
     std::fs::write(ANCHOR_FILE, &anchor_rules).map_err(|error| SystemError::KillSwitchFailed {
@@ -20,7 +63,11 @@ pub fn apply(server_ip: &str, tun_name: &str) -> Result<()> {
     run_pfctl(&["-a", ANCHOR_NAME, "-f", ANCHOR_FILE])?;
     enable_pf();
 
-    info!("[KillSwitch] pf anchor applied (server={}, tun={})", server_ip, tun_name);
+    info!(
+        "[KillSwitch] pf anchor applied (server={:?}, tun={:?})",
+        tunnel.map(|tunnel| tunnel.server_ip),
+        tunnel.and_then(|tunnel| tunnel.tun_name)
+    );
     Ok(())
 }
 
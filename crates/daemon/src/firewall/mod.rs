@@ -1,5 +1,9 @@
-use byocvpn_core::error::Result;
-use std::sync::Mutex;
+use byocvpn_core::{
+    daemon_client::SplitTunnelConfig,
+    error::{Result, SystemError},
+};
+use ipnet::IpNet;
+use std::{net::IpAddr, sync::Mutex};
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -8,31 +12,259 @@ mod linux;
 #[cfg(target_os = "windows")]
 mod windows;
 
+const LAN_NETWORKS: [&str; 5] = [
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "169.254.0.0/16",
+    "fe80::/10",
+];
+
+/// Protocol and port pairs a captive portal needs on the gateway: DNS over
+/// both protocols, then HTTP and HTTPS. Every backend renders this list.
+pub const CAPTIVE_PORTAL_PORTS: [(&str, u16); 4] =
+    [("udp", 53), ("tcp", 53), ("tcp", 80), ("tcp", 443)];
+
+/// Holes punched into the kill switch on top of the tunnel itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KillSwitchExceptions {
+    /// Pass RFC1918, link-local and the local gateway.
+    pub allow_lan: bool,
+    /// Open HTTP(S) and DNS to the gateway so a captive portal can be used.
+    pub captive_portal: bool,
+    pub gateway: Option<IpAddr>,
+}
+
+impl KillSwitchExceptions {
+    pub const fn none() -> Self {
+        Self {
+            allow_lan: false,
+            captive_portal: false,
+            gateway: None,
+        }
+    }
+
+    /// Networks allowed in both directions outside the tunnel.
+    pub fn allowed_networks(&self) -> Vec<IpNet> {
+        if !self.allow_lan {
+            return Vec::new();
+        }
+        LAN_NETWORKS
+            .iter()
+            .filter_map(|network| network.parse().ok())
+            .chain(self.gateway.map(IpNet::from))
+            .collect()
+    }
+
+    pub fn captive_portal_gateway(&self) -> Option<IpAddr> {
+        self.gateway.filter(|_| self.captive_portal)
+    }
+}
+
+/// The WireGuard peer and interface the kill switch lets traffic through.
+/// The interface is absent while the tunnel is still being set up.
+pub struct TunnelEndpoint<'a> {
+    pub server_ip: &'a str,
+    pub tun_name: Option<&'a str>,
+}
+
 pub struct KillSwitchState {
     pub server_ip: Option<String>,
     pub tun_name: Option<String>,
+    pub split_tunnel: Option<SplitTunnelConfig>,
+    pub exceptions: KillSwitchExceptions,
+    /// Block rules are installed with no tunnel to let through.
+    pub lockdown: bool,
+    /// Current answers for `split_tunnel.include_domains`, guarded like the
+    /// included networks.
+    pub domain_addresses: Vec<IpNet>,
 }
 
 pub static KILL_SWITCH: Mutex<KillSwitchState> = Mutex::new(KillSwitchState {
     server_ip: None,
     tun_name: None,
+    split_tunnel: None,
+    exceptions: KillSwitchExceptions::none(),
+    lockdown: false,
+    domain_addresses: Vec::new(),
 });
 
-pub fn apply(server_ip: &str, tun_name: &str) -> Result<()> {
-    #[cfg(target_os = "macos")]
-    macos::apply(server_ip, tun_name)?;
-    #[cfg(target_os = "linux")]
-    linux::apply(server_ip, tun_name)?;
-    #[cfg(target_os = "windows")]
-    windows::apply(server_ip, tun_name)?;
+/// Blocks traffic that would leave outside the tunnel. Excluded networks are
+/// always allowed out; in include-only split mode only the included networks
+/// are guarded and everything else flows normally.
+pub fn apply(
+    server_ip: &str,
+    tun_name: &str,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    let tunnel = TunnelEndpoint {
+        server_ip,
+        tun_name: Some(tun_name),
+    };
+    let domain_addresses = KILL_SWITCH
+        .lock()
+        .map(|state| state.domain_addresses.clone())
+        .unwrap_or_default();
+    apply_rules(
+        Some(&tunnel),
+        &with_domain_addresses(split_tunnel, &domain_addresses),
+        exceptions,
+    )?;
 
     if let Ok(mut state) = KILL_SWITCH.lock() {
         state.server_ip = Some(server_ip.to_string());
         state.tun_name = Some(tun_name.to_string());
+        state.split_tunnel = Some(split_tunnel.clone());
+        state.exceptions = exceptions.clone();
+        state.lockdown = false;
+    }
+    Ok(())
+}
+
+/// Blocks everything except loopback and the given exceptions, with no tunnel
+/// to let through. Used by lockdown mode while disconnected.
+pub fn apply_lockdown(exceptions: &KillSwitchExceptions) -> Result<()> {
+    apply_rules(None, &SplitTunnelConfig::default(), exceptions)?;
+
+    if let Ok(mut state) = KILL_SWITCH.lock() {
+        state.server_ip = None;
+        state.tun_name = None;
+        state.split_tunnel = None;
+        state.exceptions = exceptions.clone();
+        state.lockdown = true;
+        state.domain_addresses.clear();
     }
     Ok(())
 }
 
+/// Opens the WireGuard port to `server_ip` in the lockdown rules, so the
+/// server can be reached before the tunnel interface exists. Returns whether
+/// lockdown rules were up to open; undo with [`close_lockdown_endpoint`].
+pub fn open_lockdown_endpoint(server_ip: &str) -> Result<bool> {
+    let exceptions = {
+        let state = KILL_SWITCH
+            .lock()
+            .map_err(|_| SystemError::MutexPoisoned("KILL_SWITCH".to_string()))?;
+        if !state.lockdown {
+            return Ok(false);
+        }
+        state.exceptions.clone()
+    };
+
+    let tunnel = TunnelEndpoint {
+        server_ip,
+        tun_name: None,
+    };
+    apply_rules(Some(&tunnel), &SplitTunnelConfig::default(), &exceptions)?;
+    Ok(true)
+}
+
+pub fn close_lockdown_endpoint() -> Result<()> {
+    apply_lockdown(&current_exceptions())
+}
+
+/// Records the addresses the included domains currently resolve to and
+/// reprograms the kill switch if it is guarding a tunnel, so traffic to them
+/// fails closed like traffic to the included networks.
+pub fn set_domain_addresses(domain_addresses: Vec<IpNet>) -> Result<()> {
+    let tunnel = {
+        let mut state = KILL_SWITCH
+            .lock()
+            .map_err(|_| SystemError::MutexPoisoned("KILL_SWITCH".to_string()))?;
+        state.domain_addresses = domain_addresses;
+        match (&state.server_ip, &state.tun_name, &state.split_tunnel) {
+            (Some(server_ip), Some(tun_name), Some(split_tunnel)) => (
+                server_ip.clone(),
+                tun_name.clone(),
+                split_tunnel.clone(),
+                state.exceptions.clone(),
+            ),
+            _ => return Ok(()),
+        }
+    };
+
+    let (server_ip, tun_name, split_tunnel, exceptions) = tunnel;
+    apply(&server_ip, &tun_name, &split_tunnel, &exceptions)
+}
+
+fn with_domain_addresses(
+    split_tunnel: &SplitTunnelConfig,
+    domain_addresses: &[IpNet],
+) -> SplitTunnelConfig {
+    let mut guarded = split_tunnel.clone();
+    guarded
+        .include_routes
+        .extend(domain_addresses.iter().copied());
+    guarded
+}
+
+fn apply_rules(
+    tunnel: Option<&TunnelEndpoint>,
+    split_tunnel: &SplitTunnelConfig,
+    exceptions: &KillSwitchExceptions,
+) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    macos::apply(tunnel, split_tunnel, exceptions)?;
+    #[cfg(target_os = "linux")]
+    linux::apply(tunnel, split_tunnel, exceptions)?;
+    #[cfg(target_os = "windows")]
+    windows::apply(tunnel, split_tunnel, exceptions)?;
+    Ok(())
+}
+
+/// Replaces the exceptions and reprograms the rules if the kill switch or
+/// lockdown is up. Returns whether any rules were active.
+pub fn set_exceptions(exceptions: KillSwitchExceptions) -> Result<bool> {
+    let tunnel = {
+        let mut state = KILL_SWITCH
+            .lock()
+            .map_err(|_| SystemError::MutexPoisoned("KILL_SWITCH".to_string()))?;
+        match (&state.server_ip, &state.tun_name, &state.split_tunnel) {
+            (Some(server_ip), Some(tun_name), Some(split_tunnel)) => {
+                Some((server_ip.clone(), tun_name.clone(), split_tunnel.clone()))
+            }
+            _ if state.lockdown => None,
+            _ => {
+                state.exceptions = exceptions;
+                return Ok(false);
+            }
+        }
+    };
+
+    match tunnel {
+        Some((server_ip, tun_name, split_tunnel)) => {
+            apply(&server_ip, &tun_name, &split_tunnel, &exceptions)?
+        }
+        None => apply_lockdown(&exceptions)?,
+    }
+    Ok(true)
+}
+
+/// Whether kill switch rules are installed, and whether they are lockdown rules.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KillSwitchStatus {
+    pub active: bool,
+    pub lockdown: bool,
+}
+
+pub fn kill_switch_status() -> KillSwitchStatus {
+    KILL_SWITCH
+        .lock()
+        .map(|state| KillSwitchStatus {
+            active: state.server_ip.is_some() || state.lockdown,
+            lockdown: state.lockdown,
+        })
+        .unwrap_or_default()
+}
+
+pub fn current_exceptions() -> KillSwitchExceptions {
+    KILL_SWITCH
+        .lock()
+        .map(|state| state.exceptions.clone())
+        .unwrap_or_default()
+}
+
 pub fn remove() -> Result<()> {
     #[cfg(target_os = "macos")]
     macos::remove()?;
@@ -44,6 +276,10 @@ pub fn remove() -> Result<()> {
     if let Ok(mut state) = KILL_SWITCH.lock() {
         state.server_ip = None;
         state.tun_name = None;
+        state.split_tunnel = None;
+        state.exceptions = KillSwitchExceptions::none();
+        state.lockdown = false;
+        state.domain_addresses.clear();
     }
     Ok(())
 }
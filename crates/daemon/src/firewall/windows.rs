@@ -1,6 +1,12 @@
-use byocvpn_core::error::Result;
+use byocvpn_core::{daemon_client::SplitTunnelConfig, error::Result};
 
-pub fn apply(_server_ip: &str, _tun_name: &str) -> Result<()> {
+use super::{KillSwitchExceptions, TunnelEndpoint};
+
+pub fn apply(
+    _tunnel: Option<&TunnelEndpoint>,
+    _split_tunnel: &SplitTunnelConfig,
+    _exceptions: &KillSwitchExceptions,
+) -> Result<()> {
     Ok(())
 }
 
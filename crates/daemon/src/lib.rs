@@ -1,3 +1,5 @@
+#[cfg(unix)]
+mod authorization;
 pub mod firewall;
 mod tunnel_manager;
 
@@ -5,5 +7,11 @@ pub mod constants;
 pub mod daemon_client;
 
 pub mod daemon;
+mod events;
+mod metrics_exporter;
+#[cfg(target_os = "linux")]
+pub mod netns;
 mod routing;
 pub mod vpn;
+#[cfg(target_os = "linux")]
+mod wireguard_kernel;
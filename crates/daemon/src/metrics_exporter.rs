@@ -0,0 +1,261 @@
+use std::{
+    fmt::Write as _,
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use byocvpn_core::tunnel::VpnStatus;
+use log::*;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{firewall, vpn::status::get_vpn_status};
+
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// How long a client gets to send its request headers, and to take the
+/// response, before the connection is closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the tunnel state in the Prometheus text format on
+/// `127.0.0.1:<port>/metrics`. Does nothing unless a port is configured.
+pub fn spawn_metrics_exporter(port: Option<u16>) {
+    let Some(port) = port else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let listener = match TcpListener::bind(address).await {
+            Ok(listener) => {
+                info!("[Exporter] Serving metrics on http://{}/metrics", address);
+                listener
+            }
+            Err(error) => {
+                error!("[Exporter] Failed to bind {}: {}", address, error);
+                return;
+            }
+        };
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(error) = serve_request(stream).await {
+                            debug!("[Exporter] Request failed: {}", error);
+                        }
+                    });
+                }
+                Err(error) => warn!("[Exporter] Failed to accept connection: {}", error),
+            }
+        }
+    });
+}
+
+async fn serve_request(mut stream: TcpStream) -> std::io::Result<()> {
+    let request = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(Some(request))) => request,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(error)) => return Err(error),
+        Err(_) => {
+            debug!("[Exporter] Timed out reading request");
+            return Ok(());
+        }
+    };
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            render_metrics().await,
+        ),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    match tokio::time::timeout(REQUEST_TIMEOUT, stream.write_all(response.as_bytes())).await {
+        Ok(result) => result?,
+        Err(_) => {
+            debug!("[Exporter] Timed out writing response");
+            return Ok(());
+        }
+    }
+    stream.shutdown().await
+}
+
+/// Reads up to the end of the request headers. Returns `None` when the client
+/// hangs up first or sends more than `MAX_REQUEST_SIZE` bytes of headers.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<Vec<u8>>> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+            return Ok(None);
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    Ok(Some(request))
+}
+
+/// Reads the same state as the `Status` command, plus the kill switch.
+async fn render_metrics() -> String {
+    let status = match get_vpn_status().await {
+        Ok(status) => status,
+        Err(error) => {
+            warn!("[Exporter] Failed to read tunnel status: {}", error);
+            VpnStatus {
+                connected: false,
+                instance: None,
+                metrics: None,
+                connected_at: None,
+                connection_error: Some(error.to_string()),
+                reconnecting: false,
+                backend: None,
+                mtu: None,
+                mtu_probe_error: None,
+            }
+        }
+    };
+    let kill_switch = firewall::kill_switch_status();
+
+    let labels = match &status.instance {
+        Some(instance) => format!(
+            "{{provider=\"{}\",region=\"{}\"}}",
+            escape_label(&instance.provider.to_string()),
+            escape_label(&instance.region)
+        ),
+        None => String::new(),
+    };
+
+    let mut output = String::new();
+    write_metric(
+        &mut output,
+        "byocvpn_tunnel_up",
+        "gauge",
+        "Whether the tunnel is connected.",
+        &labels,
+        status.connected as u64,
+    );
+    write_metric(
+        &mut output,
+        "byocvpn_tunnel_reconnecting",
+        "gauge",
+        "Whether the daemon is rebuilding the tunnel.",
+        &labels,
+        status.reconnecting as u64,
+    );
+    write_metric(
+        &mut output,
+        "byocvpn_kill_switch_active",
+        "gauge",
+        "Whether kill switch rules are installed.",
+        "",
+        kill_switch.active as u64,
+    );
+    write_metric(
+        &mut output,
+        "byocvpn_lockdown_active",
+        "gauge",
+        "Whether lockdown is blocking traffic with no tunnel up.",
+        "",
+        kill_switch.lockdown as u64,
+    );
+
+    if let Some(metrics) = &status.metrics {
+        write_metric(
+            &mut output,
+            "byocvpn_sent_bytes_total",
+            "counter",
+            "Bytes sent through the tunnel.",
+            &labels,
+            metrics.bytes_sent,
+        );
+        write_metric(
+            &mut output,
+            "byocvpn_received_bytes_total",
+            "counter",
+            "Bytes received through the tunnel.",
+            &labels,
+            metrics.bytes_received,
+        );
+        write_metric(
+            &mut output,
+            "byocvpn_sent_packets_total",
+            "counter",
+            "Packets sent through the tunnel.",
+            &labels,
+            metrics.packets_sent,
+        );
+        write_metric(
+            &mut output,
+            "byocvpn_received_packets_total",
+            "counter",
+            "Packets received through the tunnel.",
+            &labels,
+            metrics.packets_received,
+        );
+        write_metric(
+            &mut output,
+            "byocvpn_reconnects_total",
+            "counter",
+            "Times the daemon rebuilt the tunnel since connecting.",
+            &labels,
+            metrics.reconnect_count,
+        );
+        write_metric(
+            &mut output,
+            "byocvpn_handshake_attempts",
+            "gauge",
+            "Handshake initiations sent since the last completed handshake.",
+            &labels,
+            metrics.handshake_attempts,
+        );
+
+        if let Some(last_handshake_at) = metrics.last_handshake_at {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            write_metric(
+                &mut output,
+                "byocvpn_handshake_age_seconds",
+                "gauge",
+                "Seconds since the last completed handshake.",
+                &labels,
+                now.saturating_sub(last_handshake_at),
+            );
+        }
+    }
+
+    output
+}
+
+fn write_metric(
+    output: &mut String,
+    name: &str,
+    metric_type: &str,
+    help: &str,
+    labels: &str,
+    value: u64,
+) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, metric_type);
+    let _ = writeln!(output, "{}{} {}", name, labels, value);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,384 @@
+use std::{
+    ffi::{CStr, CString},
+    fs::File,
+    io,
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::{ffi::OsStrExt, process::CommandExt},
+    },
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+use byocvpn_core::error::{Result, SystemError};
+use ipnet::IpNet;
+use log::*;
+
+use crate::authorization;
+
+const NETNS_RUN_DIR: &str = "/var/run/netns";
+const NETNS_ETC_DIR: &str = "/etc/netns";
+/// Dropped into `/etc/netns/<name>` so only namespaces byocvpn created are
+/// ever recreated or deleted.
+const OWNER_MARKER_FILE: &str = ".byocvpn";
+/// Interface-name limit; keeps names usable everywhere `ip` takes them.
+const MAX_NAMESPACE_NAME_LENGTH: usize = 15;
+
+/// Owns a network namespace holding the tunnel interface. Only processes
+/// started inside it (see [`exec_in_namespace`]) use the VPN; the host keeps
+/// its own routes and DNS.
+#[derive(Debug)]
+pub struct NetworkNamespaceGuard {
+    name: String,
+    active: bool,
+}
+
+impl NetworkNamespaceGuard {
+    pub fn create(
+        name: &str,
+        interface_name: &str,
+        private_ipv4: IpNet,
+        private_ipv6: IpNet,
+        dns_servers: &[String],
+    ) -> Result<Self> {
+        validate_namespace_name(name)?;
+        info!(
+            "[Netns] Moving {} into network namespace {}",
+            interface_name, name
+        );
+
+        if namespace_exists(name) {
+            if !is_owned(name) {
+                return Err(SystemError::NetworkNamespaceFailed {
+                    reason: format!("namespace {} exists and was not created by byocvpn", name),
+                }
+                .into());
+            }
+            warn!(
+                "[Netns] Namespace {} left over from an earlier session, recreating it",
+                name
+            );
+            let _ = run_ip(&["netns", "del", name]);
+        }
+        run_ip(&["netns", "add", name])?;
+        if let Err(error) = write_owner_marker(name) {
+            let _ = run_ip(&["netns", "del", name]);
+            return Err(error);
+        }
+
+        let mut guard = Self {
+            name: name.to_string(),
+            active: true,
+        };
+
+        if let Err(error) = guard.configure(interface_name, private_ipv4, private_ipv6, dns_servers)
+        {
+            if let Err(teardown_error) = guard.teardown() {
+                warn!("[Netns] Cleanup after failed setup failed: {}", teardown_error);
+            }
+            return Err(error);
+        }
+
+        info!("[Netns] Namespace {} ready", name);
+        Ok(guard)
+    }
+
+    fn configure(
+        &self,
+        interface_name: &str,
+        private_ipv4: IpNet,
+        private_ipv6: IpNet,
+        dns_servers: &[String],
+    ) -> Result<()> {
+        let name = self.name.as_str();
+        let ipv4 = private_ipv4.to_string();
+        let ipv6 = private_ipv6.to_string();
+
+        // Moving the link flushes its addresses; the open TUN fd keeps working.
+        run_ip(&["link", "set", interface_name, "netns", name])?;
+        run_ip(&["-n", name, "addr", "add", &ipv4, "dev", interface_name])?;
+        run_ip(&["-n", name, "-6", "addr", "add", &ipv6, "dev", interface_name, "nodad"])?;
+        run_ip(&["-n", name, "link", "set", "lo", "up"])?;
+        run_ip(&["-n", name, "link", "set", interface_name, "up"])?;
+        run_ip(&["-n", name, "route", "add", "default", "dev", interface_name])?;
+        run_ip(&["-n", name, "-6", "route", "add", "default", "dev", interface_name])?;
+
+        write_resolv_conf(name, dns_servers)
+    }
+
+    pub fn teardown(&mut self) -> Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+
+        let resolv_dir = Path::new(NETNS_ETC_DIR).join(&self.name);
+        if let Err(error) = std::fs::remove_dir_all(&resolv_dir)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!(
+                "[Netns] Failed to remove {}: {}",
+                resolv_dir.display(),
+                error
+            );
+        }
+
+        run_ip(&["netns", "del", &self.name])?;
+        self.active = false;
+        info!("[Netns] Namespace {} removed", self.name);
+        Ok(())
+    }
+}
+
+impl Drop for NetworkNamespaceGuard {
+    fn drop(&mut self) {
+        if let Err(error) = self.teardown() {
+            warn!("[Netns] Failed to remove namespace on drop: {}", error);
+        }
+    }
+}
+
+pub fn namespace_exists(name: &str) -> bool {
+    Path::new(NETNS_RUN_DIR).join(name).exists()
+}
+
+/// Accepts `[A-Za-z0-9_-]{1,15}`, so a name can never be read as an `ip`
+/// option or escape `/etc/netns` when joined onto it.
+pub fn validate_namespace_name(name: &str) -> Result<()> {
+    let is_valid = !name.is_empty()
+        && name.len() <= MAX_NAMESPACE_NAME_LENGTH
+        && name.chars().all(|character| {
+            character.is_ascii_alphanumeric() || character == '_' || character == '-'
+        });
+    if !is_valid {
+        return Err(SystemError::NetworkNamespaceFailed {
+            reason: format!(
+                "invalid namespace name {:?}; use 1-{} letters, digits, '_' or '-'",
+                name, MAX_NAMESPACE_NAME_LENGTH
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn is_owned(name: &str) -> bool {
+    Path::new(NETNS_ETC_DIR)
+        .join(name)
+        .join(OWNER_MARKER_FILE)
+        .exists()
+}
+
+fn write_owner_marker(name: &str) -> Result<()> {
+    let namespace_dir = Path::new(NETNS_ETC_DIR).join(name);
+    std::fs::create_dir_all(&namespace_dir).map_err(|error| {
+        SystemError::NetworkNamespaceFailed {
+            reason: format!("failed to create {}: {}", namespace_dir.display(), error),
+        }
+    })?;
+    let marker_path = namespace_dir.join(OWNER_MARKER_FILE);
+    std::fs::write(&marker_path, b"").map_err(|error| SystemError::NetworkNamespaceFailed {
+        reason: format!("failed to write {}: {}", marker_path.display(), error),
+    })?;
+    Ok(())
+}
+
+/// Runs `command` inside the namespace and waits for it, as the user who
+/// invoked `exec` through sudo or pkexec rather than as root. Like
+/// `ip netns exec`, the namespace's resolv.conf is bind-mounted over
+/// `/etc/resolv.conf` in a private mount namespace.
+pub fn exec_in_namespace(name: &str, command: &[String]) -> Result<ExitStatus> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(SystemError::NetworkNamespaceFailed {
+            reason: "no command given".to_string(),
+        }
+        .into());
+    };
+
+    validate_namespace_name(name)?;
+    if !namespace_exists(name) {
+        return Err(SystemError::NetworkNamespaceFailed {
+            reason: format!("namespace {} not found; connect with --namespace first", name),
+        }
+        .into());
+    }
+
+    let namespace_path = Path::new(NETNS_RUN_DIR).join(name);
+    let namespace_file =
+        File::open(&namespace_path).map_err(|error| SystemError::NetworkNamespaceFailed {
+            reason: format!("failed to open {}: {}", namespace_path.display(), error),
+        })?;
+    let resolv_conf = Path::new(NETNS_ETC_DIR).join(name).join("resolv.conf");
+    let resolv_conf = resolv_conf
+        .exists()
+        .then(|| CString::new(resolv_conf.as_os_str().as_bytes()).ok())
+        .flatten();
+    let user = invoking_user()?;
+
+    let mut child = Command::new(program);
+    child.args(args);
+    if let Some(user) = &user {
+        let user_name = user.name.to_string_lossy().into_owned();
+        child
+            .env("USER", &user_name)
+            .env("LOGNAME", &user_name)
+            .env("HOME", user.home.to_string_lossy().as_ref());
+    }
+
+    debug!(
+        "[Netns] Running {} in namespace {} as uid {}",
+        program,
+        name,
+        user.as_ref().map_or(0, |user| user.uid)
+    );
+    let namespace_fd = namespace_file.as_raw_fd();
+    // SAFETY: the closure only makes raw syscalls on data prepared before
+    // the fork, which is what `pre_exec` requires.
+    unsafe {
+        child
+            .pre_exec(move || enter_namespace(namespace_fd, resolv_conf.as_deref(), user.as_ref()));
+    }
+    let status = child
+        .status()
+        .map_err(|error| SystemError::NetworkNamespaceFailed {
+            reason: format!("failed to run {}: {}", program, error),
+        })?;
+    drop(namespace_file);
+    Ok(status)
+}
+
+/// Runs `task` inside the namespace on a thread of its own, which exits
+/// afterwards so no pooled thread is left in the wrong namespace.
+pub(crate) fn run_in_namespace<T, F>(name: &str, task: F) -> io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+{
+    let namespace_file = File::open(Path::new(NETNS_RUN_DIR).join(name))?;
+    std::thread::spawn(move || {
+        if unsafe { libc::setns(namespace_file.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        task()
+    })
+    .join()
+    .map_err(|_| io::Error::other("namespace thread panicked"))?
+}
+
+/// Who `exec` runs its command as.
+struct InvokingUser {
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    groups: Vec<libc::gid_t>,
+    name: CString,
+    home: CString,
+}
+
+/// The user behind sudo or pkexec, or `None` when started as root directly.
+fn invoking_user() -> Result<Option<InvokingUser>> {
+    let Ok(uid) = std::env::var("SUDO_UID").or_else(|_| std::env::var("PKEXEC_UID")) else {
+        return Ok(None);
+    };
+    let uid: libc::uid_t = uid
+        .parse()
+        .map_err(|_| SystemError::NetworkNamespaceFailed {
+            reason: format!("invalid invoking uid {}", uid),
+        })?;
+    if uid == 0 {
+        return Ok(None);
+    }
+
+    let entry =
+        authorization::user_entry(uid).ok_or_else(|| SystemError::NetworkNamespaceFailed {
+            reason: format!("no passwd entry for invoking uid {}", uid),
+        })?;
+    let gid = std::env::var("SUDO_GID")
+        .ok()
+        .and_then(|gid| gid.parse().ok())
+        .unwrap_or(entry.primary_group_id);
+    Ok(Some(InvokingUser {
+        uid,
+        gid,
+        groups: authorization::group_list(&entry.name, entry.primary_group_id),
+        name: entry.name,
+        home: entry.home,
+    }))
+}
+
+/// Runs in the forked child: joins the namespace, mounts its resolv.conf and
+/// drops to `user`, in that order since the first two need root.
+fn enter_namespace(
+    namespace_fd: RawFd,
+    resolv_conf: Option<&CStr>,
+    user: Option<&InvokingUser>,
+) -> io::Result<()> {
+    unsafe {
+        if libc::setns(namespace_fd, libc::CLONE_NEWNET) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(resolv_conf) = resolv_conf
+            && (libc::unshare(libc::CLONE_NEWNS) != 0
+                || libc::mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_SLAVE | libc::MS_REC,
+                    std::ptr::null(),
+                ) != 0
+                || libc::mount(
+                    resolv_conf.as_ptr(),
+                    c"/etc/resolv.conf".as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_BIND,
+                    std::ptr::null(),
+                ) != 0)
+        {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(user) = user
+            && (libc::setgroups(user.groups.len(), user.groups.as_ptr()) != 0
+                || libc::setgid(user.gid) != 0
+                || libc::setuid(user.uid) != 0)
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn write_resolv_conf(name: &str, dns_servers: &[String]) -> Result<()> {
+    if dns_servers.is_empty() {
+        warn!("[Netns] No DNS servers in config; namespace will use the host resolv.conf.");
+        return Ok(());
+    }
+
+    let resolv_dir = Path::new(NETNS_ETC_DIR).join(name);
+    let contents: String = dns_servers
+        .iter()
+        .map(|server| format!("nameserver {server}\n"))
+        .collect();
+    let resolv_path = resolv_dir.join("resolv.conf");
+    std::fs::write(&resolv_path, contents).map_err(|error| SystemError::NetworkNamespaceFailed {
+        reason: format!("failed to write {}: {}", resolv_path.display(), error),
+    })?;
+
+    debug!("[Netns] Wrote {}", resolv_path.display());
+    Ok(())
+}
+
+fn run_ip(args: &[&str]) -> Result<()> {
+    let output = Command::new("ip")
+        .args(args)
+        .output()
+        .map_err(|error| SystemError::NetworkNamespaceFailed {
+            reason: format!("ip exec failed: {}", error),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SystemError::NetworkNamespaceFailed {
+            reason: format!("ip {}: {}", args.join(" "), stderr.trim()),
+        }
+        .into());
+    }
+    Ok(())
+}
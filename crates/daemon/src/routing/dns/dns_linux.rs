@@ -1,17 +1,28 @@
-use std::{io, process::Command};
+use std::{io, path::Path, process::Command};
 
 use byocvpn_core::error::{ConfigurationError, Result};
 use log::*;
 
-const TUN_INTERFACE_NAME: &str = "tun0";
+mod network_manager;
+mod resolv_conf;
+mod resolved;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DnsBackend {
+    Resolved,
+    NetworkManager,
+    ResolvConf,
+}
 
 #[derive(Debug)]
 pub struct DnsOverrideGuard {
+    backend: DnsBackend,
+    interface_name: String,
     dns_override_active: bool,
 }
 
 impl DnsOverrideGuard {
-    pub fn override_system_dns(new_dns_servers: &[&str]) -> Result<Self> {
+    pub fn override_system_dns(interface_name: &str, new_dns_servers: &[&str]) -> Result<Self> {
         if new_dns_servers.is_empty() {
             return Err(ConfigurationError::DnsConfiguration {
                 reason: "desired DNS servers list is empty".to_string(),
@@ -19,62 +30,23 @@ impl DnsOverrideGuard {
             .into());
         }
 
+        let backend = detect_backend();
         info!(
-            "Setting DNS servers on {} via resolvectl: {:?}",
-            TUN_INTERFACE_NAME, new_dns_servers
+            "Setting DNS servers on {} via {:?}: {:?}",
+            interface_name, backend, new_dns_servers
         );
 
-        let mut set_dns_command = Command::new("resolvectl");
-        set_dns_command.arg("dns").arg(TUN_INTERFACE_NAME);
-        for server in new_dns_servers {
-            set_dns_command.arg(server);
-        }
-
-        debug!("Executing: {:?}", set_dns_command);
-        let set_dns_output =
-            set_dns_command
-                .output()
-                .map_err(|error| ConfigurationError::DnsConfiguration {
-                    reason: format!("failed to run resolvectl dns: {}", error),
-                })?;
-
-        if !set_dns_output.status.success() {
-            return Err(ConfigurationError::DnsConfiguration {
-                reason: format!(
-                    "resolvectl dns failed: {}",
-                    String::from_utf8_lossy(&set_dns_output.stderr)
-                ),
-            }
-            .into());
-        }
-
-        let mut set_domain_command = Command::new("resolvectl");
-        set_domain_command
-            .arg("domain")
-            .arg(TUN_INTERFACE_NAME)
-            .arg("~.");
-
-        debug!("Executing: {:?}", set_domain_command);
-        let set_domain_output =
-            set_domain_command
-                .output()
-                .map_err(|error| ConfigurationError::DnsConfiguration {
-                    reason: format!("failed to run resolvectl domain: {}", error),
-                })?;
-
-        if !set_domain_output.status.success() {
-            return Err(ConfigurationError::DnsConfiguration {
-                reason: format!(
-                    "resolvectl domain failed: {}",
-                    String::from_utf8_lossy(&set_domain_output.stderr)
-                ),
-            }
-            .into());
+        match backend {
+            DnsBackend::Resolved => resolved::apply(interface_name, new_dns_servers)?,
+            DnsBackend::NetworkManager => network_manager::apply(new_dns_servers)?,
+            DnsBackend::ResolvConf => resolv_conf::apply(new_dns_servers)?,
         }
 
-        info!("DNS successfully configured on {}", TUN_INTERFACE_NAME);
+        info!("DNS successfully configured on {}", interface_name);
 
         Ok(Self {
+            backend,
+            interface_name: interface_name.to_string(),
             dns_override_active: true,
         })
     }
@@ -85,28 +57,18 @@ impl DnsOverrideGuard {
         }
 
         info!(
-            "Reverting DNS settings on {} via resolvectl",
-            TUN_INTERFACE_NAME
+            "Reverting DNS settings on {} via {:?}",
+            self.interface_name, self.backend
         );
 
-        let mut revert_command = Command::new("resolvectl");
-        revert_command.arg("revert").arg(TUN_INTERFACE_NAME);
-
-        debug!("Executing: {:?}", revert_command);
-        let revert_output = revert_command.output()?;
-
-        if !revert_output.status.success() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "resolvectl revert failed: {}",
-                    String::from_utf8_lossy(&revert_output.stderr)
-                ),
-            ));
+        match self.backend {
+            DnsBackend::Resolved => resolved::revert(&self.interface_name)?,
+            DnsBackend::NetworkManager => network_manager::revert()?,
+            DnsBackend::ResolvConf => resolv_conf::revert()?,
         }
 
         self.dns_override_active = false;
-        info!("DNS restoration completed on {}", TUN_INTERFACE_NAME);
+        info!("DNS restoration completed on {}", self.interface_name);
         Ok(())
     }
 }
@@ -118,3 +80,43 @@ impl Drop for DnsOverrideGuard {
         }
     }
 }
+
+/// Undoes DNS changes left behind by a daemon that exited without restoring
+/// them. Resolved link settings vanish with the interface, so only the file
+/// based backends need this.
+pub fn restore_stale_dns_configuration() {
+    if let Err(error) = network_manager::revert_if_stale() {
+        warn!("Failed to remove stale NetworkManager DNS override: {}", error);
+    }
+    if let Err(error) = resolv_conf::revert_if_stale() {
+        warn!("Failed to restore resolv.conf backup: {}", error);
+    }
+}
+
+fn detect_backend() -> DnsBackend {
+    if Path::new("/run/systemd/resolve").is_dir() && command_succeeds("resolvectl", &["status"]) {
+        DnsBackend::Resolved
+    } else if command_output("nmcli", &["-t", "-f", "RUNNING", "general"])
+        .is_some_and(|output| output.trim() == "running")
+    {
+        DnsBackend::NetworkManager
+    } else {
+        DnsBackend::ResolvConf
+    }
+}
+
+fn command_succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,60 @@
+use std::{io, path::Path, process::Command};
+
+use byocvpn_core::error::{ConfigurationError, Result};
+use log::*;
+
+// A global DNS domain overrides every connection's DNS until it is removed.
+const DROP_IN_PATH: &str = "/etc/NetworkManager/conf.d/99-byocvpn-dns.conf";
+
+pub fn apply(new_dns_servers: &[&str]) -> Result<()> {
+    let contents = format!(
+        "[global-dns-domain-*]\nservers={}\n",
+        new_dns_servers.join(",")
+    );
+    std::fs::write(DROP_IN_PATH, contents).map_err(|error| {
+        ConfigurationError::DnsConfiguration {
+            reason: format!("failed to write {}: {}", DROP_IN_PATH, error),
+        }
+    })?;
+
+    reload().map_err(|error| {
+        let _ = std::fs::remove_file(DROP_IN_PATH);
+        ConfigurationError::DnsConfiguration {
+            reason: error.to_string(),
+        }
+        .into()
+    })
+}
+
+pub fn revert() -> io::Result<()> {
+    match std::fs::remove_file(DROP_IN_PATH) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+    reload()
+}
+
+pub fn revert_if_stale() -> io::Result<()> {
+    if !Path::new(DROP_IN_PATH).exists() {
+        return Ok(());
+    }
+    info!("Removing stale NetworkManager DNS override {}", DROP_IN_PATH);
+    revert()
+}
+
+fn reload() -> io::Result<()> {
+    let mut reload_command = Command::new("nmcli");
+    reload_command.args(["general", "reload", "conf", "dns-full"]);
+
+    debug!("Executing: {:?}", reload_command);
+    let output = reload_command.output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "nmcli general reload failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::{io, os::unix::fs::symlink, path::Path};
+
+use byocvpn_core::error::{ConfigurationError, Result};
+use log::*;
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+// Kept next to resolv.conf so both renames stay on one filesystem.
+const BACKUP_PATH: &str = "/etc/resolv.conf.byocvpn-backup";
+const STAGING_PATH: &str = "/etc/resolv.conf.byocvpn-staging";
+
+pub fn apply(new_dns_servers: &[&str]) -> Result<()> {
+    // A backup from a previous run is the real original; keep it.
+    if !Path::new(BACKUP_PATH).exists() {
+        backup().map_err(|error| ConfigurationError::DnsConfiguration {
+            reason: format!("failed to back up {}: {}", RESOLV_CONF, error),
+        })?;
+    }
+
+    let contents: String = new_dns_servers
+        .iter()
+        .map(|server| format!("nameserver {server}\n"))
+        .collect();
+    replace_atomically(RESOLV_CONF, contents.as_bytes()).map_err(|error| {
+        ConfigurationError::DnsConfiguration {
+            reason: format!("failed to write {}: {}", RESOLV_CONF, error),
+        }
+        .into()
+    })
+}
+
+pub fn revert() -> io::Result<()> {
+    if !Path::new(BACKUP_PATH).exists() {
+        warn!("No resolv.conf backup at {}; leaving it as is", BACKUP_PATH);
+        return Ok(());
+    }
+    std::fs::rename(BACKUP_PATH, RESOLV_CONF)
+}
+
+pub fn revert_if_stale() -> io::Result<()> {
+    if !Path::new(BACKUP_PATH).exists() {
+        return Ok(());
+    }
+    info!("Restoring {} from stale backup", RESOLV_CONF);
+    revert()
+}
+
+// Symlinks (e.g. to a resolvconf-managed file) are backed up as symlinks so
+// the rename in `revert` puts the original link back.
+fn backup() -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(RESOLV_CONF)?;
+    if metadata.file_type().is_symlink() {
+        let target = std::fs::read_link(RESOLV_CONF)?;
+        let _ = std::fs::remove_file(STAGING_PATH);
+        symlink(&target, STAGING_PATH)?;
+        std::fs::rename(STAGING_PATH, BACKUP_PATH)
+    } else {
+        let contents = std::fs::read(RESOLV_CONF)?;
+        replace_atomically(BACKUP_PATH, &contents)
+    }
+}
+
+fn replace_atomically(path: &str, contents: &[u8]) -> io::Result<()> {
+    std::fs::write(STAGING_PATH, contents)?;
+    std::fs::rename(STAGING_PATH, path)
+}
@@ -0,0 +1,54 @@
+use std::{io, process::Command};
+
+use byocvpn_core::error::{ConfigurationError, Result};
+use log::*;
+
+pub fn apply(interface_name: &str, new_dns_servers: &[&str]) -> Result<()> {
+    let mut set_dns_command = Command::new("resolvectl");
+    set_dns_command.arg("dns").arg(interface_name);
+    for server in new_dns_servers {
+        set_dns_command.arg(server);
+    }
+    run_resolvectl(set_dns_command, "dns")?;
+
+    let mut set_domain_command = Command::new("resolvectl");
+    set_domain_command.arg("domain").arg(interface_name).arg("~.");
+    run_resolvectl(set_domain_command, "domain")
+}
+
+pub fn revert(interface_name: &str) -> io::Result<()> {
+    let mut revert_command = Command::new("resolvectl");
+    revert_command.arg("revert").arg(interface_name);
+
+    debug!("Executing: {:?}", revert_command);
+    let revert_output = revert_command.output()?;
+
+    if !revert_output.status.success() {
+        return Err(io::Error::other(format!(
+            "resolvectl revert failed: {}",
+            String::from_utf8_lossy(&revert_output.stderr)
+        )));
+    }
+    Ok(())
+}
+
+fn run_resolvectl(mut command: Command, verb: &str) -> Result<()> {
+    debug!("Executing: {:?}", command);
+    let output = command
+        .output()
+        .map_err(|error| ConfigurationError::DnsConfiguration {
+            reason: format!("failed to run resolvectl {}: {}", verb, error),
+        })?;
+
+    if !output.status.success() {
+        return Err(ConfigurationError::DnsConfiguration {
+            reason: format!(
+                "resolvectl {} failed: {}",
+                verb,
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -10,7 +10,7 @@ pub struct DnsOverrideGuard {
 }
 
 impl DnsOverrideGuard {
-    pub fn override_system_dns(new_dns_servers: &[&str]) -> Result<Self> {
+    pub fn override_system_dns(_interface_name: &str, new_dns_servers: &[&str]) -> Result<Self> {
         if new_dns_servers.is_empty() {
             return Err(ConfigurationError::DnsConfiguration {
                 reason: "desired DNS servers list is empty".to_string(),
@@ -6,8 +6,12 @@ mod dns_macos;
 mod dns_windows;
 
 #[cfg(target_os = "linux")]
-pub use dns_linux::DnsOverrideGuard;
+pub use dns_linux::{DnsOverrideGuard, restore_stale_dns_configuration};
 #[cfg(target_os = "macos")]
 pub use dns_macos::DnsOverrideGuard;
 #[cfg(windows)]
 pub use dns_windows::DnsOverrideGuard;
+
+/// Crash recovery is only implemented for the Linux backends.
+#[cfg(not(target_os = "linux"))]
+pub fn restore_stale_dns_configuration() {}
@@ -0,0 +1,132 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    time::Duration,
+};
+
+use ipnet::IpNet;
+use log::*;
+use tokio::{net::lookup_host, sync::watch, task::JoinHandle};
+
+use crate::{
+    firewall,
+    routing::routes::{add_interface_route, delete_interface_route},
+    tunnel_manager::STATE_CHANGE_LOCK,
+};
+
+const DOMAIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keeps host routes through the tunnel, and the kill switch's drop rules, in
+/// sync with the current DNS answers for the configured hostnames. Routes are
+/// removed when the task stops.
+pub fn spawn_domain_route_task(
+    domains: Vec<String>,
+    interface_index: u32,
+    mut domain_routes_shutdown_rx: watch::Receiver<()>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut resolved: HashMap<String, HashSet<IpAddr>> = HashMap::new();
+        let mut routed: HashSet<IpAddr> = HashSet::new();
+        let mut guarded: HashSet<IpAddr> = HashSet::new();
+        let mut interval = tokio::time::interval(DOMAIN_REFRESH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    refresh_resolved_addresses(&domains, &mut resolved).await;
+                    sync_domain_routes(&resolved, &mut routed, interface_index).await;
+                    sync_kill_switch(&resolved, &mut guarded);
+                }
+                _ = domain_routes_shutdown_rx.changed() => {
+                    info!("[DomainRoutes] Stopping.");
+                    break;
+                }
+            }
+        }
+
+        for address in routed.drain() {
+            let destination = IpNet::from(address).to_string();
+            if let Err(error) = delete_interface_route(&destination, interface_index).await {
+                warn!(
+                    "[DomainRoutes] Failed to remove route {}: {}",
+                    destination, error
+                );
+            }
+        }
+    })
+}
+
+async fn refresh_resolved_addresses(
+    domains: &[String],
+    resolved: &mut HashMap<String, HashSet<IpAddr>>,
+) {
+    for domain in domains {
+        match lookup_host((domain.as_str(), 0)).await {
+            Ok(addresses) => {
+                let addresses: HashSet<IpAddr> = addresses.map(|address| address.ip()).collect();
+                if resolved.get(domain) != Some(&addresses) {
+                    debug!("[DomainRoutes] {} resolved to {:?}", domain, addresses);
+                }
+                resolved.insert(domain.clone(), addresses);
+            }
+            Err(error) => {
+                // Keep the previous answer so a transient DNS failure does not
+                // pull traffic out of the tunnel.
+                warn!("[DomainRoutes] Failed to resolve {}: {}", domain, error);
+            }
+        }
+    }
+}
+
+fn sync_kill_switch(resolved: &HashMap<String, HashSet<IpAddr>>, guarded: &mut HashSet<IpAddr>) {
+    let desired: HashSet<IpAddr> = resolved.values().flatten().copied().collect();
+    if desired == *guarded {
+        return;
+    }
+    // Disconnect holds the lock while it waits for this task to stop, so
+    // never wait for it here; the next refresh tries again.
+    let Ok(_state_change_guard) = STATE_CHANGE_LOCK.try_lock() else {
+        return;
+    };
+    match firewall::set_domain_addresses(desired.iter().copied().map(IpNet::from).collect()) {
+        Ok(()) => *guarded = desired,
+        Err(error) => warn!(
+            "[DomainRoutes] Failed to update kill switch rules: {}",
+            error
+        ),
+    }
+}
+
+async fn sync_domain_routes(
+    resolved: &HashMap<String, HashSet<IpAddr>>,
+    routed: &mut HashSet<IpAddr>,
+    interface_index: u32,
+) {
+    let desired: HashSet<IpAddr> = resolved.values().flatten().copied().collect();
+
+    for address in desired.difference(routed).copied().collect::<Vec<_>>() {
+        let destination = IpNet::from(address).to_string();
+        match add_interface_route(&destination, interface_index).await {
+            Ok(()) => {
+                routed.insert(address);
+            }
+            Err(error) => warn!(
+                "[DomainRoutes] Failed to add route {}: {}",
+                destination, error
+            ),
+        }
+    }
+
+    for address in routed.difference(&desired).copied().collect::<Vec<_>>() {
+        let destination = IpNet::from(address).to_string();
+        match delete_interface_route(&destination, interface_index).await {
+            Ok(()) => {
+                routed.remove(&address);
+            }
+            Err(error) => warn!(
+                "[DomainRoutes] Failed to remove route {}: {}",
+                destination, error
+            ),
+        }
+    }
+}
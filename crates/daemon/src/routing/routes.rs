@@ -1,9 +1,41 @@
-use byocvpn_core::error::{ConfigurationError, Result};
+use std::net::IpAddr;
+
+use byocvpn_core::{
+    daemon_client::SplitTunnelConfig,
+    error::{ConfigurationError, Result},
+};
 use ipnet::IpNet;
 use log::*;
 use net_route::{Handle, Route};
 
-pub async fn add_vpn_routes(interface_index: u32, server_ip: &str) -> Result<()> {
+const FULL_TUNNEL_ROUTES: [&str; 4] = ["0.0.0.0/1", "128.0.0.0/1", "::/1", "8000::/1"];
+
+/// Destinations routed through the tunnel interface. In split mode the DNS
+/// servers are always included so lookups do not leak around the tunnel.
+pub fn tunneled_routes(split_tunnel: &SplitTunnelConfig, dns_servers: &[String]) -> Vec<IpNet> {
+    if split_tunnel.is_full_tunnel() {
+        return FULL_TUNNEL_ROUTES
+            .iter()
+            .filter_map(|destination| destination.parse().ok())
+            .collect();
+    }
+
+    let mut routes = split_tunnel.include_routes.clone();
+    for dns_server in dns_servers {
+        match dns_server.parse::<IpAddr>() {
+            Ok(address) => routes.push(IpNet::from(address)),
+            Err(error) => warn!("Skipping DNS server route {}: {}", dns_server, error),
+        }
+    }
+    routes
+}
+
+pub async fn add_vpn_routes(
+    interface_index: u32,
+    server_ip: &str,
+    tunneled: &[IpNet],
+    bypassed: &[IpNet],
+) -> Result<()> {
     info!(
         "Adding VPN routes for server {} via interface index {}",
         server_ip, interface_index
@@ -15,8 +47,16 @@ pub async fn add_vpn_routes(interface_index: u32, server_ip: &str) -> Result<()>
         warn!("Failed to add gateway route {}: {}", server_route, error);
     }
 
-    for destination in ["0.0.0.0/1", "128.0.0.0/1", "::/1", "8000::/1"] {
-        if let Err(error) = add_interface_route(destination, interface_index).await {
+    for destination in bypassed {
+        let destination = destination.to_string();
+        if let Err(error) = add_default_gateway_route(&destination).await {
+            warn!("Failed to add bypass route {}: {}", destination, error);
+        }
+    }
+
+    for destination in tunneled {
+        let destination = destination.to_string();
+        if let Err(error) = add_interface_route(&destination, interface_index).await {
             warn!(
                 "Failed to add interface route {} via index {}: {}",
                 destination, interface_index, error
@@ -28,7 +68,12 @@ pub async fn add_vpn_routes(interface_index: u32, server_ip: &str) -> Result<()>
     Ok(())
 }
 
-pub async fn remove_vpn_routes(interface_index: u32, server_ip: &str) {
+pub async fn remove_vpn_routes(
+    interface_index: u32,
+    server_ip: &str,
+    tunneled: &[IpNet],
+    bypassed: &[IpNet],
+) {
     info!(
         "Removing VPN routes for server {} via interface index {}",
         server_ip, interface_index
@@ -40,8 +85,16 @@ pub async fn remove_vpn_routes(interface_index: u32, server_ip: &str) {
         warn!("Failed to remove gateway route {}: {}", server_route, error);
     }
 
-    for destination in ["0.0.0.0/1", "128.0.0.0/1", "::/1", "8000::/1"] {
-        if let Err(error) = delete_interface_route(destination, interface_index).await {
+    for destination in bypassed {
+        let destination = destination.to_string();
+        if let Err(error) = delete_default_gateway_route(&destination).await {
+            warn!("Failed to remove bypass route {}: {}", destination, error);
+        }
+    }
+
+    for destination in tunneled {
+        let destination = destination.to_string();
+        if let Err(error) = delete_interface_route(&destination, interface_index).await {
             warn!(
                 "Failed to remove interface route {} via index {}: {}",
                 destination, interface_index, error
@@ -106,7 +159,7 @@ async fn add_default_gateway_route(destination: &str) -> Result<()> {
     }
 }
 
-async fn add_interface_route(destination: &str, interface_index: u32) -> Result<()> {
+pub async fn add_interface_route(destination: &str, interface_index: u32) -> Result<()> {
     debug!(
         "Adding interface route: {} via index {}",
         destination, interface_index
@@ -211,7 +264,7 @@ async fn delete_default_gateway_route(destination: &str) -> Result<()> {
     }
 }
 
-async fn delete_interface_route(destination: &str, interface_index: u32) -> Result<()> {
+pub async fn delete_interface_route(destination: &str, interface_index: u32) -> Result<()> {
     debug!(
         "Deleting interface route: {} via index {}",
         destination, interface_index
@@ -261,9 +314,27 @@ async fn delete_interface_route(destination: &str, interface_index: u32) -> Resu
     }
 }
 
+pub async fn default_gateway() -> Option<IpAddr> {
+    let handle = match Handle::new() {
+        Ok(handle) => handle,
+        Err(error) => {
+            warn!("Failed to create route handle: {}", error);
+            return None;
+        }
+    };
+
+    handle
+        .default_route()
+        .await
+        .ok()
+        .flatten()
+        .and_then(|route| route.gateway)
+}
+
 pub async fn update_server_host_route(
     server_ip: &str,
-    last_gateway: &mut Option<std::net::IpAddr>,
+    bypassed: &[IpNet],
+    last_gateway: &mut Option<IpAddr>,
 ) {
     let handle = match Handle::new() {
         Ok(handle) => handle,
@@ -291,14 +362,18 @@ pub async fn update_server_host_route(
     *last_gateway = current_gateway;
 
     let server_route = format!("{}/32", server_ip);
+    let gateway_routes = std::iter::once(server_route)
+        .chain(bypassed.iter().map(|destination| destination.to_string()));
 
-    if let Err(error) = delete_default_gateway_route(&server_route).await {
-        error!("[RouteMonitor] Failed to delete old host route: {}", error);
-    }
+    for destination in gateway_routes {
+        if let Err(error) = delete_default_gateway_route(&destination).await {
+            error!("[RouteMonitor] Failed to delete old route {}: {}", destination, error);
+        }
 
-    if let Err(error) = add_default_gateway_route(&server_route).await {
-        error!("[RouteMonitor] Failed to re-add host route: {}", error);
-    } else {
-        info!("[RouteMonitor] Host route for {} refreshed.", server_ip);
+        if let Err(error) = add_default_gateway_route(&destination).await {
+            error!("[RouteMonitor] Failed to re-add route {}: {}", destination, error);
+        } else {
+            info!("[RouteMonitor] Gateway route for {} refreshed.", destination);
+        }
     }
 }
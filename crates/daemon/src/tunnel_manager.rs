@@ -1,12 +1,15 @@
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
-use byocvpn_core::tunnel::{ConnectedInstance, TunnelMetrics};
+use byocvpn_core::tunnel::{ConnectedInstance, TunnelBackend, TunnelMetrics};
+use ipnet::IpNet;
 use tokio::{
     sync::{RwLock, watch},
     task::JoinHandle,
 };
 
+#[cfg(target_os = "linux")]
+use crate::netns::NetworkNamespaceGuard;
 use crate::routing::dns::DnsOverrideGuard;
 
 pub struct TunnelHandle {
@@ -17,12 +20,26 @@ pub struct TunnelHandle {
     pub metrics_shutdown: watch::Sender<()>,
     pub route_monitor_task: JoinHandle<()>,
     pub route_monitor_shutdown: watch::Sender<()>,
+    pub domain_routes_task: Option<JoinHandle<()>>,
+    pub domain_routes_shutdown: Option<watch::Sender<()>>,
     pub dns_override_guard: Option<DnsOverrideGuard>,
+    #[cfg(target_os = "linux")]
+    pub network_namespace_guard: Option<NetworkNamespaceGuard>,
     pub server_ip: String,
     pub interface_index: u32,
+    pub tunneled_routes: Vec<IpNet>,
+    pub bypassed_routes: Vec<IpNet>,
     pub interface_name: String,
     pub instance: Option<ConnectedInstance>,
     pub connected_at: SystemTime,
+    pub backend: TunnelBackend,
+    pub mtu: u16,
+    /// Why the path MTU probe fell back to the default, if it did.
+    pub mtu_probe_error: Option<String>,
 }
 
 pub static TUNNEL_MANAGER: Mutex<Option<TunnelHandle>> = Mutex::new(None);
+
+/// Held across every connect, disconnect and firewall change, so concurrent
+/// clients cannot interleave half-built tunnel, route, DNS or firewall state.
+pub static STATE_CHANGE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
@@ -5,10 +5,10 @@ use boringtun::{
     x25519::{PublicKey, StaticSecret},
 };
 use byocvpn_core::{
-    daemon_client::VpnConnectParams,
+    daemon_client::{DaemonEvent, VpnConnectParams},
     error::{ConfigurationError, Result, SystemError},
     ipc::{IpcSocket, IpcStream},
-    tunnel::{ConnectedInstance, Tunnel, TunnelMetrics},
+    tunnel::{ConnectedInstance, Tunnel, TunnelBackend, TunnelMetrics},
 };
 use futures::StreamExt;
 use ipnet::IpNet;
@@ -16,20 +16,35 @@ use log::*;
 use net_route::Handle as RouteHandle;
 use tokio::{
     net::UdpSocket,
-    sync::{RwLock, watch},
+    sync::{
+        RwLock,
+        mpsc::{self, error::TrySendError},
+        watch,
+    },
     task::JoinHandle,
 };
 use tun_rs::{AsyncDevice, DeviceBuilder};
 
+#[cfg(target_os = "linux")]
+use crate::{
+    netns::NetworkNamespaceGuard,
+    wireguard_kernel::{KernelWireGuard, KernelWireGuardConfig, run_kernel_tunnel},
+};
 use crate::{
-    constants,
-    firewall,
+    constants, events,
+    firewall::{self, KillSwitchExceptions},
     routing::{
         dns::DnsOverrideGuard,
-        routes::{add_vpn_routes, update_server_host_route},
+        domains::spawn_domain_route_task,
+        routes::{add_vpn_routes, default_gateway, tunneled_routes, update_server_host_route},
     },
     tunnel_manager::{TUNNEL_MANAGER, TunnelHandle},
-    vpn::session::{self, PersistedSession},
+    vpn::{
+        history, lockdown,
+        mtu_probe::probe_path_mtu,
+        session::{self, PersistedSession},
+        supervisor::TunnelSupervisor,
+    },
 };
 
 pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
@@ -37,6 +52,7 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
         instance_id,
         private_key,
         public_key,
+        preshared_key,
         server_endpoint,
         private_ipv4,
         private_ipv6,
@@ -46,6 +62,11 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
         public_ip_v4,
         public_ip_v6,
         kill_switch_enabled,
+        split_tunnel,
+        network_namespace,
+        allow_lan,
+        mtu,
+        probe_mtu,
     } = params;
 
     info!(
@@ -67,45 +88,189 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
         .into());
     }
 
-    let (tun, interface_index) = setup_tun_device(private_ipv4, private_ipv6)?;
-    let interface_name = tun.name().unwrap_or_else(|_| format!("tun{}", interface_index));
+    #[cfg(not(target_os = "linux"))]
+    if network_namespace.is_some() {
+        return Err(ConfigurationError::TunnelConfiguration {
+            reason: "Network namespaces are only supported on Linux".to_string(),
+        }
+        .into());
+    }
+    let namespaced = network_namespace.is_some();
+
+    if let Some(mtu) = mtu
+        && !(constants::MIN_TUNNEL_MTU..=constants::MAX_TUNNEL_MTU).contains(&mtu)
+    {
+        return Err(ConfigurationError::InvalidValue {
+            field: "mtu".to_string(),
+            reason: format!(
+                "must be between {} and {}",
+                constants::MIN_TUNNEL_MTU,
+                constants::MAX_TUNNEL_MTU
+            ),
+        }
+        .into());
+    }
+
+    let mut mtu_probe_error = None;
+    let mtu = if probe_mtu {
+        // Lockdown blocks the server until the kill switch is programmed, so
+        // open just its WireGuard port for the probe.
+        let endpoint_opened = firewall::open_lockdown_endpoint(&server_endpoint.ip().to_string())?;
+        let probed = probe_path_mtu(
+            &private_key,
+            &public_key,
+            preshared_key.as_deref(),
+            server_endpoint,
+            private_ipv4,
+            mtu,
+        )
+        .await;
+        if endpoint_opened {
+            firewall::close_lockdown_endpoint()?;
+        }
+
+        match probed {
+            Ok(probed) => probed,
+            Err(error) => {
+                let fallback = mtu.unwrap_or(constants::TUNNEL_MTU);
+                warn!("{}; using MTU {}", error, fallback);
+                mtu_probe_error = Some(error.to_string());
+                fallback
+            }
+        }
+    } else {
+        mtu.unwrap_or(constants::TUNNEL_MTU)
+    };
+    info!("Tunnel MTU: {}", mtu);
+
+    let (data_plane, interface_name, interface_index) = create_data_plane(
+        &private_key,
+        &public_key,
+        preshared_key.as_deref(),
+        server_endpoint,
+        private_ipv4,
+        private_ipv6,
+        mtu,
+    )?;
+
+    #[cfg(target_os = "linux")]
+    let network_namespace_guard = match network_namespace.as_deref() {
+        Some(namespace) => Some(NetworkNamespaceGuard::create(
+            namespace,
+            &interface_name,
+            private_ipv4,
+            private_ipv6,
+            &dns_servers,
+        )?),
+        None => None,
+    };
 
-    if kill_switch_enabled {
-        if let Err(error) = firewall::apply(&server_endpoint.ip().to_string(), &interface_name) {
+    let lockdown_enabled = lockdown::is_enabled();
+    if namespaced && !lockdown_enabled {
+        info!("Tunnel is namespaced; leaving host firewall, routes and DNS untouched.");
+    } else if kill_switch_enabled || lockdown_enabled {
+        let exceptions = KillSwitchExceptions {
+            allow_lan,
+            gateway: if allow_lan { default_gateway().await } else { None },
+            ..KillSwitchExceptions::none()
+        };
+        if let Err(error) = firewall::apply(
+            &server_endpoint.ip().to_string(),
+            &interface_name,
+            &split_tunnel,
+            &exceptions,
+        ) {
             warn!("Kill switch apply failed: {}", error);
         }
     } else {
         warn!("Session kill switch disabled; traffic can leak if the tunnel drops.");
     }
 
-    let wireguard_tunnel = create_wireguard_tunnel(private_key, public_key)?;
-    let udp = connect_udp_socket(server_endpoint).await?;
-
     let (shutdown_tx, shutdown_rx) = watch::channel(());
-    let mut tunnel = Tunnel::new(tun, udp, wireguard_tunnel, shutdown_rx);
-    let metrics = tunnel.metrics.clone();
-
-    let task = tokio::spawn(async move {
-        if let Err(error) = tunnel.run().await {
-            error!("Tunnel exited: {error}");
+    let (task, metrics, backend) = match data_plane {
+        DataPlane::Userspace(tun) => {
+            let wireguard_tunnel =
+                create_wireguard_tunnel(&private_key, &public_key, preshared_key.as_deref())?;
+            let udp = connect_udp_socket(server_endpoint).await?;
+
+            let tunnel = Tunnel::new(tun, udp, wireguard_tunnel, shutdown_rx.clone());
+            let metrics = tunnel.metrics.clone();
+
+            let supervisor = TunnelSupervisor::new(
+                tunnel,
+                server_endpoint,
+                private_key,
+                public_key,
+                preshared_key,
+                shutdown_rx,
+            );
+            (
+                tokio::spawn(supervisor.run()),
+                metrics,
+                TunnelBackend::Userspace,
+            )
         }
-    });
+        #[cfg(target_os = "linux")]
+        DataPlane::Kernel(device) => {
+            let metrics = Arc::new(RwLock::new(TunnelMetrics::default()));
+            let task = tokio::spawn(run_kernel_tunnel(
+                device,
+                network_namespace.clone(),
+                metrics.clone(),
+                shutdown_rx,
+            ));
+            (task, metrics, TunnelBackend::Kernel)
+        }
+    };
+    info!("Tunnel backend: {:?}", backend);
 
+    history::start_session(instance_id.clone(), provider.clone(), region.clone());
     let (metrics_shutdown_tx, metrics_shutdown_rx) = watch::channel(());
     let metrics_task = spawn_metrics_task(metrics.clone(), metrics_shutdown_rx);
 
-    let mut manager = TUNNEL_MANAGER
-        .lock()
-        .map_err(|_| SystemError::MutexPoisoned("TUNNEL_MANAGER".to_string()))?;
-
     info!("Adding VPN routes...");
-    add_vpn_routes(interface_index, &server_endpoint.ip().to_string()).await?;
+    let (tunneled_routes, bypassed_routes) = if namespaced {
+        (Vec::new(), Vec::new())
+    } else {
+        (
+            tunneled_routes(&split_tunnel, &dns_servers),
+            split_tunnel.exclude_routes.clone(),
+        )
+    };
+    add_vpn_routes(
+        interface_index,
+        &server_endpoint.ip().to_string(),
+        &tunneled_routes,
+        &bypassed_routes,
+    )
+    .await?;
 
     let (route_monitor_shutdown_tx, route_monitor_shutdown_rx) = watch::channel(());
-    let route_monitor_task =
-        spawn_route_monitor_task(server_endpoint.ip().to_string(), route_monitor_shutdown_rx);
+    let route_monitor_task = spawn_route_monitor_task(
+        server_endpoint.ip().to_string(),
+        bypassed_routes.clone(),
+        route_monitor_shutdown_rx,
+    );
+
+    let (domain_routes_shutdown, domain_routes_task) = if namespaced
+        || split_tunnel.include_domains.is_empty()
+    {
+        (None, None)
+    } else {
+        let (domain_routes_shutdown_tx, domain_routes_shutdown_rx) = watch::channel(());
+        let domain_routes_task = spawn_domain_route_task(
+            split_tunnel.include_domains.clone(),
+            interface_index,
+            domain_routes_shutdown_rx,
+        );
+        (Some(domain_routes_shutdown_tx), Some(domain_routes_task))
+    };
 
-    let dns_override_guard = apply_dns_servers(dns_servers);
+    let dns_override_guard = if namespaced {
+        None
+    } else {
+        apply_dns_servers(&interface_name, dns_servers)
+    };
 
     let persisted_session = PersistedSession {
         instance_id: instance_id.clone(),
@@ -114,8 +279,16 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
         public_ip_v4: public_ip_v4.clone(),
         public_ip_v6: public_ip_v6.clone(),
         kill_switch_enabled,
+        split_tunnel,
+        network_namespace,
+        allow_lan,
+        mtu: Some(mtu),
     };
 
+    let mut manager = TUNNEL_MANAGER
+        .lock()
+        .map_err(|_| SystemError::MutexPoisoned("TUNNEL_MANAGER".to_string()))?;
+
     *manager = Some(TunnelHandle {
         shutdown: shutdown_tx,
         task,
@@ -124,9 +297,15 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
         metrics_shutdown: metrics_shutdown_tx,
         route_monitor_task,
         route_monitor_shutdown: route_monitor_shutdown_tx,
+        domain_routes_task,
+        domain_routes_shutdown,
         dns_override_guard,
+        #[cfg(target_os = "linux")]
+        network_namespace_guard,
         server_ip: server_endpoint.ip().to_string(),
         interface_index,
+        tunneled_routes,
+        bypassed_routes,
         interface_name: interface_name.clone(),
         instance: Some(ConnectedInstance {
             instance_id,
@@ -136,6 +315,9 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
             provider,
         }),
         connected_at: std::time::SystemTime::now(),
+        backend,
+        mtu,
+        mtu_probe_error,
     });
 
     if let Err(error) = session::write_session(&persisted_session) {
@@ -146,11 +328,67 @@ pub async fn connect_vpn(params: VpnConnectParams) -> Result<()> {
     Ok(())
 }
 
-fn setup_tun_device(private_ipv4: IpNet, private_ipv6: IpNet) -> Result<(AsyncDevice, u32)> {
+/// The device carrying tunnel traffic.
+enum DataPlane {
+    Userspace(AsyncDevice),
+    #[cfg(target_os = "linux")]
+    Kernel(KernelWireGuard),
+}
+
+/// Prefers the kernel's WireGuard module on Linux and falls back to a TUN
+/// device driven by boringtun. Returns the device with its name and index.
+fn create_data_plane(
+    private_key: &[u8],
+    public_key: &[u8],
+    preshared_key: Option<&[u8]>,
+    server_endpoint: SocketAddr,
+    private_ipv4: IpNet,
+    private_ipv6: IpNet,
+    mtu: u16,
+) -> Result<(DataPlane, String, u32)> {
+    #[cfg(target_os = "linux")]
+    if constants::kernel_wireguard_allowed() {
+        let config = KernelWireGuardConfig {
+            private_key,
+            public_key,
+            preshared_key,
+            server_endpoint,
+            private_ipv4,
+            private_ipv6,
+            mtu,
+        };
+        match KernelWireGuard::create(&config) {
+            Ok(device) => {
+                let interface_name = device.interface_name().to_string();
+                let interface_index = device.interface_index();
+                return Ok((DataPlane::Kernel(device), interface_name, interface_index));
+            }
+            Err(error) => info!(
+                "Kernel WireGuard unavailable, using userspace tunnel: {}",
+                error
+            ),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = (private_key, public_key, preshared_key, server_endpoint);
+
+    let (tun, interface_index) = setup_tun_device(private_ipv4, private_ipv6, mtu)?;
+    let interface_name = tun
+        .name()
+        .unwrap_or_else(|_| format!("tun{}", interface_index));
+    Ok((DataPlane::Userspace(tun), interface_name, interface_index))
+}
+
+fn setup_tun_device(
+    private_ipv4: IpNet,
+    private_ipv6: IpNet,
+    mtu: u16,
+) -> Result<(AsyncDevice, u32)> {
     let tun = DeviceBuilder::new()
         .ipv4(private_ipv4.addr(), private_ipv4.prefix_len(), None)
         .ipv6(private_ipv6.addr(), private_ipv6.prefix_len())
-        .mtu(constants::TUNNEL_MTU)
+        .mtu(mtu)
         .build_async()
         .map_err(|error| ConfigurationError::TunnelConfiguration {
             reason: format!("Failed to create TUN device: {}", error),
@@ -167,10 +405,13 @@ fn setup_tun_device(private_ipv4: IpNet, private_ipv6: IpNet) -> Result<(AsyncDe
     Ok((tun, interface_index))
 }
 
-fn create_wireguard_tunnel(private_key: Vec<u8>, public_key: Vec<u8>) -> Result<Tunn> {
+pub fn create_wireguard_tunnel(
+    private_key: &[u8],
+    public_key: &[u8],
+    preshared_key: Option<&[u8]>,
+) -> Result<Tunn> {
     let private_key_bytes: [u8; 32] =
         private_key
-            .as_slice()
             .try_into()
             .map_err(|_| ConfigurationError::InvalidValue {
                 field: "private_key".to_string(),
@@ -178,17 +419,24 @@ fn create_wireguard_tunnel(private_key: Vec<u8>, public_key: Vec<u8>) -> Result<
             })?;
     let public_key_bytes: [u8; 32] =
         public_key
-            .as_slice()
             .try_into()
             .map_err(|_| ConfigurationError::InvalidValue {
                 field: "public_key".to_string(),
                 reason: "Public key must be exactly 32 bytes".to_string(),
             })?;
+    let preshared_key_bytes = preshared_key
+        .map(|preshared_key| {
+            <[u8; 32]>::try_from(preshared_key).map_err(|_| ConfigurationError::InvalidValue {
+                field: "preshared_key".to_string(),
+                reason: "Preshared key must be exactly 32 bytes".to_string(),
+            })
+        })
+        .transpose()?;
 
     Tunn::new(
         StaticSecret::from(private_key_bytes),
         PublicKey::from(public_key_bytes),
-        None,
+        preshared_key_bytes,
         Some(25),
         0,
         None,
@@ -201,7 +449,7 @@ fn create_wireguard_tunnel(private_key: Vec<u8>, public_key: Vec<u8>) -> Result<
     })
 }
 
-async fn connect_udp_socket(server_endpoint: SocketAddr) -> Result<UdpSocket> {
+pub async fn connect_udp_socket(server_endpoint: SocketAddr) -> Result<UdpSocket> {
     let local: SocketAddr =
         "0.0.0.0:0"
             .parse()
@@ -237,6 +485,16 @@ async fn connect_udp_socket(server_endpoint: SocketAddr) -> Result<UdpSocket> {
     Ok(udp)
 }
 
+/// The metrics task ticks once a second; history is flushed once a minute.
+const HISTORY_PERSIST_INTERVAL_TICKS: u64 = 60;
+/// Stored sessions are pruned and compacted once an hour while connected.
+const HISTORY_MAINTENANCE_INTERVAL_TICKS: u64 = 60 * 60;
+
+/// Snapshots a metrics client may fall behind by before it is dropped.
+const METRICS_SUBSCRIBER_BUFFER: usize = 8;
+/// How long a single write to a metrics client may block.
+const METRICS_WRITE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
 fn spawn_metrics_task(
     metrics: Arc<RwLock<TunnelMetrics>>,
     mut metrics_shutdown_rx: watch::Receiver<()>,
@@ -261,71 +519,93 @@ fn spawn_metrics_task(
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
         let mut last_metrics = TunnelMetrics::default();
         let mut last_time = tokio::time::Instant::now();
-        let mut connected_stream: Option<IpcStream> = None;
+        let mut subscribers: Vec<mpsc::Sender<Arc<str>>> = Vec::new();
         let mut upload_history: VecDeque<u64> = VecDeque::with_capacity(10);
         let mut download_history: VecDeque<u64> = VecDeque::with_capacity(10);
+        let mut ticks_since_persist: u64 = 0;
+        let mut ticks_since_maintenance: u64 = 0;
 
         loop {
             tokio::select! {
                 Ok(stream) = listener.accept() => {
                     debug!("[Metrics] Client connected to metrics stream");
-                    connected_stream = Some(stream);
+                    let (subscriber_tx, subscriber_rx) = mpsc::channel(METRICS_SUBSCRIBER_BUFFER);
+                    tokio::spawn(serve_metrics_subscriber(stream, subscriber_rx));
+                    subscribers.push(subscriber_tx);
                 }
 
                 _ = interval.tick() => {
-                    if let Some(stream) = connected_stream.as_mut() {
-                        let current_metrics = metrics.read().await.clone();
-                        let now = tokio::time::Instant::now();
-                        let elapsed = now.duration_since(last_time).as_secs_f64();
-
-                        let upload_rate_instant = if elapsed > 0.0 {
-                            ((current_metrics.bytes_sent - last_metrics.bytes_sent) as f64 / elapsed) as u64
-                        } else {
-                            0
-                        };
-                        let download_rate_instant = if elapsed > 0.0 {
-                            ((current_metrics.bytes_received - last_metrics.bytes_received) as f64 / elapsed) as u64
-                        } else {
-                            0
-                        };
-
-                        upload_history.push_back(upload_rate_instant);
-                        download_history.push_back(download_rate_instant);
-                        if upload_history.len() > 10 { upload_history.pop_front(); }
-                        if download_history.len() > 10 { download_history.pop_front(); }
-
-                        let upload_rate = if !upload_history.is_empty() {
-                            upload_history.iter().sum::<u64>() / upload_history.len() as u64
-                        } else { 0 };
-                        let download_rate = if !download_history.is_empty() {
-                            download_history.iter().sum::<u64>() / download_history.len() as u64
-                        } else { 0 };
-
-                        let snapshot = TunnelMetrics {
-                            bytes_sent: current_metrics.bytes_sent,
-                            bytes_received: current_metrics.bytes_received,
-                            packets_sent: current_metrics.packets_sent,
-                            packets_received: current_metrics.packets_received,
-                            upload_rate,
-                            download_rate,
-                        };
-
-                        if let Ok(json) = serde_json::to_string(&snapshot) {
-                            if stream.write_all(json.as_bytes()).await.is_err()
-                                || stream.write_all(b"\n").await.is_err()
-                            {
+                    let current_metrics = metrics.read().await.clone();
+                    let now = tokio::time::Instant::now();
+                    let elapsed = now.duration_since(last_time).as_secs_f64();
+
+                    history::record(
+                        current_metrics.bytes_sent.saturating_sub(last_metrics.bytes_sent),
+                        current_metrics.bytes_received.saturating_sub(last_metrics.bytes_received),
+                    );
+                    ticks_since_persist += 1;
+                    if ticks_since_persist >= HISTORY_PERSIST_INTERVAL_TICKS {
+                        history::persist_active();
+                        ticks_since_persist = 0;
+                    }
+                    ticks_since_maintenance += 1;
+                    if ticks_since_maintenance >= HISTORY_MAINTENANCE_INTERVAL_TICKS {
+                        history::prune_and_compact();
+                        ticks_since_maintenance = 0;
+                    }
+
+                    let upload_rate_instant = if elapsed > 0.0 {
+                        ((current_metrics.bytes_sent - last_metrics.bytes_sent) as f64 / elapsed) as u64
+                    } else {
+                        0
+                    };
+                    let download_rate_instant = if elapsed > 0.0 {
+                        ((current_metrics.bytes_received - last_metrics.bytes_received) as f64 / elapsed) as u64
+                    } else {
+                        0
+                    };
+
+                    upload_history.push_back(upload_rate_instant);
+                    download_history.push_back(download_rate_instant);
+                    if upload_history.len() > 10 { upload_history.pop_front(); }
+                    if download_history.len() > 10 { download_history.pop_front(); }
+
+                    let upload_rate = if !upload_history.is_empty() {
+                        upload_history.iter().sum::<u64>() / upload_history.len() as u64
+                    } else { 0 };
+                    let download_rate = if !download_history.is_empty() {
+                        download_history.iter().sum::<u64>() / download_history.len() as u64
+                    } else { 0 };
+
+                    let snapshot = TunnelMetrics {
+                        upload_rate,
+                        download_rate,
+                        ..current_metrics.clone()
+                    };
+
+                    if let Ok(json) = serde_json::to_string(&snapshot) {
+                        let line: Arc<str> = format!("{}\n", json).into();
+                        subscribers.retain(|subscriber| match subscriber.try_send(line.clone()) {
+                            Ok(()) => true,
+                            Err(TrySendError::Full(_)) => {
+                                debug!("[Metrics] Dropping client that fell behind");
+                                false
+                            }
+                            Err(TrySendError::Closed(_)) => {
                                 debug!("[Metrics] Client disconnected");
-                                connected_stream = None;
+                                false
                             }
-                        }
-
-                        last_metrics = current_metrics;
-                        last_time = now;
+                        });
                     }
+                    events::publish(DaemonEvent::Metrics(snapshot));
+
+                    last_metrics = current_metrics;
+                    last_time = now;
                 }
 
                 _ = metrics_shutdown_rx.changed() => {
                     info!("[Metrics] Stopping metrics streamer");
+                    history::finish_session();
                     break;
                 }
             }
@@ -333,8 +613,24 @@ fn spawn_metrics_task(
     })
 }
 
+/// Writes snapshots to one metrics client so a client that stops reading only
+/// stalls its own task. Ends when the client goes away or gets dropped.
+async fn serve_metrics_subscriber(mut stream: IpcStream, mut lines: mpsc::Receiver<Arc<str>>) {
+    while let Some(line) = lines.recv().await {
+        match tokio::time::timeout(METRICS_WRITE_TIMEOUT, stream.write_all(line.as_bytes())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => break,
+            Err(_) => {
+                debug!("[Metrics] Timed out writing to client");
+                break;
+            }
+        }
+    }
+}
+
 fn spawn_route_monitor_task(
     server_ip: String,
+    bypassed_routes: Vec<IpNet>,
     mut route_monitor_shutdown_rx: watch::Receiver<()>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
@@ -359,7 +655,7 @@ fn spawn_route_monitor_task(
         loop {
             tokio::select! {
                 Some(_event) = StreamExt::next(&mut stream) => {
-                    update_server_host_route(&server_ip, &mut last_gateway).await;
+                    update_server_host_route(&server_ip, &bypassed_routes, &mut last_gateway).await;
                 }
                 _ = route_monitor_shutdown_rx.changed() => {
                     info!("[RouteMonitor] Stopping.");
@@ -370,7 +666,7 @@ fn spawn_route_monitor_task(
     })
 }
 
-fn apply_dns_servers(dns_servers: Vec<String>) -> Option<DnsOverrideGuard> {
+fn apply_dns_servers(interface_name: &str, dns_servers: Vec<String>) -> Option<DnsOverrideGuard> {
     debug!("DNS servers from config: {:?}", dns_servers);
 
     if dns_servers.is_empty() {
@@ -379,7 +675,7 @@ fn apply_dns_servers(dns_servers: Vec<String>) -> Option<DnsOverrideGuard> {
     }
 
     let as_refs: Vec<&str> = dns_servers.iter().map(|s| s.as_str()).collect();
-    match DnsOverrideGuard::override_system_dns(&as_refs) {
+    match DnsOverrideGuard::override_system_dns(interface_name, &as_refs) {
         Ok(guard) => {
             info!("DNS servers applied: {:?}", dns_servers);
             Some(guard)
@@ -1,7 +1,11 @@
 use byocvpn_core::error::{Result, SystemError};
 use log::*;
 
-use crate::{firewall, routing::routes::remove_vpn_routes, tunnel_manager::TUNNEL_MANAGER, vpn::session};
+use crate::{
+    routing::routes::remove_vpn_routes,
+    tunnel_manager::TUNNEL_MANAGER,
+    vpn::{lockdown, session},
+};
 
 pub async fn disconnect_vpn() -> Result<()> {
     info!("[VPN Disconnect] Disconnecting VPN tunnel...");
@@ -12,7 +16,7 @@ pub async fn disconnect_vpn() -> Result<()> {
         .take()
     else {
         warn!("[VPN Disconnect] No active tunnel in memory (daemon restarted?), clearing firewall rules.");
-        if let Err(error) = firewall::remove() {
+        if let Err(error) = lockdown::release_kill_switch().await {
             warn!("[VPN Disconnect] Kill switch removal failed: {}", error);
         }
         if let Err(error) = session::clear_session() {
@@ -35,14 +39,36 @@ pub async fn disconnect_vpn() -> Result<()> {
         ),
     }
 
-    remove_vpn_routes(handle.interface_index, &handle.server_ip).await;
+    if let Some(domain_routes_shutdown) = handle.domain_routes_shutdown.take() {
+        let _ = domain_routes_shutdown.send(());
+    }
+    if let Some(domain_routes_task) = handle.domain_routes_task.take() {
+        match domain_routes_task.await {
+            Ok(_) => info!("[VPN Disconnect] Domain routes removed."),
+            Err(error) => warn!(
+                "[VPN Disconnect] Domain route task panicked while shutting down: {:?}",
+                error
+            ),
+        }
+    }
+
+    remove_vpn_routes(
+        handle.interface_index,
+        &handle.server_ip,
+        &handle.tunneled_routes,
+        &handle.bypassed_routes,
+    )
+    .await;
     info!("[VPN Disconnect] Removed VPN routes.");
 
     if let Some(mut dns_override_guard) =
         handle.dns_override_guard.take()
     {
         match dns_override_guard.restore_previous_dns_configuration() {
-            Ok(_) => info!("[VPN Disconnect] Restored original DNS."),
+            Ok(_) => info!(
+                "[VPN Disconnect] Restored original DNS for {}.",
+                handle.interface_name
+            ),
             Err(error) => warn!("[VPN Disconnect] Failed to restore DNS: {error}"),
         }
     }
@@ -77,7 +103,15 @@ pub async fn disconnect_vpn() -> Result<()> {
         ),
     }
 
-    if let Err(error) = firewall::remove() {
+    #[cfg(target_os = "linux")]
+    if let Some(mut network_namespace_guard) = handle.network_namespace_guard.take() {
+        match network_namespace_guard.teardown() {
+            Ok(_) => info!("[VPN Disconnect] Removed network namespace."),
+            Err(error) => warn!("[VPN Disconnect] Failed to remove network namespace: {}", error),
+        }
+    }
+
+    if let Err(error) = lockdown::release_kill_switch().await {
         warn!("[VPN Disconnect] Kill switch removal failed: {}", error);
     }
 
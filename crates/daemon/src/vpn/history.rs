@@ -0,0 +1,220 @@
+use std::{
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use byocvpn_core::{
+    cloud_provider::CloudProviderName,
+    error::{ConfigurationError, Result, SystemError},
+    traffic_history::{SessionHistory, TrafficSample},
+};
+use log::*;
+
+/// Finished sessions that ended longer ago than this are deleted.
+const HISTORY_RETENTION: u64 = 90 * 24 * 60 * 60;
+
+/// The session currently being recorded. Finished sessions live on disk only.
+static ACTIVE_HISTORY: Mutex<Option<SessionHistory>> = Mutex::new(None);
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn start_session(instance_id: String, provider: CloudProviderName, region: String) {
+    let history = SessionHistory::new(instance_id, provider, region, unix_now());
+    if let Ok(mut active) = ACTIVE_HISTORY.lock() {
+        *active = Some(history);
+    }
+}
+
+pub fn record(bytes_sent: u64, bytes_received: u64) {
+    if let Ok(mut active) = ACTIVE_HISTORY.lock()
+        && let Some(history) = active.as_mut()
+    {
+        history.record(TrafficSample {
+            timestamp: unix_now(),
+            bytes_sent,
+            bytes_received,
+        });
+    }
+}
+
+/// Writes the active session to disk so a crash loses at most one interval.
+pub fn persist_active() {
+    let snapshot = ACTIVE_HISTORY.lock().ok().and_then(|active| active.clone());
+    if let Some(history) = snapshot
+        && let Err(error) = write_history(&history)
+    {
+        warn!("[History] Failed to persist traffic history: {}", error);
+    }
+}
+
+/// Stamps the disconnect time on the active session and writes it out.
+pub fn finish_session() {
+    let finished = ACTIVE_HISTORY
+        .lock()
+        .ok()
+        .and_then(|mut active| active.take());
+    if let Some(mut history) = finished {
+        history.finish(unix_now());
+        if let Err(error) = write_history(&history) {
+            warn!("[History] Failed to persist traffic history: {}", error);
+        }
+    }
+}
+
+/// Ends sessions left open by a daemon that stopped without disconnecting,
+/// at the time of their last sample. Runs before any session is restored.
+pub fn close_stale_sessions() {
+    let Ok(sessions) = read_history_files() else {
+        return;
+    };
+    for (_, mut history) in sessions {
+        if history.disconnected_at.is_some() {
+            continue;
+        }
+        history.finish(history.last_sample_at());
+        match write_history(&history) {
+            Ok(()) => info!(
+                "[History] Closed unfinished session for instance {}",
+                history.instance_id
+            ),
+            Err(error) => warn!("[History] Failed to close stale session: {}", error),
+        }
+    }
+}
+
+/// Deletes finished sessions past the retention window and folds the rest
+/// down as they age. Runs at startup and periodically while connected, so
+/// reads never have to touch the files.
+pub fn prune_and_compact() {
+    let Ok(sessions) = read_history_files() else {
+        return;
+    };
+    let now = unix_now();
+    for (path, mut history) in sessions {
+        let Some(disconnected_at) = history.disconnected_at else {
+            continue;
+        };
+        if disconnected_at < now.saturating_sub(HISTORY_RETENTION) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => debug!("[History] Pruned {}", path.display()),
+                Err(error) => warn!("[History] Failed to prune {}: {}", path.display(), error),
+            }
+            continue;
+        }
+        if history.compact(now)
+            && let Err(error) = write_history(&history)
+        {
+            warn!("[History] Failed to rewrite compacted history: {}", error);
+        }
+    }
+}
+
+/// Every recorded session that was still connected at or after `since`,
+/// oldest first, including the one in progress.
+pub fn query_history(since: Option<u64>) -> Result<Vec<SessionHistory>> {
+    let since = since.unwrap_or_default();
+    let now = unix_now();
+
+    let active = ACTIVE_HISTORY
+        .lock()
+        .map_err(|_| SystemError::MutexPoisoned("ACTIVE_HISTORY".to_string()))?
+        .clone();
+
+    let mut sessions = Vec::new();
+    for (_, history) in read_history_files()? {
+        // Expired sessions stay hidden until the next prune deletes them.
+        if history
+            .disconnected_at
+            .is_some_and(|disconnected_at| disconnected_at < now.saturating_sub(HISTORY_RETENTION))
+        {
+            continue;
+        }
+        sessions.push(history);
+    }
+
+    // The file for the active session may be up to one interval behind.
+    if let Some(active) = active {
+        sessions.retain(|history| history_file_name(history) != history_file_name(&active));
+        sessions.push(active);
+    }
+
+    sessions.retain(|history| history.is_active_since(since));
+    sessions.sort_by_key(|history| history.connected_at);
+    Ok(sessions)
+}
+
+fn read_history_files() -> Result<Vec<(PathBuf, SessionHistory)>> {
+    let directory = byocvpn_core::config::history_dir_path()?;
+    let mut sessions = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(&directory) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // Skips half-written temporary files from `write_history`.
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let Ok(json) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            match serde_json::from_str::<SessionHistory>(&json) {
+                Ok(history) => sessions.push((path, history)),
+                Err(error) => debug!(
+                    "[History] Skipping unreadable {}: {}",
+                    path.display(),
+                    error
+                ),
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+fn history_file_name(history: &SessionHistory) -> String {
+    format!(
+        "{}-{}.json",
+        history.connected_at,
+        history.instance_id.replace('/', "_")
+    )
+}
+
+fn history_file_path(history: &SessionHistory) -> Result<PathBuf> {
+    Ok(byocvpn_core::config::history_dir_path()?.join(history_file_name(history)))
+}
+
+fn write_history(history: &SessionHistory) -> Result<()> {
+    let path = history_file_path(history)?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).map_err(|error| {
+            ConfigurationError::TunnelConfiguration {
+                reason: format!("failed to create history directory: {}", error),
+            }
+        })?;
+    }
+
+    let json = serde_json::to_string(history).map_err(|error| {
+        ConfigurationError::TunnelConfiguration {
+            reason: format!("failed to serialize traffic history: {}", error),
+        }
+    })?;
+    // Written aside and renamed over the file so a crash or a concurrent
+    // reader never sees a partial session.
+    let temporary_path = path.with_extension("json.tmp");
+    std::fs::write(&temporary_path, json.as_bytes()).map_err(|error| {
+        ConfigurationError::TunnelConfiguration {
+            reason: format!("failed to write traffic history: {}", error),
+        }
+    })?;
+    std::fs::rename(&temporary_path, &path).map_err(|error| {
+        ConfigurationError::TunnelConfiguration {
+            reason: format!("failed to replace traffic history: {}", error),
+        }
+    })?;
+    debug!("[History] Traffic history persisted to {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use byocvpn_core::error::{Result, SystemError};
+use log::*;
+
+use crate::{
+    firewall,
+    routing::routes::default_gateway,
+    tunnel_manager::STATE_CHANGE_LOCK,
+    vpn::{lockdown, session},
+};
+
+const MAX_CAPTIVE_PORTAL_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+// Bumped on every open/close so a stale relock timer does not close a window
+// that was reopened after it was scheduled.
+static CAPTIVE_PORTAL_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub async fn set_allow_lan(enabled: bool) -> Result<()> {
+    let mut exceptions = firewall::current_exceptions();
+    exceptions.allow_lan = enabled;
+    if enabled && exceptions.gateway.is_none() {
+        exceptions.gateway = default_gateway().await;
+    }
+
+    let active = firewall::set_exceptions(exceptions)?;
+    info!(
+        "[KillSwitch] LAN access {} (kill switch {})",
+        if enabled { "allowed" } else { "blocked" },
+        if active { "active" } else { "inactive" }
+    );
+
+    if let Some(mut persisted_lockdown) = lockdown::read_lockdown() {
+        persisted_lockdown.allow_lan = enabled;
+        if let Err(error) = lockdown::write_lockdown(&persisted_lockdown) {
+            warn!("[KillSwitch] Failed to persist LAN setting for lockdown: {}", error);
+        }
+    }
+
+    if let Some(mut persisted_session) = session::read_session() {
+        persisted_session.allow_lan = enabled;
+        if let Err(error) = session::write_session(&persisted_session) {
+            warn!("[KillSwitch] Failed to persist LAN setting: {}", error);
+        }
+    }
+    Ok(())
+}
+
+pub async fn open_captive_portal(duration: Duration) -> Result<()> {
+    let gateway = default_gateway()
+        .await
+        .ok_or_else(|| SystemError::KillSwitchFailed {
+            reason: "no default gateway to open a captive portal window to".to_string(),
+        })?;
+
+    let mut exceptions = firewall::current_exceptions();
+    exceptions.captive_portal = true;
+    exceptions.gateway = Some(gateway);
+
+    if !firewall::set_exceptions(exceptions)? {
+        return Err(SystemError::KillSwitchFailed {
+            reason: "kill switch is not active".to_string(),
+        }
+        .into());
+    }
+
+    let duration = duration.min(MAX_CAPTIVE_PORTAL_WINDOW);
+    let generation = CAPTIVE_PORTAL_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    info!(
+        "[KillSwitch] Captive portal window open to {} for {}s",
+        gateway,
+        duration.as_secs()
+    );
+
+    tokio::spawn(async move {
+        tokio::time::sleep(duration).await;
+        let _state_change_guard = STATE_CHANGE_LOCK.lock().await;
+        if CAPTIVE_PORTAL_GENERATION.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        if let Err(error) = close_captive_portal() {
+            error!("[KillSwitch] Failed to close captive portal window: {}", error);
+        }
+    });
+    Ok(())
+}
+
+pub fn close_captive_portal() -> Result<()> {
+    CAPTIVE_PORTAL_GENERATION.fetch_add(1, Ordering::SeqCst);
+
+    let mut exceptions = firewall::current_exceptions();
+    if !exceptions.captive_portal {
+        return Ok(());
+    }
+    exceptions.captive_portal = false;
+    firewall::set_exceptions(exceptions)?;
+    info!("[KillSwitch] Captive portal window closed");
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use byocvpn_core::error::{ConfigurationError, Result};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    firewall::{self, KillSwitchExceptions},
+    routing::routes::default_gateway,
+    tunnel_manager::TUNNEL_MANAGER,
+};
+
+/// Lockdown keeps the kill switch installed while no tunnel is up, from daemon
+/// start until the user turns it off. It is enabled while this file exists.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersistedLockdown {
+    #[serde(default)]
+    pub allow_lan: bool,
+}
+
+pub fn read_lockdown() -> Option<PersistedLockdown> {
+    let path = byocvpn_core::config::lockdown_file_path().ok()?;
+    let json = std::fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&json) {
+        Ok(lockdown) => Some(lockdown),
+        Err(error) => {
+            // A corrupt file must not silently disable lockdown.
+            warn!("Lockdown file unreadable, keeping lockdown on: {}", error);
+            Some(PersistedLockdown::default())
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    read_lockdown().is_some()
+}
+
+pub fn write_lockdown(lockdown: &PersistedLockdown) -> Result<()> {
+    let path = byocvpn_core::config::lockdown_file_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| ConfigurationError::TunnelConfiguration {
+            reason: format!("failed to create lockdown directory: {}", error),
+        })?;
+    }
+    let json = serde_json::to_string(lockdown).map_err(|error| ConfigurationError::TunnelConfiguration {
+        reason: format!("failed to serialize lockdown: {}", error),
+    })?;
+    std::fs::write(&path, json.as_bytes()).map_err(|error| ConfigurationError::TunnelConfiguration {
+        reason: format!("failed to write lockdown file: {}", error),
+    })?;
+    debug!("Lockdown persisted to {}", path.display());
+    Ok(())
+}
+
+fn clear_lockdown() -> Result<()> {
+    let path = byocvpn_core::config::lockdown_file_path()?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(ConfigurationError::TunnelConfiguration {
+            reason: format!("failed to remove lockdown file: {}", error),
+        }
+        .into()),
+    }
+}
+
+/// Installs the lockdown rules if lockdown is persisted. Returns whether it did.
+pub async fn engage() -> Result<bool> {
+    let Some(lockdown) = read_lockdown() else {
+        return Ok(false);
+    };
+
+    let exceptions = KillSwitchExceptions {
+        allow_lan: lockdown.allow_lan,
+        gateway: if lockdown.allow_lan { default_gateway().await } else { None },
+        ..KillSwitchExceptions::none()
+    };
+    firewall::apply_lockdown(&exceptions)?;
+    info!("[Lockdown] Block rules installed (allow_lan={})", lockdown.allow_lan);
+    Ok(true)
+}
+
+/// Lifts the kill switch after a disconnect, unless lockdown keeps it in place.
+pub async fn release_kill_switch() -> Result<()> {
+    match engage().await {
+        Ok(true) => Ok(()),
+        Ok(false) => firewall::remove(),
+        Err(error) => {
+            // Fall back to the plain block so traffic stays blocked.
+            warn!("[Lockdown] Failed to re-engage with exceptions: {}", error);
+            firewall::apply_lockdown(&KillSwitchExceptions::none())
+        }
+    }
+}
+
+pub async fn set_lockdown(enabled: bool) -> Result<()> {
+    let tunnel_active = TUNNEL_MANAGER
+        .lock()
+        .map(|manager| manager.is_some())
+        .unwrap_or(false);
+
+    if enabled {
+        let allow_lan = firewall::current_exceptions().allow_lan;
+        write_lockdown(&PersistedLockdown { allow_lan })?;
+        if !tunnel_active {
+            engage().await?;
+        }
+        info!("[Lockdown] Enabled");
+    } else {
+        clear_lockdown()?;
+        if !tunnel_active {
+            firewall::remove()?;
+        }
+        info!("[Lockdown] Disabled");
+    }
+    Ok(())
+}
@@ -1,6 +1,11 @@
 pub mod connect;
 pub mod disconnect;
+pub mod history;
+pub mod kill_switch;
+pub mod lockdown;
 pub mod metrics;
+pub mod mtu_probe;
 pub mod restore;
 pub mod session;
 pub mod status;
+pub mod supervisor;
@@ -0,0 +1,270 @@
+//! Path MTU discovery through the tunnel. A short-lived WireGuard session is
+//! opened on a socket that sets Don't Fragment on its datagrams, then ICMP
+//! echo requests of increasing size are sent to the server's tunnel address.
+//! The largest request that is answered is the tunnel MTU: anything bigger
+//! was dropped on the way there or back.
+
+use std::{
+    collections::BTreeSet,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use boringtun::noise::{Tunn, TunnResult};
+use byocvpn_core::error::{ConfigurationError, Result, SystemError};
+use ipnet::IpNet;
+use log::*;
+use tokio::{
+    net::UdpSocket,
+    time::{Instant, timeout_at},
+};
+
+use crate::{
+    constants,
+    vpn::connect::{connect_udp_socket, create_wireguard_tunnel},
+};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait for echo replies after each round of probes.
+const ROUND_TIMEOUT: Duration = Duration::from_secs(1);
+const PROBE_ROUNDS: usize = 2;
+const PROBE_STEP: u16 = 8;
+/// The usual Ethernet MTU on the path to the server.
+const TYPICAL_PATH_MTU: u16 = 1500;
+/// Outer IP header plus UDP header plus WireGuard's data header and tag.
+const WIREGUARD_OVERHEAD_IPV4: u16 = 20 + 8 + 32;
+const WIREGUARD_OVERHEAD_IPV6: u16 = 40 + 8 + 32;
+
+const IPV4_HEADER_SIZE: usize = 20;
+const ICMP_HEADER_SIZE: usize = 8;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_PROTOCOL: u8 = 1;
+const PROBE_IDENTIFIER: u16 = 0xb7c0;
+const PACKET_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Returns the largest MTU, up to `ceiling` (or what a 1500-byte path can
+/// carry), whose probes reach the server and come back.
+pub async fn probe_path_mtu(
+    private_key: &[u8],
+    public_key: &[u8],
+    preshared_key: Option<&[u8]>,
+    server_endpoint: SocketAddr,
+    private_ipv4: IpNet,
+    ceiling: Option<u16>,
+) -> Result<u16> {
+    let IpNet::V4(private_ipv4) = private_ipv4 else {
+        return Err(probe_error("tunnel has no IPv4 address to probe from"));
+    };
+    let server_address = private_ipv4
+        .hosts()
+        .next()
+        .ok_or_else(|| probe_error("tunnel network has no server address"))?;
+
+    let overhead = if server_endpoint.is_ipv4() {
+        WIREGUARD_OVERHEAD_IPV4
+    } else {
+        WIREGUARD_OVERHEAD_IPV6
+    };
+    let ceiling = ceiling.unwrap_or(TYPICAL_PATH_MTU - overhead);
+    let candidates = candidate_sizes(ceiling);
+
+    let mut tunn = create_wireguard_tunnel(private_key, public_key, preshared_key)?;
+    let udp = connect_udp_socket(server_endpoint).await?;
+    set_dont_fragment(&udp, server_endpoint)?;
+
+    let mut buffer = vec![0u8; PACKET_BUFFER_SIZE];
+    let mut out_buf = vec![0u8; PACKET_BUFFER_SIZE];
+    handshake(&mut tunn, &udp, &mut buffer, &mut out_buf).await?;
+
+    let mut answered = BTreeSet::new();
+    for round in 0..PROBE_ROUNDS {
+        for &size in candidates.iter().filter(|size| !answered.contains(*size)) {
+            let packet = echo_request(private_ipv4.addr(), server_address, size);
+            if let TunnResult::WriteToNetwork(datagram) = tunn.encapsulate(&packet, &mut out_buf) {
+                // Sizes over the local interface MTU fail here with EMSGSIZE.
+                if let Err(error) = udp.send(datagram).await {
+                    debug!("[MtuProbe] Probe of {} bytes not sent: {}", size, error);
+                }
+            }
+        }
+
+        let deadline = Instant::now() + ROUND_TIMEOUT;
+        while let Ok(Ok(length)) = timeout_at(deadline, udp.recv(&mut buffer)).await {
+            if let TunnResult::WriteToTunnelV4(packet, _) =
+                tunn.decapsulate(None, &buffer[..length], &mut out_buf)
+                && let Some(size) = echo_reply_size(packet, server_address)
+            {
+                answered.insert(size);
+            }
+        }
+        debug!(
+            "[MtuProbe] Round {}: {} of {} sizes answered",
+            round + 1,
+            answered.len(),
+            candidates.len()
+        );
+    }
+
+    let mtu = answered
+        .last()
+        .copied()
+        .ok_or_else(|| probe_error("no probe was answered; the server may not reply to ICMP"))?;
+    info!("[MtuProbe] Path MTU through the tunnel: {}", mtu);
+    Ok(mtu)
+}
+
+fn candidate_sizes(ceiling: u16) -> Vec<u16> {
+    let ceiling = ceiling.clamp(constants::MIN_TUNNEL_MTU, constants::MAX_TUNNEL_MTU);
+    let mut sizes: Vec<u16> = (constants::MIN_TUNNEL_MTU..ceiling)
+        .step_by(PROBE_STEP as usize)
+        .collect();
+    sizes.push(ceiling);
+    sizes
+}
+
+async fn handshake(
+    tunn: &mut Tunn,
+    udp: &UdpSocket,
+    buffer: &mut [u8],
+    out_buf: &mut [u8],
+) -> Result<()> {
+    if let TunnResult::WriteToNetwork(packet) = tunn.format_handshake_initiation(out_buf, false) {
+        udp.send(packet)
+            .await
+            .map_err(|error| SystemError::TunnelIoFailed {
+                reason: error.to_string(),
+            })?;
+    }
+
+    let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+    while tunn.time_since_last_handshake().is_none() {
+        let length = match timeout_at(deadline, udp.recv(buffer)).await {
+            Ok(Ok(length)) => length,
+            Ok(Err(error)) => {
+                debug!("[MtuProbe] UDP receive failed: {}", error);
+                continue;
+            }
+            Err(_) => return Err(probe_error("handshake timed out")),
+        };
+        if let TunnResult::WriteToNetwork(packet) =
+            tunn.decapsulate(None, &buffer[..length], out_buf)
+        {
+            let _ = udp.send(packet).await;
+        }
+    }
+    Ok(())
+}
+
+fn echo_request(source: Ipv4Addr, destination: Ipv4Addr, size: u16) -> Vec<u8> {
+    let mut packet = vec![0u8; size as usize];
+
+    packet[0] = 0x45;
+    packet[2..4].copy_from_slice(&size.to_be_bytes());
+    packet[6] = 0x40; // Don't Fragment
+    packet[8] = 64;
+    packet[9] = ICMP_PROTOCOL;
+    packet[12..16].copy_from_slice(&source.octets());
+    packet[16..20].copy_from_slice(&destination.octets());
+    let checksum = internet_checksum(&packet[..IPV4_HEADER_SIZE]);
+    packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    // The sequence number carries the probe size back in the reply.
+    let icmp = &mut packet[IPV4_HEADER_SIZE..];
+    icmp[0] = ICMP_ECHO_REQUEST;
+    icmp[4..6].copy_from_slice(&PROBE_IDENTIFIER.to_be_bytes());
+    icmp[6..8].copy_from_slice(&size.to_be_bytes());
+    let checksum = internet_checksum(icmp);
+    icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+    packet
+}
+
+fn echo_reply_size(packet: &[u8], server_address: Ipv4Addr) -> Option<u16> {
+    if packet.len() < IPV4_HEADER_SIZE + ICMP_HEADER_SIZE
+        || packet[0] >> 4 != 4
+        || packet[9] != ICMP_PROTOCOL
+        || packet[12..16] != server_address.octets()
+    {
+        return None;
+    }
+    let header_length = ((packet[0] & 0x0f) as usize) * 4;
+    let icmp = packet.get(header_length..)?;
+    if icmp.len() < ICMP_HEADER_SIZE
+        || icmp[0] != ICMP_ECHO_REPLY
+        || icmp[4..6] != PROBE_IDENTIFIER.to_be_bytes()
+    {
+        return None;
+    }
+    let size = u16::from_be_bytes([icmp[6], icmp[7]]);
+    (packet.len() == size as usize).then_some(size)
+}
+
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Makes oversized datagrams get dropped on the path instead of fragmented,
+/// ignoring any cached path MTU.
+fn set_dont_fragment(udp: &UdpSocket, server_endpoint: SocketAddr) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsRawFd;
+
+        #[cfg(target_os = "linux")]
+        let (level, option, value) = match server_endpoint.ip() {
+            IpAddr::V4(_) => (
+                libc::IPPROTO_IP,
+                libc::IP_MTU_DISCOVER,
+                libc::IP_PMTUDISC_PROBE,
+            ),
+            IpAddr::V6(_) => (
+                libc::IPPROTO_IPV6,
+                libc::IPV6_MTU_DISCOVER,
+                libc::IPV6_PMTUDISC_PROBE,
+            ),
+        };
+        #[cfg(not(target_os = "linux"))]
+        let (level, option, value) = match server_endpoint.ip() {
+            IpAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_DONTFRAG, 1),
+            IpAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_DONTFRAG, 1),
+        };
+
+        let result = unsafe {
+            libc::setsockopt(
+                udp.as_raw_fd(),
+                level,
+                option,
+                &value as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if result != 0 {
+            return Err(probe_error(&format!(
+                "failed to set Don't Fragment: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (udp, server_endpoint);
+        Err(probe_error("not supported on this platform"))
+    }
+}
+
+fn probe_error(reason: &str) -> byocvpn_core::error::Error {
+    ConfigurationError::TunnelConfiguration {
+        reason: format!("MTU probe failed: {}", reason),
+    }
+    .into()
+}
@@ -59,6 +59,7 @@ pub async fn try_restore_session() {
         instance_id: persisted_session.instance_id,
         private_key: wireguard_config.private_key,
         public_key: wireguard_config.public_key,
+        preshared_key: wireguard_config.preshared_key,
         server_endpoint: wireguard_config.server_endpoint,
         private_ipv4: wireguard_config.private_ipv4,
         private_ipv6: wireguard_config.private_ipv6,
@@ -68,6 +69,11 @@ pub async fn try_restore_session() {
         public_ip_v4: persisted_session.public_ip_v4,
         public_ip_v6: persisted_session.public_ip_v6,
         kill_switch_enabled: persisted_session.kill_switch_enabled,
+        split_tunnel: persisted_session.split_tunnel,
+        network_namespace: persisted_session.network_namespace,
+        allow_lan: persisted_session.allow_lan,
+        mtu: persisted_session.mtu,
+        probe_mtu: false,
     };
 
     match connect_vpn(params).await {
@@ -1,5 +1,6 @@
 use byocvpn_core::{
     cloud_provider::CloudProviderName,
+    daemon_client::SplitTunnelConfig,
     error::{ConfigurationError, Result},
 };
 use log::*;
@@ -18,6 +19,15 @@ pub struct PersistedSession {
     pub public_ip_v6: Option<String>,
     #[serde(default = "default_kill_switch_enabled")]
     pub kill_switch_enabled: bool,
+    #[serde(default)]
+    pub split_tunnel: SplitTunnelConfig,
+    #[serde(default)]
+    pub network_namespace: Option<String>,
+    #[serde(default)]
+    pub allow_lan: bool,
+    /// The MTU the session ended up with, so a restore skips the probe.
+    #[serde(default)]
+    pub mtu: Option<u16>,
 }
 
 pub fn write_session(session: &PersistedSession) -> Result<()> {
@@ -1,30 +1,52 @@
 use byocvpn_core::{
     error::{Result, SystemError},
-    tunnel::VpnStatus,
+    tunnel::{PeerState, VpnStatus},
 };
 
 use crate::tunnel_manager::TUNNEL_MANAGER;
 
 pub async fn get_vpn_status() -> Result<VpnStatus> {
-    let manager = TUNNEL_MANAGER
-        .lock()
-        .map_err(|_| SystemError::MutexPoisoned("TUNNEL_MANAGER".to_string()))?;
+    let snapshot = {
+        let manager = TUNNEL_MANAGER
+            .lock()
+            .map_err(|_| SystemError::MutexPoisoned("TUNNEL_MANAGER".to_string()))?;
 
-    if let Some(handle) = manager.as_ref() {
-        let is_running = !handle.task.is_finished();
+        manager.as_ref().map(|handle| {
+            let is_running = !handle.task.is_finished();
 
-        let connected_at = handle
-            .connected_at
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-            .ok()
-            .map(|duration| duration.as_secs());
+            let connected_at = handle
+                .connected_at
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|duration| duration.as_secs());
+
+            (
+                is_running,
+                connected_at,
+                handle.instance.clone(),
+                handle.metrics.clone(),
+                handle.backend,
+                handle.mtu,
+                handle.mtu_probe_error.clone(),
+            )
+        })
+    };
+
+    if let Some((is_running, connected_at, instance, metrics, backend, mtu, mtu_probe_error)) =
+        snapshot
+    {
+        let metrics = metrics.read().await.clone();
 
         Ok(VpnStatus {
             connected: is_running,
-            instance: handle.instance.clone(),
-            metrics: Some(handle.metrics.read().await.clone()),
+            instance,
+            reconnecting: is_running && metrics.peer_state == PeerState::Reconnecting,
+            metrics: Some(metrics),
             connected_at,
             connection_error: None,
+            backend: Some(backend),
+            mtu: Some(mtu),
+            mtu_probe_error,
         })
     } else {
         Ok(VpnStatus {
@@ -33,6 +55,10 @@ pub async fn get_vpn_status() -> Result<VpnStatus> {
             metrics: None,
             connected_at: None,
             connection_error: None,
+            reconnecting: false,
+            backend: None,
+            mtu: None,
+            mtu_probe_error: None,
         })
     }
 }
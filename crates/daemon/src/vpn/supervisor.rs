@@ -0,0 +1,213 @@
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use byocvpn_core::{
+    daemon_client::DaemonEvent,
+    error::Result,
+    tunnel::{PeerState, Tunnel, TunnelMetrics},
+};
+use log::*;
+use tokio::{
+    sync::{RwLock, watch},
+    time::{Instant, MissedTickBehavior},
+};
+
+use crate::{
+    events,
+    vpn::connect::{connect_udp_socket, create_wireguard_tunnel},
+};
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+/// A watchdog tick arriving this much later than expected means the host was
+/// suspended (or the clock was stepped) and the session needs a fresh handshake.
+const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(30);
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+enum TunnelExit {
+    Shutdown,
+    Failed(String),
+}
+
+/// Keeps a tunnel alive for the lifetime of a session: whenever the data plane
+/// dies, the peer stops answering, or the host resumes from suspend, it rebinds
+/// the UDP socket and re-handshakes with backoff. The TUN device, routes, DNS
+/// and kill switch are owned by the session and are never touched here.
+pub struct TunnelSupervisor {
+    tunnel: Tunnel,
+    server_endpoint: SocketAddr,
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+    preshared_key: Option<Vec<u8>>,
+    shutdown_rx: watch::Receiver<()>,
+}
+
+impl TunnelSupervisor {
+    pub fn new(
+        tunnel: Tunnel,
+        server_endpoint: SocketAddr,
+        private_key: Vec<u8>,
+        public_key: Vec<u8>,
+        preshared_key: Option<Vec<u8>>,
+        shutdown_rx: watch::Receiver<()>,
+    ) -> Self {
+        Self {
+            tunnel,
+            server_endpoint,
+            private_key,
+            public_key,
+            preshared_key,
+            shutdown_rx,
+        }
+    }
+
+    pub async fn run(mut self) {
+        let metrics = self.tunnel.metrics.clone();
+        let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+
+        loop {
+            let (exit, handshake_completed) = self.run_until_exit(&metrics).await;
+
+            let reason = match exit {
+                TunnelExit::Shutdown => break,
+                TunnelExit::Failed(reason) => reason,
+            };
+
+            if handshake_completed {
+                reconnect_delay = INITIAL_RECONNECT_DELAY;
+            }
+
+            warn!("[Supervisor] Tunnel needs recovery: {}", reason);
+            {
+                let mut metrics = metrics.write().await;
+                metrics.peer_state = PeerState::Reconnecting;
+                metrics.reconnect_count += 1;
+            }
+
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                events::publish(DaemonEvent::Reconnecting {
+                    reason: reason.clone(),
+                    attempt,
+                });
+                info!(
+                    "[Supervisor] Reconnecting in {}s...",
+                    reconnect_delay.as_secs()
+                );
+                tokio::select! {
+                    _ = self.shutdown_rx.changed() => {
+                        info!("[Supervisor] Shutdown requested while reconnecting.");
+                        return;
+                    }
+                    _ = tokio::time::sleep(reconnect_delay) => {}
+                }
+
+                reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+
+                match self.rebuild_tunnel().await {
+                    Ok(()) => {
+                        info!("[Supervisor] Tunnel rebuilt, re-handshaking.");
+                        events::publish(DaemonEvent::Reconnected);
+                        break;
+                    }
+                    Err(error) => {
+                        warn!("[Supervisor] Reconnect attempt failed: {}", error);
+                        events::publish(DaemonEvent::Error {
+                            message: error.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        info!("[Supervisor] Stopped.");
+    }
+
+    async fn run_until_exit(&mut self, metrics: &Arc<RwLock<TunnelMetrics>>) -> (TunnelExit, bool) {
+        let mut watchdog = tokio::time::interval(WATCHDOG_INTERVAL);
+        watchdog.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut clock = ClockJumpDetector::new();
+        let mut handshake_completed = false;
+
+        let run = self.tunnel.run();
+        tokio::pin!(run);
+
+        let exit = loop {
+            tokio::select! {
+                result = &mut run => {
+                    break match result {
+                        Ok(()) => TunnelExit::Shutdown,
+                        Err(error) => TunnelExit::Failed(error.to_string()),
+                    };
+                }
+
+                _ = watchdog.tick() => {
+                    if let Some(gap) = clock.check() {
+                        break TunnelExit::Failed(format!(
+                            "clock jumped {}s, assuming suspend/resume",
+                            gap.as_secs()
+                        ));
+                    }
+
+                    match metrics.read().await.peer_state {
+                        PeerState::Established => handshake_completed = true,
+                        PeerState::Unreachable => {
+                            break TunnelExit::Failed("peer stopped answering handshakes".to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        };
+
+        (exit, handshake_completed)
+    }
+
+    async fn rebuild_tunnel(&mut self) -> Result<()> {
+        let wireguard_tunnel = create_wireguard_tunnel(
+            &self.private_key,
+            &self.public_key,
+            self.preshared_key.as_deref(),
+        )?;
+        let udp = connect_udp_socket(self.server_endpoint).await?;
+        self.tunnel.reset(udp, wireguard_tunnel);
+        Ok(())
+    }
+}
+
+/// Compares the monotonic and wall clocks between watchdog ticks. Depending on
+/// the platform one or the other stops while the host sleeps, so a gap on
+/// either side is treated as a resume.
+struct ClockJumpDetector {
+    last_instant: Instant,
+    last_wall_time: SystemTime,
+}
+
+impl ClockJumpDetector {
+    fn new() -> Self {
+        Self {
+            last_instant: Instant::now(),
+            last_wall_time: SystemTime::now(),
+        }
+    }
+
+    fn check(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let wall_time = SystemTime::now();
+
+        let monotonic_elapsed = now.duration_since(self.last_instant);
+        let wall_elapsed = wall_time
+            .duration_since(self.last_wall_time)
+            .unwrap_or_default();
+
+        self.last_instant = now;
+        self.last_wall_time = wall_time;
+
+        let elapsed = monotonic_elapsed.max(wall_elapsed);
+        (elapsed > WATCHDOG_INTERVAL + CLOCK_JUMP_THRESHOLD).then_some(elapsed)
+    }
+}
@@ -0,0 +1,744 @@
+use std::{
+    ffi::CString,
+    io, mem,
+    net::{IpAddr, SocketAddr},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use byocvpn_core::{
+    daemon_client::DaemonEvent,
+    error::{ConfigurationError, Result},
+    tunnel::{PeerState, TunnelMetrics},
+};
+use ipnet::IpNet;
+use log::*;
+use tokio::sync::{RwLock, watch};
+
+use crate::{events, netns};
+
+const NLMSG_HEADER_SIZE: usize = 16;
+const NLA_HEADER_SIZE: usize = 4;
+const NLA_F_NESTED: u16 = 1 << 15;
+const NLA_TYPE_MASK: u16 = !(NLA_F_NESTED | (1 << 14));
+
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_GETLINK: u16 = 18;
+const RTM_NEWADDR: u16 = 20;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MTU: u16 = 4;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_STATS64: u16 = 23;
+const IFLA_INFO_KIND: u16 = 1;
+/// Size of `struct ifinfomsg`, which precedes a link's attributes.
+const IFINFOMSG_SIZE: usize = 16;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const IFA_F_NODAD: u8 = 0x02;
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const WG_GENL_NAME: &str = "wireguard";
+const WG_GENL_VERSION: u8 = 1;
+const WG_CMD_GET_DEVICE: u8 = 0;
+const WG_CMD_SET_DEVICE: u8 = 1;
+const WGDEVICE_A_IFINDEX: u16 = 1;
+const WGDEVICE_A_PRIVATE_KEY: u16 = 3;
+const WGDEVICE_A_FLAGS: u16 = 5;
+const WGDEVICE_A_PEERS: u16 = 8;
+const WGDEVICE_F_REPLACE_PEERS: u32 = 1;
+const WGPEER_A_PUBLIC_KEY: u16 = 1;
+const WGPEER_A_PRESHARED_KEY: u16 = 2;
+const WGPEER_A_FLAGS: u16 = 3;
+const WGPEER_A_ENDPOINT: u16 = 4;
+const WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL: u16 = 5;
+const WGPEER_A_LAST_HANDSHAKE_TIME: u16 = 6;
+const WGPEER_A_RX_BYTES: u16 = 7;
+const WGPEER_A_TX_BYTES: u16 = 8;
+const WGPEER_A_ALLOWEDIPS: u16 = 9;
+const WGPEER_F_REPLACE_ALLOWEDIPS: u32 = 2;
+const WGALLOWEDIP_A_FAMILY: u16 = 1;
+const WGALLOWEDIP_A_IPADDR: u16 = 2;
+const WGALLOWEDIP_A_CIDR_MASK: u16 = 3;
+
+const INTERFACE_NAME: &str = "byocvpn0";
+const PERSISTENT_KEEPALIVE: u16 = 25;
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+/// A session older than this is rejected by WireGuard (`REJECT_AFTER_TIME`).
+const SESSION_REJECT_AFTER: u64 = 180;
+/// How long the kernel retries a handshake before giving up on it
+/// (`REKEY_ATTEMPT_TIME`).
+const REKEY_ATTEMPT_TIME: u64 = 90;
+
+/// Everything the kernel needs to bring up the tunnel.
+pub struct KernelWireGuardConfig<'a> {
+    pub private_key: &'a [u8],
+    pub public_key: &'a [u8],
+    pub preshared_key: Option<&'a [u8]>,
+    pub server_endpoint: SocketAddr,
+    pub private_ipv4: IpNet,
+    pub private_ipv6: IpNet,
+    pub mtu: u16,
+}
+
+/// A `wireguard` link configured over netlink. Crypto and the peer's timers
+/// run in the kernel; the link is deleted when this is dropped.
+#[derive(Debug)]
+pub struct KernelWireGuard {
+    interface_name: String,
+    interface_index: u32,
+    family_id: u16,
+}
+
+#[derive(Debug, Default)]
+struct PeerStats {
+    rx_bytes: u64,
+    tx_bytes: u64,
+    last_handshake_at: Option<u64>,
+}
+
+impl KernelWireGuard {
+    /// Creates and configures the link. Fails (leaving nothing behind) when
+    /// the `wireguard` module is missing, so the caller can fall back to the
+    /// userspace tunnel.
+    pub fn create(config: &KernelWireGuardConfig) -> Result<Self> {
+        let mut generic = NetlinkSocket::open(libc::NETLINK_GENERIC).map_err(netlink_error)?;
+        let family_id = resolve_family(&mut generic).map_err(netlink_error)?;
+
+        let mut route = NetlinkSocket::open(libc::NETLINK_ROUTE).map_err(netlink_error)?;
+        if let Ok(stale_index) = interface_index(INTERFACE_NAME) {
+            warn!(
+                "[KernelWG] Removing stale {} left by a previous run",
+                INTERFACE_NAME
+            );
+            delete_link(&mut route, stale_index).map_err(netlink_error)?;
+        }
+        create_link(&mut route, INTERFACE_NAME, config.mtu).map_err(netlink_error)?;
+
+        let interface_index = interface_index(INTERFACE_NAME).map_err(netlink_error)?;
+        let device = Self {
+            interface_name: INTERFACE_NAME.to_string(),
+            interface_index,
+            family_id,
+        };
+
+        device.configure(&mut generic, &mut route, config)?;
+        info!(
+            "[KernelWG] Created {} (index: {})",
+            device.interface_name, device.interface_index
+        );
+        Ok(device)
+    }
+
+    pub fn interface_name(&self) -> &str {
+        &self.interface_name
+    }
+
+    pub fn interface_index(&self) -> u32 {
+        self.interface_index
+    }
+
+    fn configure(
+        &self,
+        generic: &mut NetlinkSocket,
+        route: &mut NetlinkSocket,
+        config: &KernelWireGuardConfig,
+    ) -> Result<()> {
+        set_device(generic, self.family_id, self.interface_index, config).map_err(netlink_error)?;
+        add_address(route, self.interface_index, config.private_ipv4).map_err(netlink_error)?;
+        add_address(route, self.interface_index, config.private_ipv6).map_err(netlink_error)?;
+        set_link_up(route, self.interface_index).map_err(netlink_error)?;
+        Ok(())
+    }
+}
+
+/// Netlink sockets for polling the link, opened in whichever network
+/// namespace it lives in. A socket stays bound to the namespace it was
+/// created in, so only opening has to happen there.
+struct StatsSockets {
+    generic: NetlinkSocket,
+    route: NetlinkSocket,
+    interface_index: u32,
+}
+
+impl StatsSockets {
+    fn open(interface_name: &str, network_namespace: Option<&str>) -> io::Result<Self> {
+        let interface_name = interface_name.to_string();
+        let open = move || {
+            Ok(Self {
+                generic: NetlinkSocket::open(libc::NETLINK_GENERIC)?,
+                route: NetlinkSocket::open(libc::NETLINK_ROUTE)?,
+                // Moving the link to a namespace may change its index.
+                interface_index: interface_index(&interface_name)?,
+            })
+        };
+        match network_namespace {
+            Some(network_namespace) => netns::run_in_namespace(network_namespace, open),
+            None => open(),
+        }
+    }
+}
+
+/// Sums the transfer counters and takes the last handshake across peers.
+fn read_stats(
+    generic: &mut NetlinkSocket,
+    family_id: u16,
+    interface_index: u32,
+) -> io::Result<PeerStats> {
+    let mut attributes = Attributes::default();
+    attributes.u32(WGDEVICE_A_IFINDEX, interface_index);
+
+    let replies = generic.dump(
+        family_id,
+        &genl_payload(WG_CMD_GET_DEVICE, WG_GENL_VERSION, &attributes),
+    )?;
+
+    let mut stats = PeerStats::default();
+    for reply in &replies {
+        let Some(device_attributes) = reply.get(4..) else {
+            continue;
+        };
+        for (kind, peers) in parse_attributes(device_attributes) {
+            if kind != WGDEVICE_A_PEERS {
+                continue;
+            }
+            for (_, peer) in parse_attributes(peers) {
+                for (kind, value) in parse_attributes(peer) {
+                    match kind {
+                        WGPEER_A_RX_BYTES => stats.rx_bytes += read_u64(value),
+                        WGPEER_A_TX_BYTES => stats.tx_bytes += read_u64(value),
+                        WGPEER_A_LAST_HANDSHAKE_TIME => {
+                            let seconds = read_u64(value);
+                            if seconds > 0 {
+                                stats.last_handshake_at = Some(seconds);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    Ok(stats)
+}
+
+impl Drop for KernelWireGuard {
+    fn drop(&mut self) {
+        let result = NetlinkSocket::open(libc::NETLINK_ROUTE)
+            .and_then(|mut route| delete_link(&mut route, self.interface_index));
+        match result {
+            Ok(()) => info!("[KernelWG] Removed {}", self.interface_name),
+            // Already gone, e.g. destroyed along with its network namespace.
+            Err(error) => debug!(
+                "[KernelWG] Could not remove {}: {}",
+                self.interface_name, error
+            ),
+        }
+    }
+}
+
+/// Copies the kernel's transfer and handshake stats into `metrics` until
+/// shutdown, then removes the link. The kernel retries handshakes itself, so
+/// the peer state is derived from the handshake age rather than supervised.
+/// `network_namespace` names the namespace the link was moved to, if any.
+pub async fn run_kernel_tunnel(
+    device: KernelWireGuard,
+    network_namespace: Option<String>,
+    metrics: Arc<RwLock<TunnelMetrics>>,
+    mut shutdown_rx: watch::Receiver<()>,
+) {
+    let started_at = unix_now();
+    let mut interval = tokio::time::interval(STATS_INTERVAL);
+    let mut sockets: Option<StatsSockets> = None;
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.changed() => {
+                info!("[KernelWG] Shutdown requested.");
+                break;
+            }
+            _ = interval.tick() => {
+                // Netlink calls block, so they run off the async workers on
+                // sockets that are kept between polls.
+                let open_sockets = sockets.take();
+                let family_id = device.family_id;
+                let interface_name = device.interface_name.clone();
+                let network_namespace = network_namespace.clone();
+                let poll = tokio::task::spawn_blocking(move || {
+                    let mut sockets = match open_sockets {
+                        Some(sockets) => sockets,
+                        None => StatsSockets::open(&interface_name, network_namespace.as_deref())?,
+                    };
+                    let stats = read_stats(&mut sockets.generic, family_id, sockets.interface_index)?;
+                    let packet_counts = read_packet_counts(&mut sockets.route, sockets.interface_index)?;
+                    Ok::<_, io::Error>((sockets, stats, packet_counts))
+                })
+                .await;
+                let (stats, (packets_sent, packets_received)) = match poll {
+                    Ok(Ok((open_sockets, stats, packet_counts))) => {
+                        sockets = Some(open_sockets);
+                        (stats, packet_counts)
+                    }
+                    // The sockets are dropped so the next poll starts clean.
+                    Ok(Err(error)) => {
+                        warn!("[KernelWG] Failed to read stats: {}", error);
+                        continue;
+                    }
+                    Err(error) => {
+                        warn!("[KernelWG] Stats poll panicked: {}", error);
+                        continue;
+                    }
+                };
+
+                let peer_state = derive_peer_state(stats.last_handshake_at, started_at, unix_now());
+
+                let mut metrics = metrics.write().await;
+                metrics.bytes_sent = stats.tx_bytes;
+                metrics.bytes_received = stats.rx_bytes;
+                metrics.packets_sent = packets_sent;
+                metrics.packets_received = packets_received;
+                metrics.last_handshake_at = stats.last_handshake_at;
+                if metrics.peer_state != peer_state {
+                    info!("[KernelWG] Peer state changed: {:?} -> {:?}", metrics.peer_state, peer_state);
+                    match peer_state {
+                        PeerState::Reconnecting => {
+                            metrics.reconnect_count += 1;
+                            events::publish(DaemonEvent::Reconnecting {
+                                reason: "handshake expired".to_string(),
+                                attempt: 1,
+                            });
+                        }
+                        PeerState::Established
+                            if matches!(
+                                metrics.peer_state,
+                                PeerState::Reconnecting | PeerState::Unreachable
+                            ) =>
+                        {
+                            events::publish(DaemonEvent::Reconnected);
+                        }
+                        _ => {}
+                    }
+                    metrics.peer_state = peer_state;
+                }
+            }
+        }
+    }
+    drop(device);
+}
+
+/// Maps the age of the last handshake onto the same states the userspace
+/// supervisor reports: a lapsed session is being renegotiated until the
+/// kernel's retry window runs out, after which the peer counts as unreachable.
+fn derive_peer_state(last_handshake_at: Option<u64>, started_at: u64, now: u64) -> PeerState {
+    match last_handshake_at {
+        Some(last_handshake_at) => {
+            let age = now.saturating_sub(last_handshake_at);
+            if age < SESSION_REJECT_AFTER {
+                PeerState::Established
+            } else if age < SESSION_REJECT_AFTER + REKEY_ATTEMPT_TIME {
+                PeerState::Reconnecting
+            } else {
+                PeerState::Unreachable
+            }
+        }
+        None if now.saturating_sub(started_at) < REKEY_ATTEMPT_TIME => PeerState::Handshaking,
+        None => PeerState::Unreachable,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// The kernel does not count packets per peer, so use the link's counters,
+/// as `(sent, received)`. Asked over netlink rather than sysfs, which only
+/// shows the host's links.
+fn read_packet_counts(route: &mut NetlinkSocket, index: u32) -> io::Result<(u64, u64)> {
+    let replies = route.request(RTM_GETLINK, 0, &interface_info(index, 0, 0))?;
+    let stats = replies
+        .iter()
+        .filter_map(|reply| reply.get(IFINFOMSG_SIZE..))
+        .flat_map(parse_attributes)
+        .find(|(kind, _)| *kind == IFLA_STATS64)
+        .map(|(_, value)| value)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "link reported no statistics"))?;
+    // `struct rtnl_link_stats64` starts with rx_packets, then tx_packets.
+    Ok((
+        read_u64(stats.get(8..).unwrap_or_default()),
+        read_u64(stats),
+    ))
+}
+
+fn netlink_error(error: io::Error) -> ConfigurationError {
+    ConfigurationError::TunnelConfiguration {
+        reason: format!("kernel WireGuard: {}", error),
+    }
+}
+
+fn interface_index(name: &str) -> io::Result<u32> {
+    let name =
+        CString::new(name).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+fn resolve_family(generic: &mut NetlinkSocket) -> io::Result<u16> {
+    let mut attributes = Attributes::default();
+    attributes.string(CTRL_ATTR_FAMILY_NAME, WG_GENL_NAME);
+    let not_found = || io::Error::new(io::ErrorKind::NotFound, "wireguard module is not loaded");
+    let replies = generic
+        .request(
+            GENL_ID_CTRL,
+            0,
+            &genl_payload(CTRL_CMD_GETFAMILY, 1, &attributes),
+        )
+        .map_err(|error| match error.raw_os_error() {
+            Some(libc::ENOENT) => not_found(),
+            _ => error,
+        })?;
+
+    replies
+        .iter()
+        .filter_map(|reply| reply.get(4..))
+        .flat_map(parse_attributes)
+        .find(|(kind, _)| *kind == CTRL_ATTR_FAMILY_ID)
+        .and_then(|(_, value)| value.get(..2))
+        .map(|value| u16::from_ne_bytes([value[0], value[1]]))
+        .ok_or_else(not_found)
+}
+
+fn create_link(route: &mut NetlinkSocket, name: &str, mtu: u16) -> io::Result<()> {
+    let mut attributes = Attributes::default();
+    attributes.string(IFLA_IFNAME, name);
+    attributes.u32(IFLA_MTU, u32::from(mtu));
+    attributes.nested(IFLA_LINKINFO, |link_info| {
+        link_info.string(IFLA_INFO_KIND, WG_GENL_NAME);
+    });
+
+    let mut payload = interface_info(0, 0, 0);
+    payload.extend_from_slice(&attributes.0);
+    route
+        .request(
+            RTM_NEWLINK,
+            (libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16,
+            &payload,
+        )
+        .map(|_| ())
+}
+
+fn set_link_up(route: &mut NetlinkSocket, index: u32) -> io::Result<()> {
+    let flags = libc::IFF_UP as u32;
+    route
+        .request(RTM_NEWLINK, 0, &interface_info(index, flags, flags))
+        .map(|_| ())
+}
+
+fn delete_link(route: &mut NetlinkSocket, index: u32) -> io::Result<()> {
+    route
+        .request(RTM_DELLINK, 0, &interface_info(index, 0, 0))
+        .map(|_| ())
+}
+
+fn add_address(route: &mut NetlinkSocket, index: u32, address: IpNet) -> io::Result<()> {
+    let (family, flags, bytes) = match address.addr() {
+        IpAddr::V4(ip) => (libc::AF_INET as u8, 0, ip.octets().to_vec()),
+        IpAddr::V6(ip) => (libc::AF_INET6 as u8, IFA_F_NODAD, ip.octets().to_vec()),
+    };
+
+    // struct ifaddrmsg
+    let mut payload = vec![family, address.prefix_len(), flags, 0];
+    payload.extend_from_slice(&index.to_ne_bytes());
+
+    let mut attributes = Attributes::default();
+    attributes.bytes(IFA_LOCAL, &bytes);
+    attributes.bytes(IFA_ADDRESS, &bytes);
+    payload.extend_from_slice(&attributes.0);
+
+    route
+        .request(
+            RTM_NEWADDR,
+            (libc::NLM_F_CREATE | libc::NLM_F_REPLACE) as u16,
+            &payload,
+        )
+        .map(|_| ())
+}
+
+fn set_device(
+    generic: &mut NetlinkSocket,
+    family_id: u16,
+    index: u32,
+    config: &KernelWireGuardConfig,
+) -> io::Result<()> {
+    let mut attributes = Attributes::default();
+    attributes.u32(WGDEVICE_A_IFINDEX, index);
+    attributes.bytes(WGDEVICE_A_PRIVATE_KEY, config.private_key);
+    attributes.u32(WGDEVICE_A_FLAGS, WGDEVICE_F_REPLACE_PEERS);
+    attributes.nested(WGDEVICE_A_PEERS, |peers| {
+        peers.nested(0, |peer| {
+            peer.bytes(WGPEER_A_PUBLIC_KEY, config.public_key);
+            if let Some(preshared_key) = config.preshared_key {
+                peer.bytes(WGPEER_A_PRESHARED_KEY, preshared_key);
+            }
+            peer.u32(WGPEER_A_FLAGS, WGPEER_F_REPLACE_ALLOWEDIPS);
+            peer.bytes(WGPEER_A_ENDPOINT, &socket_address(config.server_endpoint));
+            peer.u16(WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL, PERSISTENT_KEEPALIVE);
+            // Routing decides what enters the link; the peer may send anything.
+            peer.nested(WGPEER_A_ALLOWEDIPS, |allowed_ips| {
+                for (family, length) in [(libc::AF_INET, 4), (libc::AF_INET6, 16)] {
+                    allowed_ips.nested(0, |allowed_ip| {
+                        allowed_ip.u16(WGALLOWEDIP_A_FAMILY, family as u16);
+                        allowed_ip.bytes(WGALLOWEDIP_A_IPADDR, &vec![0u8; length]);
+                        allowed_ip.bytes(WGALLOWEDIP_A_CIDR_MASK, &[0]);
+                    });
+                }
+            });
+        });
+    });
+
+    generic
+        .request(
+            family_id,
+            0,
+            &genl_payload(WG_CMD_SET_DEVICE, WG_GENL_VERSION, &attributes),
+        )
+        .map(|_| ())
+}
+
+/// `struct sockaddr_in` / `struct sockaddr_in6` as the kernel expects them.
+fn socket_address(address: SocketAddr) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match address {
+        SocketAddr::V4(address) => {
+            bytes.extend_from_slice(&(libc::AF_INET as u16).to_ne_bytes());
+            bytes.extend_from_slice(&address.port().to_be_bytes());
+            bytes.extend_from_slice(&address.ip().octets());
+            bytes.extend_from_slice(&[0; 8]);
+        }
+        SocketAddr::V6(address) => {
+            bytes.extend_from_slice(&(libc::AF_INET6 as u16).to_ne_bytes());
+            bytes.extend_from_slice(&address.port().to_be_bytes());
+            bytes.extend_from_slice(&address.flowinfo().to_be_bytes());
+            bytes.extend_from_slice(&address.ip().octets());
+            bytes.extend_from_slice(&address.scope_id().to_ne_bytes());
+        }
+    }
+    bytes
+}
+
+/// `struct ifinfomsg` for `index`, setting the flags selected by `change`.
+fn interface_info(index: u32, flags: u32, change: u32) -> Vec<u8> {
+    let mut payload = vec![libc::AF_UNSPEC as u8, 0, 0, 0];
+    payload.extend_from_slice(&(index as i32).to_ne_bytes());
+    payload.extend_from_slice(&flags.to_ne_bytes());
+    payload.extend_from_slice(&change.to_ne_bytes());
+    payload
+}
+
+/// `struct genlmsghdr` followed by the attributes.
+fn genl_payload(command: u8, version: u8, attributes: &Attributes) -> Vec<u8> {
+    let mut payload = vec![command, version, 0, 0];
+    payload.extend_from_slice(&attributes.0);
+    payload
+}
+
+fn read_u64(value: &[u8]) -> u64 {
+    value
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_ne_bytes)
+        .unwrap_or_default()
+}
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+/// Builds a run of netlink attributes.
+#[derive(Default)]
+struct Attributes(Vec<u8>);
+
+impl Attributes {
+    fn bytes(&mut self, kind: u16, value: &[u8]) {
+        let length = NLA_HEADER_SIZE + value.len();
+        self.0.extend_from_slice(&(length as u16).to_ne_bytes());
+        self.0.extend_from_slice(&kind.to_ne_bytes());
+        self.0.extend_from_slice(value);
+        self.0.resize(align(self.0.len()), 0);
+    }
+
+    fn u16(&mut self, kind: u16, value: u16) {
+        self.bytes(kind, &value.to_ne_bytes());
+    }
+
+    fn u32(&mut self, kind: u16, value: u32) {
+        self.bytes(kind, &value.to_ne_bytes());
+    }
+
+    fn string(&mut self, kind: u16, value: &str) {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        self.bytes(kind, &bytes);
+    }
+
+    fn nested(&mut self, kind: u16, build: impl FnOnce(&mut Attributes)) {
+        let mut nested = Attributes::default();
+        build(&mut nested);
+        self.bytes(kind | NLA_F_NESTED, &nested.0);
+    }
+}
+
+fn parse_attributes(mut data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < NLA_HEADER_SIZE {
+            return None;
+        }
+        let length = u16::from_ne_bytes([data[0], data[1]]) as usize;
+        let kind = u16::from_ne_bytes([data[2], data[3]]) & NLA_TYPE_MASK;
+        if length < NLA_HEADER_SIZE || length > data.len() {
+            return None;
+        }
+        let value = &data[NLA_HEADER_SIZE..length];
+        data = &data[align(length).min(data.len())..];
+        Some((kind, value))
+    })
+}
+
+struct NetlinkSocket {
+    fd: OwnedFd,
+    sequence: u32,
+}
+
+impl NetlinkSocket {
+    fn open(protocol: libc::c_int) -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                protocol,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let result = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, sequence: 0 })
+    }
+
+    /// Sends one request and collects the payloads of every reply up to the
+    /// acknowledgement.
+    fn request(
+        &mut self,
+        message_type: u16,
+        flags: u16,
+        payload: &[u8],
+    ) -> io::Result<Vec<Vec<u8>>> {
+        self.transact(message_type, flags, payload, false)
+    }
+
+    /// Sends a dump request and collects every reply up to `NLMSG_DONE`.
+    fn dump(&mut self, message_type: u16, payload: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+        self.transact(message_type, libc::NLM_F_DUMP as u16, payload, true)
+    }
+
+    // The dump flag shares bits with NLM_F_REPLACE and NLM_F_EXCL, so whether
+    // to wait for NLMSG_DONE is passed separately.
+    fn transact(
+        &mut self,
+        message_type: u16,
+        flags: u16,
+        payload: &[u8],
+        dump: bool,
+    ) -> io::Result<Vec<Vec<u8>>> {
+        self.sequence += 1;
+        let flags = flags | (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+
+        let mut message = Vec::with_capacity(NLMSG_HEADER_SIZE + payload.len());
+        message.extend_from_slice(&((NLMSG_HEADER_SIZE + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(&flags.to_ne_bytes());
+        message.extend_from_slice(&self.sequence.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(payload);
+
+        let sent = unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut replies = Vec::new();
+        let mut buffer = vec![0u8; 32 * 1024];
+        loop {
+            let received = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if received < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut data = &buffer[..received as usize];
+            while data.len() >= NLMSG_HEADER_SIZE {
+                let length = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                let kind = u16::from_ne_bytes([data[4], data[5]]);
+                if length < NLMSG_HEADER_SIZE || length > data.len() {
+                    break;
+                }
+                let body = &data[NLMSG_HEADER_SIZE..length];
+
+                match kind as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(replies),
+                    libc::NLMSG_ERROR => {
+                        let code = body
+                            .get(..4)
+                            .map(|bytes| {
+                                i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                            })
+                            .unwrap_or(0);
+                        if code == 0 {
+                            if !dump {
+                                return Ok(replies);
+                            }
+                        } else {
+                            return Err(io::Error::from_raw_os_error(-code));
+                        }
+                    }
+                    _ => replies.push(body.to_vec()),
+                }
+                data = &data[align(length).min(data.len())..];
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use aws_sdk_ec2::{
+    Client as Ec2Client,
+    types::{Address, DomainType, Filter, ResourceType, Tag, TagSpecification},
+};
+use byocvpn_core::{
+    cloud_provider::{CloudProviderName, ReservedIpInfo},
+    error::{NetworkProvisioningError, Result},
+};
+use log::*;
+
+use crate::{
+    aws_error::extract_error_message,
+    instance::{SERVER_INSTANCE_TYPE, TAG_CREATED_BY_KEY, TAG_CREATED_BY_VALUE},
+    pricing,
+};
+
+const RESERVED_IP_NAME: &str = "byocvpn-reserved-ip";
+
+pub(super) struct ReservedAddress {
+    pub allocation_id: String,
+    pub public_ip: String,
+}
+
+/// Returns an unassociated Elastic IP created by byocvpn, allocating a new
+/// one when every existing address is in use.
+async fn ensure_reserved_ip(ec2_client: &Ec2Client) -> Result<ReservedAddress> {
+    let addresses = describe_reserved_ips(ec2_client).await?;
+    if let Some(address) = addresses
+        .iter()
+        .filter(|address| address.association_id().is_none())
+        .find_map(to_reserved_address)
+    {
+        info!(
+            "Reusing Elastic IP {} ({})",
+            address.public_ip, address.allocation_id
+        );
+        return Ok(address);
+    }
+
+    let tags = TagSpecification::builder()
+        .resource_type(ResourceType::ElasticIp)
+        .tags(Tag::builder().key("Name").value(RESERVED_IP_NAME).build())
+        .tags(
+            Tag::builder()
+                .key(TAG_CREATED_BY_KEY)
+                .value(TAG_CREATED_BY_VALUE)
+                .build(),
+        )
+        .build();
+    let response = ec2_client
+        .allocate_address()
+        .domain(DomainType::Vpc)
+        .tag_specifications(tags)
+        .send()
+        .await
+        .map_err(
+            |error| NetworkProvisioningError::ReservedIpAllocationFailed {
+                reason: extract_error_message(&error),
+            },
+        )?;
+
+    let address = match (response.allocation_id(), response.public_ip()) {
+        (Some(allocation_id), Some(public_ip)) => ReservedAddress {
+            allocation_id: allocation_id.to_string(),
+            public_ip: public_ip.to_string(),
+        },
+        _ => {
+            return Err(NetworkProvisioningError::MissingResourceField {
+                field: "allocationId",
+                resource: "elastic IP",
+            }
+            .into());
+        }
+    };
+    info!(
+        "Allocated Elastic IP {} ({})",
+        address.public_ip, address.allocation_id
+    );
+    Ok(address)
+}
+
+/// Attaches a reserved Elastic IP to `instance_id` and returns its address.
+pub(super) async fn attach_reserved_ip(
+    ec2_client: &Ec2Client,
+    instance_id: &str,
+) -> Result<String> {
+    let reserved = ensure_reserved_ip(ec2_client).await?;
+    associate_reserved_ip(ec2_client, &reserved.allocation_id, instance_id).await?;
+    Ok(reserved.public_ip)
+}
+
+async fn associate_reserved_ip(
+    ec2_client: &Ec2Client,
+    allocation_id: &str,
+    instance_id: &str,
+) -> Result<()> {
+    debug!(
+        "Associating Elastic IP {} with instance {}",
+        allocation_id, instance_id
+    );
+    ec2_client
+        .associate_address()
+        .allocation_id(allocation_id)
+        .instance_id(instance_id)
+        .send()
+        .await
+        .map_err(
+            |error| NetworkProvisioningError::ReservedIpAssociationFailed {
+                address_id: allocation_id.to_string(),
+                reason: extract_error_message(&error),
+            },
+        )?;
+    Ok(())
+}
+
+pub(super) async fn list_reserved_ips_in_region(
+    ec2_client: &Ec2Client,
+    region: &str,
+) -> Result<Vec<ReservedIpInfo>> {
+    let ip_hourly_rate = pricing::get_pricing(SERVER_INSTANCE_TYPE)
+        .map(|pricing| pricing.ip_hourly_rate)
+        .unwrap_or_default();
+    let addresses = describe_reserved_ips(ec2_client).await?;
+    Ok(addresses
+        .iter()
+        .filter_map(|address| {
+            let reserved = to_reserved_address(address)?;
+            Some(ReservedIpInfo {
+                id: reserved.allocation_id,
+                region: region.to_string(),
+                public_ip: reserved.public_ip,
+                provider: CloudProviderName::Aws,
+                instance_id: address.instance_id().map(ToString::to_string),
+                ip_hourly_rate,
+            })
+        })
+        .collect())
+}
+
+pub(super) async fn release_reserved_ip(ec2_client: &Ec2Client, allocation_id: &str) -> Result<()> {
+    debug!("Releasing Elastic IP {}", allocation_id);
+    ec2_client
+        .release_address()
+        .allocation_id(allocation_id)
+        .send()
+        .await
+        .map_err(|error| NetworkProvisioningError::ReservedIpReleaseFailed {
+            address_id: allocation_id.to_string(),
+            reason: extract_error_message(&error),
+        })?;
+
+    info!("Elastic IP {} released", allocation_id);
+    Ok(())
+}
+
+async fn describe_reserved_ips(ec2_client: &Ec2Client) -> Result<Vec<Address>> {
+    let response = ec2_client
+        .describe_addresses()
+        .filters(
+            Filter::builder()
+                .name(format!("tag:{}", TAG_CREATED_BY_KEY))
+                .values(TAG_CREATED_BY_VALUE)
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|error| NetworkProvisioningError::NetworkQueryFailed {
+            reason: extract_error_message(&error),
+        })?;
+    Ok(response.addresses().to_vec())
+}
+
+fn to_reserved_address(address: &Address) -> Option<ReservedAddress> {
+    Some(ReservedAddress {
+        allocation_id: address.allocation_id()?.to_string(),
+        public_ip: address.public_ip()?.to_string(),
+    })
+}
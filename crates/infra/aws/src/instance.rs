@@ -1,12 +1,12 @@
 use aws_sdk_ec2::{
     Client as Ec2Client,
     client::Waiters,
-    types::{Filter, ResourceType, Tag, TagSpecification},
+    types::{Filter, Instance, ResourceType, ShutdownBehavior, Tag, TagSpecification},
 };
 use aws_sdk_ssm::Client as SsmClient;
 use base64::{Engine, engine::general_purpose};
 use byocvpn_core::{
-    cloud_provider::{CloudProviderName, InstanceInfo, InstanceState},
+    cloud_provider::{CloudProviderName, InstanceInfo, InstanceState, SpawnInstanceParams},
     error::{ComputeProvisioningError, Result},
 };
 use chrono::{DateTime, Utc};
@@ -22,26 +22,24 @@ use crate::{
 };
 
 const SERVER_INSTANCE_NAME: &str = "byocvpn-server";
-const SERVER_INSTANCE_TYPE: &str = "t3.micro";
-const TAG_CREATED_BY_KEY: &str = "created-by";
-const TAG_CREATED_BY_VALUE: &str = "byocvpn";
+pub(crate) const SERVER_INSTANCE_TYPE: &str = "t3.micro";
+pub(crate) const TAG_CREATED_BY_KEY: &str = "created-by";
+pub(crate) const TAG_CREATED_BY_VALUE: &str = "byocvpn";
 const TAG_SPAWN_ID_KEY: &str = "byocvpn-spawn-id";
 
 pub(super) async fn spawn_instance(
     ec2_client: &Ec2Client,
     ssm_client: &SsmClient,
-    region: &str,
-    spawn_id: &str,
-    server_private_key: &str,
-    client_public_key: &str,
+    params: &SpawnInstanceParams<'_>,
 ) -> Result<InstanceInfo> {
+    let region = params.region;
+    let spawn_id = params.spawn_id;
     let vpc_id = network::ensure_vpc(ec2_client, VPC_CIDR_BLOCK, VPC_NAME).await?;
 
     let subnet_id =
         network::ensure_subnet(ec2_client, &vpc_id, SUBNET_CIDR_BLOCK, SUBNET_NAME).await?;
 
-    let user_data =
-        startup_script::generate_server_startup_script(server_private_key, client_public_key)?;
+    let user_data = startup_script::generate_server_startup_script(params)?;
 
     debug!("Generated startup script ({} bytes)", user_data.len());
     let encoded_user_data = general_purpose::STANDARD.encode(user_data);
@@ -78,6 +76,8 @@ pub(super) async fn spawn_instance(
         .security_group_ids(security_group_id)
         .instance_type(aws_sdk_ec2::types::InstanceType::from(SERVER_INSTANCE_TYPE))
         .user_data(encoded_user_data)
+        // The server powers itself off when idle or too old; make that final.
+        .instance_initiated_shutdown_behavior(ShutdownBehavior::Terminate)
         .min_count(1)
         .max_count(1)
         .tag_specifications(tags)
@@ -170,6 +170,73 @@ pub async fn terminate_instance(ec2_client: &Ec2Client, instance_id: &str) -> Re
     Ok(())
 }
 
+pub async fn stop_instance(ec2_client: &Ec2Client, instance_id: &str) -> Result<()> {
+    debug!("Stopping AWS instance {}", instance_id);
+    ec2_client
+        .stop_instances()
+        .instance_ids(instance_id)
+        .send()
+        .await
+        .map_err(|error| ComputeProvisioningError::InstanceStopFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: extract_error_message(&error),
+        })?;
+
+    info!("AWS instance {} stopping", instance_id);
+    Ok(())
+}
+
+pub async fn start_instance(
+    ec2_client: &Ec2Client,
+    region: &str,
+    instance_id: &str,
+) -> Result<InstanceInfo> {
+    debug!("Starting AWS instance {}", instance_id);
+    ec2_client
+        .start_instances()
+        .instance_ids(instance_id)
+        .send()
+        .await
+        .map_err(|error| ComputeProvisioningError::InstanceStartFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: extract_error_message(&error),
+        })?;
+
+    ec2_client
+        .wait_until_instance_running()
+        .instance_ids(instance_id)
+        .wait(Duration::from_secs(300))
+        .await
+        .map_err(|error| ComputeProvisioningError::InstanceWaitFailed {
+            reason: error.to_string(),
+        })?;
+
+    let desc = ec2_client
+        .describe_instances()
+        .instance_ids(instance_id)
+        .send()
+        .await
+        .map_err(|error| ComputeProvisioningError::InstanceStartFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: extract_error_message(&error),
+        })?;
+    let instance = desc
+        .reservations()
+        .iter()
+        .flat_map(|reservation| reservation.instances())
+        .find_map(|instance| build_instance_info(instance, region))
+        .ok_or(ComputeProvisioningError::NoInstanceInResponse)?;
+    if instance.public_ip_v4.is_empty() {
+        return Err(ComputeProvisioningError::MissingPublicIpv4.into());
+    }
+
+    info!(
+        "AWS instance {} started — IPv4: {}",
+        instance_id, instance.public_ip_v4
+    );
+    Ok(instance)
+}
+
 pub(super) async fn list_instances_in_region(
     ec2_client: &Ec2Client,
     region: &str,
@@ -202,59 +269,64 @@ pub(super) async fn list_instances_in_region(
         .reservations()
         .iter()
         .flat_map(|reservation| reservation.instances())
-        .filter_map(|instance| {
-            let id = instance.instance_id()?.to_string();
-
-            let raw_state = instance
-                .state()
-                .and_then(|instance_state| {
-                    instance_state.name().map(|state_name| state_name.as_str())
-                })
-                .unwrap_or("unknown");
-
-            let state: InstanceState = Ec2InstanceState::from(raw_state).into();
-            let name = instance.tags().iter().find_map(|tag| {
-                tag.key()
-                    .filter(|key| *key == "Name")
-                    .and_then(|_| tag.value().map(ToString::to_string))
-            });
-            let spawn_id = instance.tags().iter().find_map(|tag| {
-                tag.key()
-                    .filter(|key| *key == TAG_SPAWN_ID_KEY)
-                    .and_then(|_| tag.value().map(ToString::to_string))
-            });
-
-            let public_ip_v4 = instance
-                .public_ip_address()
-                .map(|address| address.to_string())?;
-            let public_ip_v6 = instance.ipv6_address().map(|address| address.to_string())?;
-
-            let instance_type = instance
-                .instance_type()
-                .map(|type_value| type_value.as_str().to_string())
-                .unwrap_or_default();
-
-            let launched_at = instance.launch_time().and_then(|timestamp| {
-                DateTime::parse_from_rfc3339(&timestamp.to_string())
-                    .ok()
-                    .map(|datetime| datetime.with_timezone(&Utc))
-            });
-
-            Some(InstanceInfo {
-                id,
-                name,
-                state,
-                public_ip_v4,
-                public_ip_v6,
-                region: region.to_string(),
-                provider: CloudProviderName::Aws,
-                instance_type,
-                launched_at,
-                error_reason: None,
-                spawn_id,
-            })
-        })
+        .filter_map(|instance| build_instance_info(instance, region))
         .collect::<Vec<InstanceInfo>>();
     debug!("Found {} instances in region {}", instances.len(), region);
     Ok(instances)
 }
+
+fn build_instance_info(instance: &Instance, region: &str) -> Option<InstanceInfo> {
+    let id = instance.instance_id()?.to_string();
+
+    let raw_state = instance
+        .state()
+        .and_then(|instance_state| instance_state.name().map(|state_name| state_name.as_str()))
+        .unwrap_or("unknown");
+
+    let state: InstanceState = Ec2InstanceState::from(raw_state).into();
+    let name = instance.tags().iter().find_map(|tag| {
+        tag.key()
+            .filter(|key| *key == "Name")
+            .and_then(|_| tag.value().map(ToString::to_string))
+    });
+    let spawn_id = instance.tags().iter().find_map(|tag| {
+        tag.key()
+            .filter(|key| *key == TAG_SPAWN_ID_KEY)
+            .and_then(|_| tag.value().map(ToString::to_string))
+    });
+
+    // A stopped instance has given up its public IPv4 address.
+    let public_ip_v4 = instance
+        .public_ip_address()
+        .map(|address| address.to_string())
+        .unwrap_or_default();
+    let public_ip_v6 = instance
+        .ipv6_address()
+        .map(|address| address.to_string())
+        .unwrap_or_default();
+
+    let instance_type = instance
+        .instance_type()
+        .map(|type_value| type_value.as_str().to_string())
+        .unwrap_or_default();
+
+    let launched_at = instance.launch_time().and_then(|timestamp| {
+        DateTime::parse_from_rfc3339(&timestamp.to_string())
+            .ok()
+            .map(|datetime| datetime.with_timezone(&Utc))
+    });
+
+    Some(InstanceInfo {
+        id,
+        name,
+        state,
+        public_ip_v4,
+        public_ip_v6,
+        region: region.to_string(),
+        provider: CloudProviderName::Aws,
+        instance_type,
+        launched_at,
+        error_reason: None,
+        spawn_id,
+    })
+}
@@ -1,3 +1,4 @@
+mod address;
 mod aws_error;
 mod config;
 mod constants;
@@ -6,8 +6,9 @@ use aws_sdk_ec2::error::{ProvideErrorMetadata, SdkError};
 use aws_sdk_ssm::Client as SsmClient;
 use byocvpn_core::{
     cloud_provider::{
-        CloudProvider, CloudProviderName, InstanceInfo, PermissionStatus, SpawnInstanceParams,
-        SpawnStep, TerminateInstanceParams,
+        CloudProvider, CloudProviderName, InstanceInfo, PermissionStatus, ReleaseReservedIpParams,
+        ReservedIpInfo, SpawnInstanceParams, SpawnStep, StartInstanceParams, StopInstanceParams,
+        TerminateInstanceParams,
     },
     commands::setup::Region,
     error::{NetworkProvisioningError, Result},
@@ -18,7 +19,7 @@ use serde_json::Value;
 use crate::constants::{
     IPV4_ALL_CIDR, SECURITY_GROUP_NAME, SUBNET_CIDR_BLOCK, SUBNET_NAME, VPC_CIDR_BLOCK, VPC_NAME,
 };
-use crate::{config, instance, network};
+use crate::{address, config, instance, network};
 
 const INTERNET_GATEWAY_NAME: &str = "byocvpn-igw";
 const MAIN_ROUTE_TABLE_NAME: &str = "byocvpn-main-route-table";
@@ -586,15 +587,33 @@ impl CloudProvider for AwsProvider {
         let ssm_client = self
             .create_ssm_client(Some(params.region.to_string()))
             .await;
-        instance::spawn_instance(
-            &ec2_client,
-            &ssm_client,
-            params.region,
-            params.spawn_id,
-            params.server_private_key,
-            params.client_public_key,
-        )
-        .await
+        let mut instance = instance::spawn_instance(&ec2_client, &ssm_client, params).await?;
+
+        // The Elastic IP replaces the auto-assigned address and stays behind
+        // when the instance is terminated.
+        if params.reserve_ip {
+            match address::attach_reserved_ip(&ec2_client, &instance.id).await {
+                Ok(public_ip) => instance.public_ip_v4 = public_ip,
+                // The client was asked for a stable address; don't leave a
+                // billed instance behind that doesn't have one.
+                Err(error) => {
+                    warn!(
+                        "Failed to attach an Elastic IP to {}, terminating it",
+                        instance.id
+                    );
+                    if let Err(terminate_error) =
+                        instance::terminate_instance(&ec2_client, &instance.id).await
+                    {
+                        warn!(
+                            "Failed to terminate instance {}: {}",
+                            instance.id, terminate_error
+                        );
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(instance)
     }
 
     async fn terminate_instance(&self, params: &TerminateInstanceParams) -> Result<()> {
@@ -604,6 +623,20 @@ impl CloudProvider for AwsProvider {
         instance::terminate_instance(&ec2_client, params.instance_id).await
     }
 
+    async fn stop_instance(&self, params: &StopInstanceParams) -> Result<()> {
+        let ec2_client = self
+            .create_ec2_client(Some(params.region.to_string()))
+            .await;
+        instance::stop_instance(&ec2_client, params.instance_id).await
+    }
+
+    async fn start_instance(&self, params: &StartInstanceParams) -> Result<InstanceInfo> {
+        let ec2_client = self
+            .create_ec2_client(Some(params.region.to_string()))
+            .await;
+        instance::start_instance(&ec2_client, params.region, params.instance_id).await
+    }
+
     async fn list_instances(&self, region: Option<&str>) -> Result<Vec<InstanceInfo>> {
         if let Some(region_name) = region {
             let ec2_client = self.create_ec2_client(Some(region_name.to_string())).await;
@@ -632,6 +665,35 @@ impl CloudProvider for AwsProvider {
             .collect());
     }
 
+    async fn list_reserved_ips(&self, region: Option<&str>) -> Result<Vec<ReservedIpInfo>> {
+        if let Some(region_name) = region {
+            let ec2_client = self.create_ec2_client(Some(region_name.to_string())).await;
+            return address::list_reserved_ips_in_region(&ec2_client, region_name).await;
+        }
+        let regions = self.get_regions().await?;
+        let results = futures::future::join_all(regions.iter().map(|region| async move {
+            let ec2_client = self.create_ec2_client(Some(region.name.clone())).await;
+            let result = address::list_reserved_ips_in_region(&ec2_client, &region.name).await;
+            if let Err(error) = &result {
+                warn!("Skipping region {}: {}", region.name, error);
+            }
+            result
+        }))
+        .await;
+        Ok(results
+            .into_iter()
+            .filter_map(|result| result.ok())
+            .flatten()
+            .collect())
+    }
+
+    async fn release_reserved_ip(&self, params: &ReleaseReservedIpParams) -> Result<()> {
+        let ec2_client = self
+            .create_ec2_client(Some(params.region.to_string()))
+            .await;
+        address::release_reserved_ip(&ec2_client, params.address_id).await
+    }
+
     async fn get_regions(&self) -> Result<Vec<Region>> {
         let ec2_client = self.create_ec2_client(None).await;
         let regions_map = HashMap::from([
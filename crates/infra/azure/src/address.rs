@@ -0,0 +1,194 @@
+use byocvpn_core::{
+    cloud_provider::{CloudProviderName, ReservedIpInfo},
+    error::{NetworkProvisioningError, Result},
+};
+use log::*;
+use uuid::Uuid;
+
+use crate::{
+    client::{AzureClient, extract_name_from_id, extract_resource_group_from_id},
+    instance::VM_SIZES,
+    models::{
+        PublicIpListResponse, PublicIpProperties, PublicIpRequest, PublicIpResponse, PublicIpSku,
+        byocvpn_tags,
+    },
+    network::{API_VERSION_NETWORK, build_resource_group_name},
+    pricing,
+};
+
+/// Marks public IPs that outlive their VM, so VM cleanup never matches them
+/// and the next spawn in the location can pick them up.
+const RESERVED_IP_TAG_KEY: &str = "byocvpn-reserved";
+
+pub struct ReservedPublicIp {
+    pub id: String,
+    pub ip_address: String,
+}
+
+/// Returns a Static IPv4 public IP reserved by byocvpn in the location that is
+/// not attached to a NIC, creating one when none is free.
+pub async fn ensure_reserved_public_ip(
+    client: &AzureClient,
+    location: &str,
+) -> Result<ReservedPublicIp> {
+    let public_ips = list_reserved_public_ips(client).await?;
+    if let Some(reserved) = public_ips
+        .iter()
+        .filter(|public_ip| public_ip.location.as_deref() == Some(location))
+        .filter(|public_ip| {
+            public_ip
+                .properties
+                .as_ref()
+                .is_some_and(|properties| properties.ip_configuration.is_none())
+        })
+        .find_map(to_reserved_public_ip)
+    {
+        info!(
+            "[Azure] Reusing reserved public IP {} in {}.",
+            reserved.ip_address, location
+        );
+        return Ok(reserved);
+    }
+
+    let resource_group = build_resource_group_name(location);
+    let name = format!(
+        "byocvpn-ip-{}",
+        Uuid::new_v4().to_string().replace('-', "")[..12].to_lowercase()
+    );
+    let path = client.build_subscription_path(&format!(
+        "/resourceGroups/{}/providers/Microsoft.Network/publicIPAddresses/{}",
+        resource_group, name
+    ));
+    let url = client.build_arm_url(&path, API_VERSION_NETWORK);
+
+    let mut tags = byocvpn_tags();
+    tags.insert(RESERVED_IP_TAG_KEY.to_string(), "true".to_string());
+    let body = PublicIpRequest {
+        location: location.to_string(),
+        sku: PublicIpSku {
+            name: "Standard".to_string(),
+        },
+        tags,
+        properties: PublicIpProperties {
+            public_ip_allocation_method: "Static".to_string(),
+            public_ip_address_version: "IPv4".to_string(),
+        },
+    };
+
+    info!("[Azure] Creating reserved public IP '{}'...", name);
+    let async_op_url = client.put(&url, &body).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpAllocationFailed {
+            reason: error.to_string(),
+        }
+    })?;
+    if let Some(operation_url) = async_op_url {
+        client.wait_for_async_operation(&operation_url).await?;
+    }
+
+    let public_ip: PublicIpResponse = client.get(&url).await?;
+    let reserved = to_reserved_public_ip(&public_ip).ok_or(
+        NetworkProvisioningError::MissingResourceField {
+            field: "ipAddress",
+            resource: "public IP",
+        },
+    )?;
+    info!(
+        "[Azure] Reserved public IP {} created in {}.",
+        reserved.ip_address, location
+    );
+    Ok(reserved)
+}
+
+/// The reserved public IP on the VM's NIC, for VMs spawned without their own.
+pub async fn find_attached_reserved_ip(client: &AzureClient, vm_name: &str) -> Result<String> {
+    let public_ips = list_reserved_public_ips(client).await?;
+    Ok(public_ips
+        .iter()
+        .find(|public_ip| get_attached_vm_name(public_ip) == Some(vm_name))
+        .and_then(to_reserved_public_ip)
+        .map(|reserved| reserved.ip_address)
+        .unwrap_or_default())
+}
+
+pub async fn list_reserved_ips(
+    client: &AzureClient,
+    location: Option<&str>,
+) -> Result<Vec<ReservedIpInfo>> {
+    // The pricing covers a VM's IPv4 and IPv6 address; a reserved IP is one.
+    let ip_hourly_rate = pricing::get_pricing(VM_SIZES[0])
+        .map(|pricing| pricing.ip_hourly_rate / 2.0)
+        .unwrap_or_default();
+
+    let public_ips = list_reserved_public_ips(client).await?;
+    Ok(public_ips
+        .iter()
+        .filter(|public_ip| {
+            location.is_none_or(|location| public_ip.location.as_deref() == Some(location))
+        })
+        .filter_map(|public_ip| {
+            let reserved = to_reserved_public_ip(public_ip)?;
+            let instance_id = get_attached_vm_name(public_ip).and_then(|vm_name| {
+                extract_resource_group_from_id(&reserved.id)
+                    .map(|resource_group| format!("{}/{}", resource_group, vm_name))
+            });
+            Some(ReservedIpInfo {
+                id: reserved.id,
+                region: public_ip.location.clone()?,
+                public_ip: reserved.ip_address,
+                provider: CloudProviderName::Azure,
+                instance_id,
+                ip_hourly_rate,
+            })
+        })
+        .collect())
+}
+
+pub async fn release_reserved_ip(client: &AzureClient, address_id: &str) -> Result<()> {
+    info!("[Azure] Releasing reserved public IP '{}'...", address_id);
+    let url = client.build_arm_url(address_id, API_VERSION_NETWORK);
+    let async_op_url = client.delete(&url).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpReleaseFailed {
+            address_id: address_id.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    if let Some(operation_url) = async_op_url {
+        client.wait_for_async_operation(&operation_url).await?;
+    }
+
+    info!("[Azure] Reserved public IP '{}' released.", address_id);
+    Ok(())
+}
+
+async fn list_reserved_public_ips(client: &AzureClient) -> Result<Vec<PublicIpResponse>> {
+    let path = client.build_subscription_path("/providers/Microsoft.Network/publicIPAddresses");
+    let url = client.build_arm_url(&path, API_VERSION_NETWORK);
+    let response: PublicIpListResponse = client.get(&url).await?;
+
+    Ok(response
+        .value
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|public_ip| {
+            public_ip
+                .tags
+                .as_ref()
+                .is_some_and(|tags| tags.contains_key(RESERVED_IP_TAG_KEY))
+        })
+        .collect())
+}
+
+fn to_reserved_public_ip(public_ip: &PublicIpResponse) -> Option<ReservedPublicIp> {
+    Some(ReservedPublicIp {
+        id: public_ip.id.clone()?,
+        ip_address: public_ip.properties.as_ref()?.ip_address.clone()?,
+    })
+}
+
+/// NICs are named `<vm>-nic`, and the IP configuration ID runs through the NIC:
+/// `.../networkInterfaces/<vm>-nic/ipConfigurations/<name>`.
+fn get_attached_vm_name(public_ip: &PublicIpResponse) -> Option<&str> {
+    let ip_configuration_id = &public_ip.properties.as_ref()?.ip_configuration.as_ref()?.id;
+    let (nic_path, _) = ip_configuration_id.split_once("/ipConfigurations/")?;
+    extract_name_from_id(nic_path)?.strip_suffix("-nic")
+}
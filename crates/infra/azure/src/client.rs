@@ -110,6 +110,24 @@ impl AzureClient {
         parse_json_response("POST", url, response).await
     }
 
+    /// POSTs a long-running action such as `start` or `deallocate`, which
+    /// replies with no body.
+    pub async fn post_action(&self, url: &str) -> Result<Option<String>> {
+        debug!("[Azure] POST {}", url);
+        let token = self.get_access_token().await?;
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(&token)
+            .header(reqwest::header::CONTENT_LENGTH, 0)
+            .send()
+            .await
+            .map_err(|error| NetworkProvisioningError::NetworkQueryFailed {
+                reason: format!("Azure POST {} connection failed: {}", url, error),
+            })?;
+        parse_lro_response("POST", url, response).await
+    }
+
     pub async fn wait_for_async_operation(&self, operation_url: &str) -> Result<()> {
         retry(
             || async move {
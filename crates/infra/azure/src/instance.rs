@@ -10,12 +10,13 @@ use tokio::time::Duration;
 use uuid::Uuid;
 
 use crate::{
+    address::{ensure_reserved_public_ip, find_attached_reserved_ip},
     client::AzureClient,
     models::{
-        AsyncOperationResponse, HardwareProfile, ImageReference, LinuxConfiguration,
+        AsyncOperationResponse, DeleteOption, HardwareProfile, ImageReference, LinuxConfiguration,
         NetworkInterfaceReference, NetworkProfile, OsDisk,
-        OsProfile, StorageProfile, VmListResponse, VmProperties, VmRequest, VmResponse,
-        byocvpn_tags,
+        OsProfile, RoleAssignmentProperties, RoleAssignmentRequest, StorageProfile, VmIdentity,
+        VmInstanceViewResponse, VmListResponse, VmProperties, VmRequest, VmResponse, byocvpn_tags,
     },
     network::{
         IpVersion, build_resource_group_name, cleanup_vm_resources, create_nic,
@@ -23,10 +24,10 @@ use crate::{
         get_public_ip_address,
     },
     startup_script::generate_server_startup_script,
-    state::AzureProvisioningState,
+    state::{AzurePowerState, AzureProvisioningState},
 };
 
-const VM_SIZES: &[&str] = &[
+pub(crate) const VM_SIZES: &[&str] = &[
     "Standard_B1s",
     "Standard_B1ms",
     "Standard_B2s",
@@ -39,6 +40,10 @@ const IMAGE_PUBLISHER: &str = "Canonical";
 const IMAGE_OFFER: &str = "0001-com-ubuntu-server-jammy";
 const IMAGE_SKU: &str = "22_04-lts-gen2";
 const API_VERSION_COMPUTE: &str = "2024-07-01";
+const API_VERSION_AUTHORIZATION: &str = "2022-04-01";
+/// The built-in Contributor role, granted to a VM's own identity on the VM and
+/// the network resources deleted with it so the VM can delete itself.
+const CONTRIBUTOR_ROLE_ID: &str = "b24988ac-6180-42a0-ab88-20f7382dd24c";
 
 fn build_spawn_error(location: &str, context: &str, error: impl std::fmt::Display) -> Error {
     let reason = format!("{}: {}", context, error);
@@ -80,10 +85,23 @@ pub async fn spawn_instance(
 
     let resource_group = build_resource_group_name(location);
 
-    let public_ipv4_id =
-        ensure_public_ip(client, location, &vm_name, IpVersion::V4)
+    // A reserved IP is named apart from the VM, so cleanup leaves it alone.
+    let reserved_ip = if params.reserve_ip {
+        Some(
+            ensure_reserved_public_ip(client, location)
+                .await
+                .map_err(|error| build_spawn_error(location, "Reserved public IP", error))?,
+        )
+    } else {
+        None
+    };
+
+    let public_ipv4_id = match &reserved_ip {
+        Some(reserved) => reserved.id.clone(),
+        None => ensure_public_ip(client, location, &vm_name, IpVersion::V4)
             .await
-            .map_err(|error| build_spawn_error(location, "Public IP", error))?;
+            .map_err(|error| build_spawn_error(location, "Public IP", error))?,
+    };
 
     let public_ipv6_id = match ensure_public_ip(client, location, &vm_name, IpVersion::V6).await {
         Ok(id) => id,
@@ -111,9 +129,14 @@ pub async fn spawn_instance(
         }
     };
 
-    let custom_data =
-        generate_server_startup_script(params.server_private_key, params.client_public_key)
-            .map_err(|error| build_spawn_error(location, "Startup script", error))?;
+    let custom_data = generate_server_startup_script(
+        params.client_public_key,
+        params.preshared_key,
+        params.status_key,
+        params.status_access,
+        params.lifetime,
+    )
+    .map_err(|error| build_spawn_error(location, "Startup script", error))?;
     let vm_path = client.build_subscription_path(&format!(
         "/resourceGroups/{}/providers/Microsoft.Compute/virtualMachines/{}",
         resource_group, vm_name
@@ -135,6 +158,10 @@ pub async fn spawn_instance(
             let vm_body = VmRequest {
                 location: location.to_string(),
                 tags: vm_tags.clone(),
+                // The startup script's watcher deletes the VM as this identity.
+                identity: params.lifetime.is_limited().then(|| VmIdentity {
+                    identity_type: "SystemAssigned".to_string(),
+                }),
                 properties: VmProperties {
                     hardware_profile: HardwareProfile {
                         vm_size: vm_size.to_string(),
@@ -165,6 +192,9 @@ pub async fn spawn_instance(
                     network_profile: NetworkProfile {
                         network_interfaces: vec![NetworkInterfaceReference {
                             id: nic_id.clone(),
+                            properties: DeleteOption {
+                                delete_option: "Delete".to_string(),
+                            },
                         }],
                     },
                 },
@@ -209,9 +239,39 @@ pub async fn spawn_instance(
         return Err(error);
     }
 
-    let public_ip_v4 = get_public_ip_address(client, location, &vm_name, IpVersion::V4)
-        .await
-        .unwrap_or_default();
+    if params.lifetime.is_limited() {
+        let mut scopes = vec![vm_path.as_str(), nic_id.as_str(), public_ipv6_id.as_str()];
+        if reserved_ip.is_none() {
+            scopes.push(public_ipv4_id.as_str());
+        }
+        if let Err(error) = grant_self_delete(client, &vm_url, &scopes).await {
+            // Without the grant the VM could only power itself off, which
+            // leaves it allocated and billed.
+            warn!(
+                "[Azure] Could not let VM '{}' delete itself, deleting it",
+                vm_name
+            );
+            let instance_id = format!("{}/{}", resource_group, vm_name);
+            if let Err(terminate_error) = terminate_instance(client, &instance_id).await {
+                warn!(
+                    "[Azure] Failed to delete VM '{}': {}",
+                    vm_name, terminate_error
+                );
+            }
+            return Err(build_spawn_error(
+                location,
+                "Self-delete permission (needs Owner or User Access Administrator)",
+                error,
+            ));
+        }
+    }
+
+    let public_ip_v4 = match reserved_ip {
+        Some(reserved) => reserved.ip_address,
+        None => get_public_ip_address(client, location, &vm_name, IpVersion::V4)
+            .await
+            .unwrap_or_default(),
+    };
     let public_ip_v6 = get_public_ip_address(client, location, &vm_name, IpVersion::V6)
         .await
         .unwrap_or_default();
@@ -249,6 +309,44 @@ pub async fn spawn_instance(
     })
 }
 
+/// Grants the VM's system-assigned identity Contributor on each of `scopes`,
+/// retrying while the new identity replicates.
+async fn grant_self_delete(client: &AzureClient, vm_url: &str, scopes: &[&str]) -> Result<()> {
+    let vm: VmResponse = client.get(vm_url).await?;
+    let principal_id = vm
+        .identity
+        .and_then(|identity| identity.principal_id)
+        .ok_or_else(|| ComputeProvisioningError::InstanceSpawnFailed {
+            region_name: vm.location.unwrap_or_default(),
+            reason: "VM has no managed identity".to_string(),
+        })?;
+    let role_definition_id = client.build_subscription_path(&format!(
+        "/providers/Microsoft.Authorization/roleDefinitions/{}",
+        CONTRIBUTOR_ROLE_ID
+    ));
+
+    for scope in scopes {
+        let url = client.build_arm_url(
+            &format!(
+                "{}/providers/Microsoft.Authorization/roleAssignments/{}",
+                scope,
+                Uuid::new_v4()
+            ),
+            API_VERSION_AUTHORIZATION,
+        );
+        let body = RoleAssignmentRequest {
+            properties: RoleAssignmentProperties {
+                role_definition_id: role_definition_id.clone(),
+                principal_id: principal_id.clone(),
+                principal_type: "ServicePrincipal".to_string(),
+            },
+        };
+        retry(|| client.put(&url, &body), 12, Duration::from_secs(5)).await?;
+        debug!("[Azure] Granted VM identity Contributor on {}", scope);
+    }
+    Ok(())
+}
+
 pub async fn terminate_instance(client: &AzureClient, instance_id: &str) -> Result<()> {
     info!("[Azure] Terminating instance '{}'...", instance_id);
     let (resource_group, vm_name) = parse_instance_id(instance_id)?;
@@ -290,6 +388,82 @@ pub async fn terminate_instance(client: &AzureClient, instance_id: &str) -> Resu
     Ok(())
 }
 
+pub async fn stop_instance(client: &AzureClient, instance_id: &str) -> Result<()> {
+    info!("[Azure] Deallocating instance '{}'...", instance_id);
+    let (resource_group, vm_name) = parse_instance_id(instance_id)?;
+
+    // Deallocating, unlike a plain stop, releases the compute so it stops
+    // being billed. The static public IPs stay with the NIC.
+    let url = build_vm_action_url(client, resource_group, vm_name, "deallocate");
+    let async_op_url = client.post_action(&url).await.map_err(|error| {
+        ComputeProvisioningError::InstanceStopFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    if let Some(operation_url) = async_op_url {
+        client
+            .wait_for_async_operation(&operation_url)
+            .await
+            .map_err(|error| ComputeProvisioningError::InstanceStopFailed {
+                instance_identifier: instance_id.to_string(),
+                reason: error.to_string(),
+            })?;
+    }
+
+    info!("[Azure] VM '{}' deallocated.", vm_name);
+    Ok(())
+}
+
+pub async fn start_instance(client: &AzureClient, instance_id: &str) -> Result<InstanceInfo> {
+    info!("[Azure] Starting instance '{}'...", instance_id);
+    let (resource_group, vm_name) = parse_instance_id(instance_id)?;
+
+    let url = build_vm_action_url(client, resource_group, vm_name, "start");
+    let async_op_url = client.post_action(&url).await.map_err(|error| {
+        ComputeProvisioningError::InstanceStartFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    if let Some(operation_url) = async_op_url {
+        client
+            .wait_for_async_operation(&operation_url)
+            .await
+            .map_err(|error| ComputeProvisioningError::InstanceStartFailed {
+                instance_identifier: instance_id.to_string(),
+                reason: error.to_string(),
+            })?;
+    }
+
+    let vm_path = client.build_subscription_path(&format!(
+        "/resourceGroups/{}/providers/Microsoft.Compute/virtualMachines/{}",
+        resource_group, vm_name
+    ));
+    let virtual_machine: VmResponse = client
+        .get(&client.build_arm_url(&vm_path, API_VERSION_COMPUTE))
+        .await?;
+    let instance = resolve_vm_info(client, &virtual_machine)
+        .await?
+        .ok_or(ComputeProvisioningError::NoInstanceInResponse)?;
+
+    info!("[Azure] VM '{}' started.", vm_name);
+    Ok(instance)
+}
+
+fn build_vm_action_url(
+    client: &AzureClient,
+    resource_group: &str,
+    vm_name: &str,
+    action: &str,
+) -> String {
+    let path = client.build_subscription_path(&format!(
+        "/resourceGroups/{}/providers/Microsoft.Compute/virtualMachines/{}/{}",
+        resource_group, vm_name, action
+    ));
+    client.build_arm_url(&path, API_VERSION_COMPUTE)
+}
+
 pub async fn list_instances(client: &AzureClient, location: &str) -> Result<Vec<InstanceInfo>> {
     let all = list_all_instances(client).await?;
     Ok(all
@@ -385,7 +559,10 @@ async fn resolve_vm_info(
         .and_then(|properties| properties.provisioning_state.as_deref())
         .unwrap_or("Unknown");
 
-    let state: InstanceState = AzureProvisioningState::from(provisioning_state).into();
+    let mut state: InstanceState = AzureProvisioningState::from(provisioning_state).into();
+    if state == InstanceState::Running {
+        state = get_power_state(client, vm_id).await;
+    }
 
     let instance_type = virtual_machine
         .properties
@@ -401,9 +578,14 @@ async fn resolve_vm_info(
         .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
         .map(|datetime| datetime.with_timezone(&Utc));
 
-    let public_ip_v4 = get_public_ip_address(client, &location, &vm_name, IpVersion::V4)
+    let mut public_ip_v4 = get_public_ip_address(client, &location, &vm_name, IpVersion::V4)
         .await
         .unwrap_or_default();
+    if public_ip_v4.is_empty() {
+        public_ip_v4 = find_attached_reserved_ip(client, &vm_name)
+            .await
+            .unwrap_or_default();
+    }
     let public_ip_v6 = get_public_ip_address(client, &location, &vm_name, IpVersion::V6)
         .await
         .unwrap_or_default();
@@ -425,6 +607,25 @@ async fn resolve_vm_info(
     }))
 }
 
+async fn get_power_state(client: &AzureClient, vm_id: &str) -> InstanceState {
+    let url = client.build_arm_url(&format!("{}/instanceView", vm_id), API_VERSION_COMPUTE);
+    let instance_view: VmInstanceViewResponse = match client.get(&url).await {
+        Ok(instance_view) => instance_view,
+        Err(error) => {
+            warn!("[Azure] Failed to read power state of {}: {}", vm_id, error);
+            return InstanceState::Unknown;
+        }
+    };
+    instance_view
+        .statuses
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|status| status.code.as_deref())
+        .find(|code| code.starts_with("PowerState/"))
+        .map(|code| AzurePowerState::from(code).into())
+        .unwrap_or(InstanceState::Unknown)
+}
+
 fn parse_instance_id(instance_id: &str) -> Result<(&str, &str)> {
     let mut parts = instance_id.splitn(2, '/');
     let resource_group = parts
@@ -194,6 +194,8 @@ pub struct PublicIpProperties {
 #[derive(Deserialize)]
 pub struct PublicIpResponse {
     pub id: Option<String>,
+    pub location: Option<String>,
+    pub tags: Option<HashMap<String, String>>,
     pub properties: Option<PublicIpResponseProperties>,
 }
 
@@ -201,6 +203,12 @@ pub struct PublicIpResponse {
 #[serde(rename_all = "camelCase")]
 pub struct PublicIpResponseProperties {
     pub ip_address: Option<String>,
+    pub ip_configuration: Option<ResourceReference>,
+}
+
+#[derive(Deserialize)]
+pub struct PublicIpListResponse {
+    pub value: Option<Vec<PublicIpResponse>>,
 }
 
 // ── NIC ───────────────────────────────────────────────────────────────────────
@@ -232,7 +240,7 @@ pub struct IpConfigurationProperties {
     #[serde(rename = "privateIPAddressVersion")]
     pub private_ip_address_version: String,
     pub subnet: ResourceReference,
-    pub public_ip_address: ResourceReference,
+    pub public_ip_address: DeletableReference,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub primary: Option<bool>,
 }
@@ -248,6 +256,8 @@ pub struct NicResponse {
 pub struct VmRequest {
     pub location: String,
     pub tags: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<VmIdentity>,
     pub properties: VmProperties,
 }
 
@@ -315,6 +325,47 @@ pub struct NetworkProfile {
 #[derive(Serialize)]
 pub struct NetworkInterfaceReference {
     pub id: String,
+    pub properties: DeleteOption,
+}
+
+/// A reference to a resource that says what happens to it when the VM using
+/// it is deleted.
+#[derive(Serialize)]
+pub struct DeletableReference {
+    pub id: String,
+    pub properties: DeleteOption,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteOption {
+    /// `Delete` or `Detach`.
+    pub delete_option: String,
+}
+
+#[derive(Serialize)]
+pub struct VmIdentity {
+    #[serde(rename = "type")]
+    pub identity_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VmIdentityResponse {
+    pub principal_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RoleAssignmentRequest {
+    pub properties: RoleAssignmentProperties,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleAssignmentProperties {
+    pub role_definition_id: String,
+    pub principal_id: String,
+    pub principal_type: String,
 }
 
 #[derive(Deserialize)]
@@ -328,6 +379,7 @@ pub struct VmResponse {
     pub name: Option<String>,
     pub location: Option<String>,
     pub tags: Option<HashMap<String, String>>,
+    pub identity: Option<VmIdentityResponse>,
     pub properties: Option<VmResponseProperties>,
 }
 
@@ -339,6 +391,16 @@ pub struct VmResponseProperties {
     pub time_created: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct VmInstanceViewResponse {
+    pub statuses: Option<Vec<InstanceViewStatus>>,
+}
+
+#[derive(Deserialize)]
+pub struct InstanceViewStatus {
+    pub code: Option<String>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HardwareProfileResponse {
@@ -4,14 +4,15 @@ use log::*;
 use serde::Serialize;
 use tokio::time::{Duration, sleep};
 
-use crate::client::AzureClient;
+use crate::client::{AzureClient, extract_name_from_id};
 use crate::models::{
-    AddressSpace, EmptyRequest, IpConfiguration, IpConfigurationProperties, LocationListResponse,
-    NicProperties, NicRequest, NicResponse, NsgProperties, NsgRequest, NsgResponse,
-    ProviderRegistrationResponse, PublicIpProperties, PublicIpRequest, PublicIpResponse,
-    PublicIpSku, ResourceGroupRequest, ResourceGroupResponse, ResourceReference, SecurityRule,
-    SecurityRuleProperties, SubnetRequest, SubnetRequestProperties, SubnetResponse, VnetProperties,
-    VnetRequest, VnetResponse, byocvpn_tags,
+    AddressSpace, DeletableReference, DeleteOption, EmptyRequest, IpConfiguration,
+    IpConfigurationProperties, LocationListResponse, NicProperties, NicRequest, NicResponse,
+    NsgProperties, NsgRequest, NsgResponse, ProviderRegistrationResponse, PublicIpProperties,
+    PublicIpRequest, PublicIpResponse, PublicIpSku, ResourceGroupRequest, ResourceGroupResponse,
+    ResourceReference, SecurityRule, SecurityRuleProperties, SubnetRequest,
+    SubnetRequestProperties, SubnetResponse, VnetProperties, VnetRequest, VnetResponse,
+    byocvpn_tags,
 };
 
 const API_VERSION_RESOURCE_GROUPS: &str = "2021-04-01";
@@ -892,6 +893,15 @@ pub async fn create_nic(
 ) -> Result<String> {
     let resource_group = build_resource_group_name(location);
     let nic_name = format!("{}-nic", vm_name);
+    // A reserved IP is named apart from the VM and outlives it; an IP named
+    // after the VM is deleted with it, so a VM that deletes itself leaves
+    // nothing behind.
+    let ipv4_delete_option =
+        if extract_name_from_id(public_ipv4_id).is_some_and(|name| name.starts_with(vm_name)) {
+            "Delete"
+        } else {
+            "Detach"
+        };
 
     let path = client.build_subscription_path(&format!(
         "/resourceGroups/{}/providers/Microsoft.Network/networkInterfaces/{}",
@@ -915,8 +925,11 @@ pub async fn create_nic(
                         subnet: ResourceReference {
                             id: subnet_id.to_string(),
                         },
-                        public_ip_address: ResourceReference {
+                        public_ip_address: DeletableReference {
                             id: public_ipv4_id.to_string(),
+                            properties: DeleteOption {
+                                delete_option: ipv4_delete_option.to_string(),
+                            },
                         },
                         primary: Some(true),
                     },
@@ -929,8 +942,11 @@ pub async fn create_nic(
                         subnet: ResourceReference {
                             id: subnet_id.to_string(),
                         },
-                        public_ip_address: ResourceReference {
+                        public_ip_address: DeletableReference {
                             id: public_ipv6_id.to_string(),
+                            properties: DeleteOption {
+                                delete_option: "Delete".to_string(),
+                            },
                         },
                         primary: None,
                     },
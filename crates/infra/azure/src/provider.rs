@@ -3,8 +3,9 @@ use std::str::FromStr;
 use async_trait::async_trait;
 use byocvpn_core::{
     cloud_provider::{
-        CloudProvider, CloudProviderName, InstanceInfo, PermissionStatus, SpawnInstanceParams,
-        SpawnStep, TerminateInstanceParams,
+        CloudProvider, CloudProviderName, InstanceInfo, PermissionStatus, ReleaseReservedIpParams,
+        ReservedIpInfo, SpawnInstanceParams, SpawnStep, StartInstanceParams, StopInstanceParams,
+        TerminateInstanceParams,
     },
     commands::setup::Region,
     error::{NetworkProvisioningError, Result},
@@ -13,7 +14,7 @@ use log::*;
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::{auth::create_credential, client::AzureClient, instance, network};
+use crate::{address, auth::create_credential, client::AzureClient, instance, network};
 
 const REQUIRED_ACTIONS: &[&str] = &[
     "Microsoft.Compute/register/action",
@@ -282,6 +283,14 @@ impl CloudProvider for AzureProvider {
         instance::terminate_instance(&self.client, params.instance_id).await
     }
 
+    async fn stop_instance(&self, params: &StopInstanceParams) -> Result<()> {
+        instance::stop_instance(&self.client, params.instance_id).await
+    }
+
+    async fn start_instance(&self, params: &StartInstanceParams) -> Result<InstanceInfo> {
+        instance::start_instance(&self.client, params.instance_id).await
+    }
+
     async fn list_instances(&self, region: Option<&str>) -> Result<Vec<InstanceInfo>> {
         match region {
             Some(region_name) => instance::list_instances(&self.client, region_name).await,
@@ -289,6 +298,14 @@ impl CloudProvider for AzureProvider {
         }
     }
 
+    async fn list_reserved_ips(&self, region: Option<&str>) -> Result<Vec<ReservedIpInfo>> {
+        address::list_reserved_ips(&self.client, region).await
+    }
+
+    async fn release_reserved_ip(&self, params: &ReleaseReservedIpParams) -> Result<()> {
+        address::release_reserved_ip(&self.client, params.address_id).await
+    }
+
     async fn get_regions(&self) -> Result<Vec<Region>> {
         let region_list = network::list_regions(&self.client).await?;
         Ok(region_list
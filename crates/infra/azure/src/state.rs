@@ -40,3 +40,44 @@ impl From<AzureProvisioningState> for InstanceState {
         }
     }
 }
+
+/// The `PowerState/*` status from a VM's instance view. The provisioning
+/// state stays `Succeeded` while a VM is stopped, so this decides whether a
+/// provisioned VM is actually running.
+pub enum AzurePowerState {
+    Starting,
+    Running,
+    Stopping,
+    Stopped,
+    Deallocating,
+    Deallocated,
+    Unknown,
+}
+
+impl From<&str> for AzurePowerState {
+    fn from(s: &str) -> Self {
+        match s {
+            "PowerState/starting" => Self::Starting,
+            "PowerState/running" => Self::Running,
+            "PowerState/stopping" => Self::Stopping,
+            "PowerState/stopped" => Self::Stopped,
+            "PowerState/deallocating" => Self::Deallocating,
+            "PowerState/deallocated" => Self::Deallocated,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl From<AzurePowerState> for InstanceState {
+    fn from(state: AzurePowerState) -> Self {
+        match state {
+            AzurePowerState::Starting => InstanceState::Unknown,
+            AzurePowerState::Running => InstanceState::Running,
+            AzurePowerState::Stopping => InstanceState::Stopping,
+            AzurePowerState::Stopped => InstanceState::Stopped,
+            AzurePowerState::Deallocating => InstanceState::Stopping,
+            AzurePowerState::Deallocated => InstanceState::Stopped,
+            AzurePowerState::Unknown => InstanceState::Unknown,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use byocvpn_core::{
+    cloud_provider::{CloudProviderName, ReservedIpInfo},
+    error::{NetworkProvisioningError, Result},
+};
+use log::*;
+use uuid::Uuid;
+
+use crate::{
+    client::GcpClient,
+    instance::{INSTANCE_LABEL_KEY, INSTANCE_LABEL_VALUE, MACHINE_TYPE},
+    models::{
+        AddressListResponse, AddressResponse, AggregatedAddressListResponse, CreateAddressRequest,
+        Operation,
+    },
+    network::wait_for_operation_response,
+    pricing,
+};
+
+/// Status of a static address no resource is using.
+const ADDRESS_STATUS_RESERVED: &str = "RESERVED";
+
+/// Returns a static external address created by byocvpn in the region that no
+/// instance is using, reserving a new one when none is free.
+pub async fn ensure_reserved_address(client: &GcpClient, region: &str) -> Result<String> {
+    let addresses = fetch_reserved_addresses(client, region).await?;
+    if let Some(address) = addresses
+        .iter()
+        .filter(|address| address.status.as_deref() == Some(ADDRESS_STATUS_RESERVED))
+        .find_map(|address| address.address.clone())
+    {
+        info!("Reusing static address {} in {}", address, region);
+        return Ok(address);
+    }
+
+    let name = format!(
+        "byocvpn-ip-{}",
+        Uuid::new_v4().to_string().replace('-', "")[..12].to_lowercase()
+    );
+    let mut labels = HashMap::new();
+    labels.insert(
+        INSTANCE_LABEL_KEY.to_string(),
+        INSTANCE_LABEL_VALUE.to_string(),
+    );
+    let body = CreateAddressRequest {
+        name: name.clone(),
+        address_type: "EXTERNAL".to_string(),
+        network_tier: "PREMIUM".to_string(),
+        labels,
+    };
+
+    let url = build_region_addresses_url(client, region);
+    let operation: Operation = client.post(&url, &body).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpAllocationFailed {
+            reason: error.to_string(),
+        }
+    })?;
+    wait_for_operation_response(client, &operation).await?;
+
+    let created: AddressResponse = client.get(&format!("{}/{}", url, name)).await?;
+    let address = created
+        .address
+        .ok_or(NetworkProvisioningError::MissingResourceField {
+            field: "address",
+            resource: "static address",
+        })?;
+    info!(
+        "Reserved static address {} ({}) in {}",
+        address, name, region
+    );
+    Ok(address)
+}
+
+pub async fn list_reserved_addresses(
+    client: &GcpClient,
+    region: &str,
+) -> Result<Vec<ReservedIpInfo>> {
+    let addresses = fetch_reserved_addresses(client, region).await?;
+    Ok(addresses
+        .iter()
+        .filter_map(|address| parse_reserved_ip_info(address, region))
+        .collect())
+}
+
+pub async fn list_all_reserved_addresses(client: &GcpClient) -> Result<Vec<ReservedIpInfo>> {
+    let url = format!(
+        "{}/aggregated/addresses?filter=labels.{label_key}%3D{label_value}&maxResults=500",
+        client.build_compute_base_url(),
+        label_key = INSTANCE_LABEL_KEY,
+        label_value = INSTANCE_LABEL_VALUE,
+    );
+    let response: AggregatedAddressListResponse = client.get(&url).await?;
+
+    let mut reserved_ips = Vec::new();
+    for (region_key, region_data) in response.items.unwrap_or_default() {
+        let region = region_key.trim_start_matches("regions/");
+        for address in region_data.addresses.unwrap_or_default() {
+            if let Some(info) = parse_reserved_ip_info(&address, region) {
+                reserved_ips.push(info);
+            }
+        }
+    }
+    Ok(reserved_ips)
+}
+
+pub async fn release_reserved_address(client: &GcpClient, address_id: &str) -> Result<()> {
+    let (region, name) = address_id.split_once('/').ok_or_else(|| {
+        NetworkProvisioningError::ReservedIpReleaseFailed {
+            address_id: address_id.to_string(),
+            reason: "expected <region>/<name>".to_string(),
+        }
+    })?;
+    let url = format!("{}/{}", build_region_addresses_url(client, region), name);
+    client.delete(&url).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpReleaseFailed {
+            address_id: address_id.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+
+    info!("Static address {} released", address_id);
+    Ok(())
+}
+
+async fn fetch_reserved_addresses(
+    client: &GcpClient,
+    region: &str,
+) -> Result<Vec<AddressResponse>> {
+    let url = format!(
+        "{}?filter=labels.{label_key}%3D{label_value}",
+        build_region_addresses_url(client, region),
+        label_key = INSTANCE_LABEL_KEY,
+        label_value = INSTANCE_LABEL_VALUE,
+    );
+    let response: AddressListResponse = client.get(&url).await?;
+    Ok(response.items.unwrap_or_default())
+}
+
+fn build_region_addresses_url(client: &GcpClient, region: &str) -> String {
+    format!(
+        "{}/regions/{}/addresses",
+        client.build_compute_base_url(),
+        region
+    )
+}
+
+fn parse_reserved_ip_info(address: &AddressResponse, region: &str) -> Option<ReservedIpInfo> {
+    let name = address.name.as_deref()?;
+    // Users are instance self links ending in `zones/<zone>/instances/<name>`,
+    // which matches the `<zone>/<name>` instance IDs.
+    let instance_id = address
+        .users
+        .as_ref()
+        .and_then(|users| users.first())
+        .and_then(|user| user.split_once("/zones/"))
+        .map(|(_, zone_path)| zone_path.replacen("/instances/", "/", 1));
+    let ip_hourly_rate = pricing::get_pricing(MACHINE_TYPE)
+        .map(|pricing| pricing.ip_hourly_rate)
+        .unwrap_or_default();
+
+    Some(ReservedIpInfo {
+        id: format!("{}/{}", region, name),
+        region: region.to_string(),
+        public_ip: address.address.clone()?,
+        provider: CloudProviderName::Gcp,
+        instance_id,
+        ip_hourly_rate,
+    })
+}
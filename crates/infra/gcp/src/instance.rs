@@ -12,9 +12,10 @@ use uuid::Uuid;
 use crate::{
     client::GcpClient,
     models::{
-        AccessConfig, AttachedDisk, CreateInstanceRequest, DiskInitializeParams, InstanceMetadata,
-        InstanceResponse, InstanceTags, Ipv6AccessConfig, MetadataItem, NetworkInterface,
-        Operation, ZoneInstanceListResponse, ZoneOperationResponse,
+        AccessConfig, AttachedDisk, CreateInstanceRequest, DiskInitializeParams, DurationValue,
+        EmptyRequest, InstanceMetadata, InstanceResponse, InstanceTags, Ipv6AccessConfig,
+        MetadataItem, NetworkInterface, Operation, Scheduling, ServiceAccount,
+        ZoneInstanceListResponse, ZoneOperationResponse,
     },
     network::build_primary_zone_for_region,
     startup_script::generate_server_startup_script,
@@ -22,23 +23,33 @@ use crate::{
 };
 use log::*;
 
-const MACHINE_TYPE: &str = "e2-micro";
+pub(crate) const MACHINE_TYPE: &str = "e2-micro";
 const DISK_TYPE_SUFFIX: &str = "pd-standard";
-const INSTANCE_LABEL_KEY: &str = "created-by";
-const INSTANCE_LABEL_VALUE: &str = "byocvpn";
+pub(crate) const INSTANCE_LABEL_KEY: &str = "created-by";
+pub(crate) const INSTANCE_LABEL_VALUE: &str = "byocvpn";
 const INSTANCE_TAG: &str = "byocvpn";
+/// The project's default compute service account, which may delete instances
+/// unless the project has narrowed its roles.
+const SERVICE_ACCOUNT_EMAIL: &str = "default";
+const COMPUTE_SCOPE: &str = "https://www.googleapis.com/auth/compute";
 
 pub async fn spawn_instance(
     client: &GcpClient,
     subnet_self_link: &str,
     image_self_link: &str,
     region: &str,
+    reserved_ip: Option<String>,
     params: &SpawnInstanceParams<'_>,
 ) -> Result<InstanceInfo> {
     let spawn_id = params.spawn_id;
     let zone = build_primary_zone_for_region(region);
-    let startup_script =
-        generate_server_startup_script(params.server_private_key, params.client_public_key)?;
+    let startup_script = generate_server_startup_script(
+        params.client_public_key,
+        params.preshared_key,
+        params.status_key,
+        params.status_access,
+        params.lifetime,
+    )?;
 
     let instance_name = format!(
         "byocvpn-{}",
@@ -78,6 +89,7 @@ pub async fn spawn_instance(
                 access_type: "ONE_TO_ONE_NAT".to_string(),
                 name: "External NAT".to_string(),
                 network_tier: "PREMIUM".to_string(),
+                nat_ip: reserved_ip,
             }],
             ipv6_access_configs: Some(vec![Ipv6AccessConfig {
                 access_type: "DIRECT_IPV6".to_string(),
@@ -95,6 +107,25 @@ pub async fn spawn_instance(
         tags: InstanceTags {
             items: vec![INSTANCE_TAG.to_string()],
         },
+        // Backs the startup script's own max lifetime with one GCP enforces.
+        scheduling: params
+            .lifetime
+            .max_lifetime_minutes
+            .map(|minutes| Scheduling {
+                max_run_duration: DurationValue {
+                    seconds: (u64::from(minutes) * 60).to_string(),
+                },
+                instance_termination_action: "DELETE".to_string(),
+            }),
+        // Lets the startup script's watcher delete the instance it runs on.
+        service_accounts: if params.lifetime.is_limited() {
+            vec![ServiceAccount {
+                email: SERVICE_ACCOUNT_EMAIL.to_string(),
+                scopes: vec![COMPUTE_SCOPE.to_string()],
+            }]
+        } else {
+            Vec::new()
+        },
     };
 
     let url = format!(
@@ -156,6 +187,59 @@ pub async fn terminate_instance(client: &GcpClient, instance_id: &str) -> Result
     })
 }
 
+pub async fn stop_instance(client: &GcpClient, instance_id: &str) -> Result<()> {
+    let (zone, instance_name) = parse_instance_id(instance_id)?;
+    let url = format!(
+        "{}/zones/{}/instances/{}/stop",
+        client.build_compute_base_url(),
+        zone,
+        instance_name
+    );
+    let _: Operation = client.post(&url, &EmptyRequest {}).await.map_err(|error| {
+        ComputeProvisioningError::InstanceStopFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    info!("[GCP] Instance '{}' stopping.", instance_name);
+    Ok(())
+}
+
+pub async fn start_instance(
+    client: &GcpClient,
+    region: &str,
+    instance_id: &str,
+) -> Result<InstanceInfo> {
+    let (zone, instance_name) = parse_instance_id(instance_id)?;
+    let url = format!(
+        "{}/zones/{}/instances/{}/start",
+        client.build_compute_base_url(),
+        zone,
+        instance_name
+    );
+    let operation: Operation = client.post(&url, &EmptyRequest {}).await.map_err(|error| {
+        ComputeProvisioningError::InstanceStartFailed {
+            instance_identifier: instance_id.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    if let Some(operation_url) = operation.self_link.as_deref() {
+        wait_for_zone_operation(client, operation_url, region).await?;
+    }
+
+    let instance = get_instance(client, zone, instance_name).await?;
+    let info = parse_instance_info(&instance, zone, region)
+        .ok_or(ComputeProvisioningError::NoInstanceInResponse)?;
+    if info.public_ip_v4.is_empty() {
+        return Err(ComputeProvisioningError::MissingPublicIpv4.into());
+    }
+    info!(
+        "[GCP] Instance '{}' started with IPv4 {}.",
+        instance_name, info.public_ip_v4
+    );
+    Ok(info)
+}
+
 pub async fn list_instances(client: &GcpClient, region: &str) -> Result<Vec<InstanceInfo>> {
     let url = format!(
         "{}/aggregated/instances?filter=labels.{label_key}%3D{label_value}&maxResults=500",
@@ -1,3 +1,4 @@
+pub mod address;
 pub mod auth;
 pub mod client;
 pub mod credentials;
@@ -154,6 +154,28 @@ pub struct CreateInstanceRequest {
     pub metadata: InstanceMetadata,
     pub labels: HashMap<String, String>,
     pub tags: InstanceTags,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduling: Option<Scheduling>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub service_accounts: Vec<ServiceAccount>,
+}
+
+#[derive(Serialize)]
+pub struct ServiceAccount {
+    pub email: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scheduling {
+    pub max_run_duration: DurationValue,
+    pub instance_termination_action: String,
+}
+
+#[derive(Serialize)]
+pub struct DurationValue {
+    pub seconds: String,
 }
 
 #[derive(Serialize)]
@@ -189,6 +211,8 @@ pub struct AccessConfig {
     pub access_type: String,
     pub name: String,
     pub network_tier: String,
+    #[serde(rename = "natIP", skip_serializing_if = "Option::is_none")]
+    pub nat_ip: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -265,3 +289,35 @@ pub struct ZoneOperationResponse {
 
 #[derive(Serialize)]
 pub struct EmptyRequest {}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAddressRequest {
+    pub name: String,
+    pub address_type: String,
+    pub network_tier: String,
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub struct AddressResponse {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub status: Option<String>,
+    pub users: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct AddressListResponse {
+    pub items: Option<Vec<AddressResponse>>,
+}
+
+#[derive(Deserialize)]
+pub struct AggregatedAddressListResponse {
+    pub items: Option<HashMap<String, RegionAddresses>>,
+}
+
+#[derive(Deserialize)]
+pub struct RegionAddresses {
+    pub addresses: Option<Vec<AddressResponse>>,
+}
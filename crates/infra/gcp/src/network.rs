@@ -61,7 +61,10 @@ async fn wait_for_operation(client: &GcpClient, operation_url: &str) -> Result<(
     .await
 }
 
-async fn wait_for_operation_response(client: &GcpClient, operation: &Operation) -> Result<()> {
+pub(crate) async fn wait_for_operation_response(
+    client: &GcpClient,
+    operation: &Operation,
+) -> Result<()> {
     let operation_url = operation
         .self_link
         .as_deref()
@@ -4,8 +4,9 @@ use std::str::FromStr;
 use async_trait::async_trait;
 use byocvpn_core::{
     cloud_provider::{
-        CloudProvider, CloudProviderName, InstanceInfo, PermissionStatus, SpawnInstanceParams,
-        SpawnStep, TerminateInstanceParams,
+        CloudProvider, CloudProviderName, InstanceInfo, PermissionStatus, ReleaseReservedIpParams,
+        ReservedIpInfo, SpawnInstanceParams, SpawnStep, StartInstanceParams, StopInstanceParams,
+        TerminateInstanceParams,
     },
     commands::setup::Region,
     error::{NetworkProvisioningError, Result},
@@ -15,7 +16,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
-    auth::parse_credentials_from_service_account_json, client::GcpClient, instance, network,
+    address, auth::parse_credentials_from_service_account_json, client::GcpClient, instance,
+    network,
 };
 
 const REQUIRED_PERMISSIONS: &[&str] = &[
@@ -280,11 +282,18 @@ impl CloudProvider for GcpProvider {
         let image_self_link = network::get_ubuntu_image_self_link(&self.client).await?;
         debug!("Resolved GCP Ubuntu image {}", image_self_link);
 
+        let reserved_ip = if params.reserve_ip {
+            Some(address::ensure_reserved_address(&self.client, params.region).await?)
+        } else {
+            None
+        };
+
         instance::spawn_instance(
             &self.client,
             &subnet_self_link,
             &image_self_link,
             params.region,
+            reserved_ip,
             params,
         )
         .await
@@ -294,6 +303,14 @@ impl CloudProvider for GcpProvider {
         instance::terminate_instance(&self.client, params.instance_id).await
     }
 
+    async fn stop_instance(&self, params: &StopInstanceParams) -> Result<()> {
+        instance::stop_instance(&self.client, params.instance_id).await
+    }
+
+    async fn start_instance(&self, params: &StartInstanceParams) -> Result<InstanceInfo> {
+        instance::start_instance(&self.client, params.region, params.instance_id).await
+    }
+
     async fn list_instances(&self, region: Option<&str>) -> Result<Vec<InstanceInfo>> {
         match region {
             Some(region_name) => instance::list_instances(&self.client, region_name).await,
@@ -301,6 +318,17 @@ impl CloudProvider for GcpProvider {
         }
     }
 
+    async fn list_reserved_ips(&self, region: Option<&str>) -> Result<Vec<ReservedIpInfo>> {
+        match region {
+            Some(region_name) => address::list_reserved_addresses(&self.client, region_name).await,
+            None => address::list_all_reserved_addresses(&self.client).await,
+        }
+    }
+
+    async fn release_reserved_ip(&self, params: &ReleaseReservedIpParams) -> Result<()> {
+        address::release_reserved_address(&self.client, params.address_id).await
+    }
+
     async fn get_regions(&self) -> Result<Vec<Region>> {
         network::ensure_compute_api_enabled(&self.client).await?;
         let region_list = network::list_regions(&self.client).await?;
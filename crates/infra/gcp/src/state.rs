@@ -38,7 +38,7 @@ impl From<GcpInstanceStatus> for InstanceState {
             GcpInstanceStatus::Suspending => InstanceState::Stopping,
             GcpInstanceStatus::Suspended => InstanceState::Stopped,
             GcpInstanceStatus::Repairing => InstanceState::Error,
-            GcpInstanceStatus::Terminated => InstanceState::Unknown,
+            GcpInstanceStatus::Terminated => InstanceState::Stopped,
             GcpInstanceStatus::Unknown => InstanceState::Unknown,
         }
     }
@@ -0,0 +1,196 @@
+use byocvpn_core::{
+    cloud_provider::{CloudProviderName, ReservedIpInfo},
+    error::{NetworkProvisioningError, Result},
+};
+use log::*;
+use tokio::time::{Duration, sleep};
+
+use crate::{
+    client::OciClient,
+    instance::INSTANCE_SHAPE,
+    models::{
+        CreatePublicIpRequest, PrivateIp, PublicIpResponse, UpdatePublicIpRequest, VnicAttachment,
+        byocvpn_tags,
+    },
+    pricing,
+};
+
+const RESERVED_IP_DISPLAY_NAME: &str = "byocvpn-reserved-ip";
+const TAG_INSTANCE_KEY: &str = "byocvpn-instance-id";
+
+/// Returns a reserved public IP created by byocvpn in the region that is not
+/// assigned, creating one when none is free.
+pub async fn ensure_reserved_ip(
+    client: &OciClient,
+    compartment_ocid: &str,
+) -> Result<PublicIpResponse> {
+    let public_ips = list_reserved_public_ips(client, compartment_ocid).await?;
+    if let Some(public_ip) = public_ips
+        .into_iter()
+        .find(|public_ip| public_ip.assigned_entity_id.is_none())
+    {
+        info!(
+            "Reusing reserved public IP {}",
+            public_ip.ip_address.as_deref().unwrap_or_default()
+        );
+        return Ok(public_ip);
+    }
+
+    let body = CreatePublicIpRequest {
+        compartment_id: compartment_ocid.to_string(),
+        lifetime: "RESERVED".to_string(),
+        display_name: RESERVED_IP_DISPLAY_NAME.to_string(),
+        freeform_tags: byocvpn_tags(),
+    };
+    let url = client.build_core_url("/publicIps");
+    let public_ip: PublicIpResponse = client.post(&url, &body).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpAllocationFailed {
+            reason: error.to_string(),
+        }
+    })?;
+    info!(
+        "Reserved public IP {} ({})",
+        public_ip.ip_address.as_deref().unwrap_or_default(),
+        public_ip.id
+    );
+    Ok(public_ip)
+}
+
+/// Assigns the reserved IP to the instance's primary private IP. Waits only
+/// for the VNIC, not for the instance to run, so the address is in place
+/// before the startup script needs the internet.
+pub async fn assign_reserved_ip(
+    client: &OciClient,
+    compartment_ocid: &str,
+    instance_ocid: &str,
+    public_ip_ocid: &str,
+) -> Result<()> {
+    let private_ip_ocid = wait_for_primary_private_ip(client, compartment_ocid, instance_ocid)
+        .await
+        .map_err(
+            |error| NetworkProvisioningError::ReservedIpAssociationFailed {
+                address_id: public_ip_ocid.to_string(),
+                reason: error.to_string(),
+            },
+        )?;
+
+    let mut freeform_tags = byocvpn_tags();
+    freeform_tags.insert(TAG_INSTANCE_KEY.to_string(), instance_ocid.to_string());
+    let body = UpdatePublicIpRequest {
+        private_ip_id: private_ip_ocid,
+        freeform_tags,
+    };
+    let url = client.build_core_url(&format!("/publicIps/{}", public_ip_ocid));
+    client.put(&url, &body).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpAssociationFailed {
+            address_id: public_ip_ocid.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    debug!(
+        "Reserved public IP {} assigned to instance {}",
+        public_ip_ocid, instance_ocid
+    );
+    Ok(())
+}
+
+pub async fn list_reserved_ips(
+    client: &OciClient,
+    compartment_ocid: &str,
+    region: &str,
+) -> Result<Vec<ReservedIpInfo>> {
+    let ip_hourly_rate = pricing::get_pricing(INSTANCE_SHAPE)
+        .map(|pricing| pricing.ip_hourly_rate)
+        .unwrap_or_default();
+    let public_ips = list_reserved_public_ips(client, compartment_ocid).await?;
+    Ok(public_ips
+        .into_iter()
+        .filter_map(|public_ip| {
+            // The instance tag outlives the assignment, so only trust it
+            // while the address is assigned.
+            let instance_id = public_ip.assigned_entity_id.as_ref().and_then(|_| {
+                public_ip
+                    .freeform_tags
+                    .as_ref()
+                    .and_then(|tags| tags.get(TAG_INSTANCE_KEY))
+                    .cloned()
+            });
+            Some(ReservedIpInfo {
+                id: public_ip.id,
+                region: region.to_string(),
+                public_ip: public_ip.ip_address?,
+                provider: CloudProviderName::Oracle,
+                instance_id,
+                ip_hourly_rate,
+            })
+        })
+        .collect())
+}
+
+pub async fn release_reserved_ip(client: &OciClient, public_ip_ocid: &str) -> Result<()> {
+    debug!("Releasing reserved public IP {}", public_ip_ocid);
+    let url = client.build_core_url(&format!("/publicIps/{}", public_ip_ocid));
+    client.delete(&url).await.map_err(|error| {
+        NetworkProvisioningError::ReservedIpReleaseFailed {
+            address_id: public_ip_ocid.to_string(),
+            reason: error.to_string(),
+        }
+    })?;
+    info!("Reserved public IP {} released", public_ip_ocid);
+    Ok(())
+}
+
+async fn list_reserved_public_ips(
+    client: &OciClient,
+    compartment_ocid: &str,
+) -> Result<Vec<PublicIpResponse>> {
+    let url = client.build_core_url(&format!(
+        "/publicIps?scope=REGION&lifetime=RESERVED&compartmentId={}",
+        compartment_ocid
+    ));
+    let public_ips: Vec<PublicIpResponse> = client.get(&url).await?;
+    Ok(public_ips
+        .into_iter()
+        .filter(|public_ip| {
+            public_ip
+                .freeform_tags
+                .as_ref()
+                .and_then(|tags| tags.get("created-by"))
+                .is_some_and(|value| value == "byocvpn")
+        })
+        .collect())
+}
+
+async fn wait_for_primary_private_ip(
+    client: &OciClient,
+    compartment_ocid: &str,
+    instance_ocid: &str,
+) -> Result<String> {
+    let attachments_url = client.build_core_url(&format!(
+        "/vnicAttachments?compartmentId={}&instanceId={}",
+        compartment_ocid, instance_ocid
+    ));
+    for _ in 0..36 {
+        // The VNIC ID is only filled in once the attachment completes.
+        let attachments: Result<Vec<VnicAttachment>> = client.get(&attachments_url).await;
+        if let Some(vnic_ocid) = attachments
+            .ok()
+            .and_then(|attachments| attachments.into_iter().next())
+            .map(|attachment| attachment.vnic_id)
+        {
+            let url = client.build_core_url(&format!("/privateIps?vnicId={}", vnic_ocid));
+            let private_ips: Vec<PrivateIp> = client.get(&url).await?;
+            if let Some(private_ip) = private_ips
+                .into_iter()
+                .find(|private_ip| private_ip.is_primary.unwrap_or(false))
+            {
+                return Ok(private_ip.id);
+            }
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
+    Err(NetworkProvisioningError::CloudOperationTimedOut {
+        operation: format!("VNIC attachment for instance {}", instance_ocid),
+    }
+    .into())
+}
@@ -0,0 +1,123 @@
+use byocvpn_core::error::{NetworkProvisioningError, Result};
+use log::*;
+
+use crate::{
+    client::OciClient,
+    models::{
+        CreateDynamicGroupRequest, CreatePolicyRequest, DynamicGroup, Policy,
+        UpdateDynamicGroupRequest,
+    },
+};
+
+const DYNAMIC_GROUP_NAME: &str = "byocvpn-servers";
+const DYNAMIC_GROUP_DESCRIPTION: &str = "byocvpn servers allowed to terminate themselves";
+const POLICY_NAME: &str = "byocvpn-servers-self-delete";
+const POLICY_DESCRIPTION: &str = "Lets byocvpn servers terminate themselves";
+
+fn setup_error(step: &str, error: impl ToString) -> NetworkProvisioningError {
+    NetworkProvisioningError::ProviderSetupFailed {
+        step: step.to_string(),
+        reason: error.to_string(),
+    }
+}
+
+/// Lets the given instances terminate themselves with their instance principal.
+///
+/// The instances make up the `byocvpn-servers` dynamic group, which is rewritten
+/// on every call so terminated servers drop out of it, and a tenancy policy
+/// allows that group to terminate instances and nothing else. Identity resources
+/// can only be changed in the home region, so `client` must point there.
+pub async fn ensure_self_delete_access(
+    client: &OciClient,
+    tenancy_ocid: &str,
+    instance_ocids: &[String],
+) -> Result<()> {
+    let matching_rule = format!(
+        "ANY {{{}}}",
+        instance_ocids
+            .iter()
+            .map(|ocid| format!("instance.id = '{}'", ocid))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let groups_url =
+        client.build_identity_url(&format!("/dynamicGroups?compartmentId={}", tenancy_ocid));
+    let groups: Vec<DynamicGroup> = client
+        .get(&groups_url)
+        .await
+        .map_err(|error| setup_error("list dynamic groups", error))?;
+
+    let existing_group = groups
+        .into_iter()
+        .find(|group| group.name == DYNAMIC_GROUP_NAME);
+    match existing_group {
+        Some(group) if group.matching_rule == matching_rule => {}
+        Some(group) => {
+            let url = client.build_identity_url(&format!("/dynamicGroups/{}", group.id));
+            client
+                .put(
+                    &url,
+                    &UpdateDynamicGroupRequest {
+                        description: DYNAMIC_GROUP_DESCRIPTION.to_string(),
+                        matching_rule,
+                    },
+                )
+                .await
+                .map_err(|error| setup_error("update dynamic group", error))?;
+            debug!("Updated OCI dynamic group {}", DYNAMIC_GROUP_NAME);
+        }
+        None => {
+            let _: DynamicGroup = client
+                .post(
+                    &client.build_identity_url("/dynamicGroups"),
+                    &CreateDynamicGroupRequest {
+                        compartment_id: tenancy_ocid.to_string(),
+                        name: DYNAMIC_GROUP_NAME.to_string(),
+                        description: DYNAMIC_GROUP_DESCRIPTION.to_string(),
+                        matching_rule,
+                    },
+                )
+                .await
+                .map_err(|error| setup_error("create dynamic group", error))?;
+            info!("Created OCI dynamic group {}", DYNAMIC_GROUP_NAME);
+        }
+    }
+
+    let policies_url =
+        client.build_identity_url(&format!("/policies?compartmentId={}", tenancy_ocid));
+    let policies: Vec<Policy> = client
+        .get(&policies_url)
+        .await
+        .map_err(|error| setup_error("list policies", error))?;
+    if policies.iter().any(|policy| policy.name == POLICY_NAME) {
+        return Ok(());
+    }
+
+    // Scoping on the operation grants whatever volume and network permissions
+    // termination checks without letting the group do anything else.
+    let statements = ["instance-family", "volume-family", "virtual-network-family"]
+        .iter()
+        .map(|family| {
+            format!(
+                "Allow dynamic-group {} to manage {} in tenancy where request.operation = 'TerminateInstance'",
+                DYNAMIC_GROUP_NAME, family
+            )
+        })
+        .collect();
+    let _: Policy = client
+        .post(
+            &client.build_identity_url("/policies"),
+            &CreatePolicyRequest {
+                compartment_id: tenancy_ocid.to_string(),
+                name: POLICY_NAME.to_string(),
+                description: POLICY_DESCRIPTION.to_string(),
+                statements,
+            },
+        )
+        .await
+        .map_err(|error| setup_error("create self-delete policy", error))?;
+    info!("Created OCI policy {}", POLICY_NAME);
+
+    Ok(())
+}
@@ -9,17 +9,18 @@ use chrono::{DateTime, Utc};
 use tokio::time::{Duration, sleep};
 
 use crate::{
+    address::{assign_reserved_ip, ensure_reserved_ip},
     client::OciClient,
     models::{
-        AvailabilityDomain, CreateVnicDetails, InstanceResponse, LaunchInstanceRequest,
-        ShapeConfig, SourceDetails, Vnic, VnicAttachment, byocvpn_tags,
+        AvailabilityDomain, CreateVnicDetails, EmptyRequest, InstanceResponse,
+        LaunchInstanceRequest, ShapeConfig, SourceDetails, Vnic, VnicAttachment, byocvpn_tags,
     },
     startup_script::generate_server_startup_script,
     state::OciLifecycleState,
 };
 use log::*;
 
-const INSTANCE_SHAPE: &str = "VM.Standard.A1.Flex";
+pub(crate) const INSTANCE_SHAPE: &str = "VM.Standard.A1.Flex";
 const INSTANCE_OCPUS: f32 = 1.0;
 const INSTANCE_MEMORY_GB: f32 = 6.0;
 const INSTANCE_DISPLAY_NAME: &str = "byocvpn-server";
@@ -33,10 +34,21 @@ pub async fn spawn_instance(
     region: &str,
     params: &SpawnInstanceParams<'_>,
 ) -> Result<InstanceInfo> {
-    let user_data =
-        generate_server_startup_script(params.server_private_key, params.client_public_key)?;
+    let user_data = generate_server_startup_script(
+        params.client_public_key,
+        params.preshared_key,
+        params.status_key,
+        params.status_access,
+        params.lifetime,
+    )?;
     let encoded_user_data = BASE64.encode(&user_data);
 
+    let reserved_ip = if params.reserve_ip {
+        Some(ensure_reserved_ip(client, compartment_ocid).await?)
+    } else {
+        None
+    };
+
     let body = LaunchInstanceRequest {
         compartment_id: compartment_ocid.to_string(),
         display_name: INSTANCE_DISPLAY_NAME.to_string(),
@@ -52,7 +64,8 @@ pub async fn spawn_instance(
         },
         create_vnic_details: CreateVnicDetails {
             subnet_id: subnet_ocid.to_string(),
-            assign_public_ip: true,
+            // A private IP takes one public IP, so leave room for the reserved one.
+            assign_public_ip: reserved_ip.is_none(),
             assign_ipv6_ip: subnet_has_ipv6.then_some(true),
         },
         metadata: HashMap::from([("user_data".to_string(), encoded_user_data)]),
@@ -76,6 +89,25 @@ pub async fn spawn_instance(
     })?;
 
     let instance_ocid = response.id.clone();
+    if let Some(public_ip) = &reserved_ip {
+        // The instance was launched without a public IP of its own, so it is
+        // unreachable unless the reserved one is attached.
+        if let Err(error) =
+            assign_reserved_ip(client, compartment_ocid, &instance_ocid, &public_ip.id).await
+        {
+            warn!(
+                "Failed to attach reserved IP to OCI instance {}, terminating it",
+                instance_ocid
+            );
+            if let Err(terminate_error) = terminate_instance(client, &instance_ocid).await {
+                warn!(
+                    "Failed to terminate OCI instance {}: {}",
+                    instance_ocid, terminate_error
+                );
+            }
+            return Err(error);
+        }
+    }
     debug!(
         "OCI instance {} created, waiting for RUNNING state",
         instance_ocid
@@ -110,6 +142,52 @@ pub async fn terminate_instance(client: &OciClient, instance_ocid: &str) -> Resu
     Ok(())
 }
 
+pub async fn stop_instance(client: &OciClient, instance_ocid: &str) -> Result<()> {
+    debug!("Stopping OCI instance {}", instance_ocid);
+    // SOFTSTOP lets the OS shut down cleanly before the instance stops.
+    let url = client.build_core_url(&format!("/instances/{}?action=SOFTSTOP", instance_ocid));
+    let _: InstanceResponse = client.post(&url, &EmptyRequest {}).await.map_err(|error| {
+        CoreError::from(ComputeProvisioningError::InstanceStopFailed {
+            instance_identifier: instance_ocid.to_string(),
+            reason: error.to_string(),
+        })
+    })?;
+    info!("OCI instance {} stopping", instance_ocid);
+    Ok(())
+}
+
+pub async fn start_instance(
+    client: &OciClient,
+    compartment_ocid: &str,
+    region: &str,
+    instance_ocid: &str,
+) -> Result<InstanceInfo> {
+    debug!("Starting OCI instance {}", instance_ocid);
+    let url = client.build_core_url(&format!("/instances/{}?action=START", instance_ocid));
+    let _: InstanceResponse = client.post(&url, &EmptyRequest {}).await.map_err(|error| {
+        CoreError::from(ComputeProvisioningError::InstanceStartFailed {
+            instance_identifier: instance_ocid.to_string(),
+            reason: error.to_string(),
+        })
+    })?;
+    wait_until_running(client, instance_ocid, region).await?;
+
+    let details = get_instance_details(client, instance_ocid).await?;
+    let mut info = build_instance_info(&details, region);
+    let (public_ip_v4, public_ip_v6) =
+        get_public_ips(client, instance_ocid, compartment_ocid).await;
+    info.public_ip_v4 = public_ip_v4;
+    info.public_ip_v6 = public_ip_v6;
+    if info.public_ip_v4.is_empty() {
+        return Err(ComputeProvisioningError::MissingPublicIpv4.into());
+    }
+    info!(
+        "OCI instance {} started — IPv4: {}",
+        instance_ocid, info.public_ip_v4
+    );
+    Ok(info)
+}
+
 pub async fn list_instances(
     client: &OciClient,
     compartment_ocid: &str,
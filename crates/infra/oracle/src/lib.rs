@@ -1,5 +1,7 @@
+mod address;
 mod auth;
 mod client;
+mod identity;
 mod models;
 pub mod credentials;
 mod instance;
@@ -249,3 +249,81 @@ pub struct Region {
 pub struct SubscribeRegionRequest {
     pub region_key: String,
 }
+
+#[derive(Serialize)]
+pub struct EmptyRequest {}
+
+// ── Reserved public IPs ──────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePublicIpRequest {
+    pub compartment_id: String,
+    pub lifetime: String,
+    pub display_name: String,
+    pub freeform_tags: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePublicIpRequest {
+    pub private_ip_id: String,
+    pub freeform_tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicIpResponse {
+    pub id: String,
+    pub ip_address: Option<String>,
+    pub assigned_entity_id: Option<String>,
+    pub freeform_tags: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivateIp {
+    pub id: String,
+    pub is_primary: Option<bool>,
+}
+
+// ── Identity ──────────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DynamicGroup {
+    pub id: String,
+    pub name: String,
+    pub matching_rule: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDynamicGroupRequest {
+    pub compartment_id: String,
+    pub name: String,
+    pub description: String,
+    pub matching_rule: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDynamicGroupRequest {
+    pub description: String,
+    pub matching_rule: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Policy {
+    pub name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePolicyRequest {
+    pub compartment_id: String,
+    pub name: String,
+    pub description: String,
+    pub statements: Vec<String>,
+}
@@ -3,7 +3,8 @@ use std::str::FromStr;
 use async_trait::async_trait;
 use byocvpn_core::{
     cloud_provider::{
-        CloudProvider, CloudProviderName, InstanceInfo, SpawnInstanceParams, SpawnStep,
+        CloudProvider, CloudProviderName, InstanceInfo, ReleaseReservedIpParams, ReservedIpInfo,
+        SpawnInstanceParams, SpawnStep, StartInstanceParams, StopInstanceParams,
         TerminateInstanceParams,
     },
     commands::setup::Region,
@@ -11,7 +12,7 @@ use byocvpn_core::{
 };
 use serde_json::Value;
 
-use crate::{auth::OciCredentials, client::OciClient, instance, network};
+use crate::{address, auth::OciCredentials, client::OciClient, identity, instance, network};
 use log::*;
 
 pub struct OracleProviderConfig {
@@ -293,7 +294,7 @@ impl CloudProvider for OracleProvider {
         let image_ocid = network::get_ubuntu_image(&client, compartment_ocid).await?;
         debug!("Resolved Ubuntu image {} in {}", image_ocid, params.region);
 
-        instance::spawn_instance(
+        let instance_info = instance::spawn_instance(
             &client,
             compartment_ocid,
             &subnet_ocid,
@@ -302,7 +303,42 @@ impl CloudProvider for OracleProvider {
             params.region,
             params,
         )
-        .await
+        .await?;
+
+        if params.lifetime.is_limited() {
+            let self_delete_access = async {
+                let mut instance_ocids: Vec<String> = self
+                    .list_instances(None)
+                    .await?
+                    .into_iter()
+                    .map(|instance| instance.id)
+                    .collect();
+                if !instance_ocids.contains(&instance_info.id) {
+                    instance_ocids.push(instance_info.id.clone());
+                }
+
+                let home_client = self.make_client(None);
+                identity::ensure_self_delete_access(&home_client, compartment_ocid, &instance_ocids)
+                    .await
+            };
+            if let Err(error) = self_delete_access.await {
+                warn!(
+                    "Could not let OCI instance {} delete itself, terminating it: {}",
+                    instance_info.id, error
+                );
+                if let Err(terminate_error) =
+                    instance::terminate_instance(&client, &instance_info.id).await
+                {
+                    error!(
+                        "Failed to terminate OCI instance {}: {}",
+                        instance_info.id, terminate_error
+                    );
+                }
+                return Err(error);
+            }
+        }
+
+        Ok(instance_info)
     }
 
     async fn terminate_instance(&self, params: &TerminateInstanceParams) -> Result<()> {
@@ -310,6 +346,22 @@ impl CloudProvider for OracleProvider {
         instance::terminate_instance(&client, params.instance_id).await
     }
 
+    async fn stop_instance(&self, params: &StopInstanceParams) -> Result<()> {
+        let client = self.make_client(Some(params.region));
+        instance::stop_instance(&client, params.instance_id).await
+    }
+
+    async fn start_instance(&self, params: &StartInstanceParams) -> Result<InstanceInfo> {
+        let client = self.make_client(Some(params.region));
+        instance::start_instance(
+            &client,
+            self.get_compartment_ocid(),
+            params.region,
+            params.instance_id,
+        )
+        .await
+    }
+
     async fn list_instances(&self, region: Option<&str>) -> Result<Vec<InstanceInfo>> {
         if let Some(region_name) = region {
             let client = self.make_client(Some(region_name));
@@ -338,6 +390,34 @@ impl CloudProvider for OracleProvider {
         Ok(results.into_iter().flatten().collect())
     }
 
+    async fn list_reserved_ips(&self, region: Option<&str>) -> Result<Vec<ReservedIpInfo>> {
+        if let Some(region_name) = region {
+            let client = self.make_client(Some(region_name));
+            return address::list_reserved_ips(&client, self.get_compartment_ocid(), region_name)
+                .await;
+        }
+
+        let client = self.make_client(None);
+        let region_list = network::list_regions(&client, self.get_compartment_ocid()).await?;
+        let results = futures::future::join_all(region_list.iter().map(|(name, _)| async move {
+            let client = self.make_client(Some(name));
+            match address::list_reserved_ips(&client, self.get_compartment_ocid(), name).await {
+                Ok(reserved_ips) => reserved_ips,
+                Err(error) => {
+                    error!("Skipping OCI region {}: {}", name, error);
+                    vec![]
+                }
+            }
+        }))
+        .await;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    async fn release_reserved_ip(&self, params: &ReleaseReservedIpParams) -> Result<()> {
+        let client = self.make_client(Some(params.region));
+        address::release_reserved_ip(&client, params.address_id).await
+    }
+
     async fn get_regions(&self) -> Result<Vec<Region>> {
         let client = self.make_client(None);
         let region_list = network::list_all_regions(&client).await?;
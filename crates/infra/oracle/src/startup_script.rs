@@ -1,4 +1,7 @@
-use byocvpn_core::error::{ConfigurationError, Result};
+use byocvpn_core::{
+    cloud_provider::{ServerLifetime, StatusEndpointAccess},
+    error::{ConfigurationError, Result},
+};
 use handlebars::Handlebars;
 use log::*;
 use serde::Serialize;
@@ -7,18 +10,29 @@ use serde::Serialize;
 struct WireguardCloudInitContext {
     wg_config: String,
     status_server_script: &'static str,
+    status_key: String,
+    status_access: String,
+    idle_timeout_minutes: u32,
+    max_lifetime_minutes: u32,
 }
 
 pub fn generate_server_startup_script(
-    server_private_key: &str,
     client_public_key: &str,
+    preshared_key: &str,
+    status_key: &str,
+    status_access: StatusEndpointAccess,
+    lifetime: ServerLifetime,
 ) -> Result<String> {
-    let wg_config = generate_wireguard_server_config(server_private_key, client_public_key)?;
+    let wg_config = generate_wireguard_server_config(client_public_key, preshared_key)?;
 
     let template_text: &str = include_str!("templates/server_startup_script.sh.hbs");
     let context = WireguardCloudInitContext {
         wg_config,
         status_server_script: byocvpn_core::STATUS_SERVER_SCRIPT,
+        status_key: status_key.to_string(),
+        status_access: status_access.to_string(),
+        idle_timeout_minutes: lifetime.idle_timeout_minutes.unwrap_or(0),
+        max_lifetime_minutes: lifetime.max_lifetime_minutes.unwrap_or(0),
     };
 
     let handlebars_registry = Handlebars::new();
@@ -33,18 +47,18 @@ pub fn generate_server_startup_script(
 
 #[derive(Serialize)]
 struct ServerConfigContext {
-    server_private_key: String,
     client_public_key: String,
+    preshared_key: String,
 }
 
 fn generate_wireguard_server_config(
-    server_private_key: &str,
     client_public_key: &str,
+    preshared_key: &str,
 ) -> Result<String> {
     let template_text: &str = include_str!("templates/wireguard_server_config.hbs");
     let context = ServerConfigContext {
-        server_private_key: server_private_key.to_string(),
         client_public_key: client_public_key.to_string(),
+        preshared_key: preshared_key.to_string(),
     };
     let handlebars_registry = Handlebars::new();
     handlebars_registry
@@ -7,17 +7,19 @@ use byocvpn_core::{
         CloudProvider, CloudProviderName, EnableRegionCompleteEvent, EnableRegionJob,
         EnableRegionProgressEvent, InstanceInfo, InstanceState, PricingInfo,
         ProvisionAccountCompleteEvent, ProvisionAccountJob, ProvisionAccountProgressEvent,
-        SpawnCompleteEvent, SpawnJob, SpawnProgressEvent,
+        ReservedIpInfo, ServerLifetime, SpawnCompleteEvent, SpawnJob, SpawnOptions,
+        SpawnProgressEvent, StatusEndpointAccess,
     },
     commands,
-    commands::setup::Region,
+    commands::{connect::ConnectOptions, setup::Region},
     connectivity::{self, ProbeStatus},
     credentials::CredentialStore,
-    crypto::generate_keypair,
+    crypto::{generate_keypair, generate_preshared_key},
     daemon_client::DaemonClient,
     error::{ConfigurationError, Error, Result},
     ledger::LedgerEntry,
     metrics_stream,
+    traffic_history::SessionHistory,
     tunnel::VpnStatus,
 };
 use byocvpn_daemon::daemon_client::UnixDaemonClient;
@@ -133,13 +135,22 @@ pub async fn verify_permissions(
 pub async fn spawn_instance(
     region: String,
     provider: String,
+    status_access: Option<StatusEndpointAccess>,
+    lifetime: Option<ServerLifetime>,
+    reserve_ip: Option<bool>,
     app_handle: AppHandle,
 ) -> Result<SpawnJob> {
     let provider_name = CloudProviderName::from_str(&provider)?;
     let cloud_provider = create_cloud_provider(provider_name.clone()).await?;
 
     let (client_private_key, client_public_key) = generate_keypair();
-    let (server_private_key, server_public_key) = generate_keypair();
+    let preshared_key = generate_preshared_key();
+    let options = SpawnOptions {
+        status_access: status_access.unwrap_or_default(),
+        lifetime: lifetime
+            .unwrap_or_else(|| crate::server_monitor::server_lifetime_from_settings(&app_handle)),
+        reserve_ip: reserve_ip.unwrap_or(false),
+    };
 
     let job = SpawnJob {
         job_id: format!("{}-{}", provider_name, Utc::now().timestamp_millis()),
@@ -167,9 +178,9 @@ pub async fn spawn_instance(
             &region,
             &job_id,
             &client_private_key,
-            &server_private_key,
             &client_public_key,
-            &server_public_key,
+            &preshared_key,
+            options,
             move |step_id, status, error| {
                 progress_handle
                     .state::<SpawnJobRegistry>()
@@ -203,6 +214,9 @@ pub async fn spawn_instance(
                         setup_complete: false,
                         bytes_sent: 0,
                         bytes_received: 0,
+                        stopped_since: None,
+                        stopped_seconds: 0,
+                        restarted_at: None,
                     };
                     ledger.set_entry(&entry);
                 }
@@ -225,6 +239,22 @@ pub async fn spawn_instance(
             Ok(mut instance) => {
                 if let Some(ledger) = LedgerStore::open(&app_handle) {
                     ledger.mark_setup_complete(&instance.id);
+                    // Start billing a newly allocated address right away.
+                    if options.reserve_ip {
+                        match commands::reserved_ip::list_reserved_ips(
+                            &*cloud_provider,
+                            Some(&region),
+                        )
+                        .await
+                        {
+                            Ok(reserved_ips) => ledger.reconcile_reserved_ips(
+                                &reserved_ips,
+                                &[provider_name.clone()],
+                                Some(&region),
+                            ),
+                            Err(error) => warn!("Failed to list reserved IPs: {}", error),
+                        }
+                    }
                 }
                 instance.state = InstanceState::Running;
                 if let Err(error) =
@@ -270,6 +300,42 @@ pub async fn terminate_instance(
     Ok(format!("Instance {} terminated successfully.", instance_id))
 }
 
+#[tauri::command]
+pub async fn stop_instance(
+    instance_id: String,
+    region: String,
+    provider: String,
+    app_handle: AppHandle,
+) -> Result<String> {
+    let provider_name = CloudProviderName::from_str(&provider)?;
+    let cloud_provider = create_cloud_provider(provider_name).await?;
+    commands::stop::stop_instance(&*cloud_provider, &region, &instance_id).await?;
+
+    if let Some(ledger) = LedgerStore::open(&app_handle) {
+        ledger.mark_stopped(&instance_id);
+    }
+
+    Ok(format!("Instance {} is stopping.", instance_id))
+}
+
+#[tauri::command]
+pub async fn start_instance(
+    instance_id: String,
+    region: String,
+    provider: String,
+    app_handle: AppHandle,
+) -> Result<InstanceInfo> {
+    let provider_name = CloudProviderName::from_str(&provider)?;
+    let cloud_provider = create_cloud_provider(provider_name).await?;
+    let instance = commands::start::start_instance(&*cloud_provider, &region, &instance_id).await?;
+
+    if let Some(ledger) = LedgerStore::open(&app_handle) {
+        ledger.mark_started(&instance_id);
+    }
+
+    Ok(instance)
+}
+
 #[tauri::command]
 pub async fn list_instances(
     region: Option<String>,
@@ -315,6 +381,7 @@ pub async fn list_instances(
     if let Some(ledger) = LedgerStore::open(&app_handle) {
         let running_ids: HashSet<&str> = all_instances.iter().map(|i| i.id.as_str()).collect();
         ledger.reconcile_terminated(&running_ids, &queried_provider_names);
+        ledger.reconcile_stopped(&all_instances);
 
         let in_progress_ids = app_handle
             .state::<SpawnJobRegistry>()
@@ -330,8 +397,14 @@ pub async fn list_instances(
             }
             let instance_id = instance.id.clone();
             let instance_ip = instance.public_ip_v4.clone();
+            let provider_name = instance.provider.clone();
+            let instance_region = instance.region.clone();
             probe_handles.push(tokio::spawn(async move {
-                (instance_id, connectivity::probe_status(&instance_ip).await)
+                let status_key =
+                    connectivity::load_status_key(&provider_name, &instance_region, &instance_id)
+                        .await;
+                let status = connectivity::probe_status(&instance_ip, status_key.as_deref()).await;
+                (instance_id, status)
             }));
         }
 
@@ -347,7 +420,7 @@ pub async fn list_instances(
                 instance.state = InstanceState::Installing;
             } else if let Some(probe_status) = probe_results.remove(&instance.id) {
                 match probe_status {
-                    ProbeStatus::Ready => {
+                    ProbeStatus::Ready { .. } => {
                         ledger.mark_setup_complete(&instance.id);
                         instance.state = InstanceState::Running;
                     }
@@ -355,6 +428,13 @@ pub async fn list_instances(
                         instance.state = InstanceState::Error;
                         instance.error_reason = Some(reason);
                     }
+                    // The status endpoint may be gone once setup has
+                    // completed: it can close after the client connects and
+                    // is not restarted when a stopped server is started.
+                    ProbeStatus::Installing
+                        if ledger
+                            .get_entry(&instance.id)
+                            .is_some_and(|entry| entry.setup_complete) => {}
                     ProbeStatus::Installing => {
                         instance.state = InstanceState::Installing;
                     }
@@ -366,6 +446,69 @@ pub async fn list_instances(
     Ok(all_instances)
 }
 
+#[tauri::command]
+pub async fn list_reserved_ips(app_handle: AppHandle) -> Result<Vec<ReservedIpInfo>> {
+    async fn list_provider_reserved_ips(
+        provider_name: CloudProviderName,
+    ) -> (CloudProviderName, Option<Vec<ReservedIpInfo>>) {
+        match create_cloud_provider(provider_name.clone()).await {
+            Ok(provider) => {
+                match commands::reserved_ip::list_reserved_ips(&*provider, None).await {
+                    Ok(reserved_ips) => (provider_name, Some(reserved_ips)),
+                    Err(error) => {
+                        error!("Failed to list {} reserved IPs: {}", provider_name, error);
+                        (provider_name, None)
+                    }
+                }
+            }
+            Err(error) => {
+                debug!("No credentials for {}, skipping: {}", provider_name, error);
+                (provider_name, None)
+            }
+        }
+    }
+
+    let (r_aws, r_oracle, r_gcp, r_azure) = tokio::join!(
+        list_provider_reserved_ips(CloudProviderName::Aws),
+        list_provider_reserved_ips(CloudProviderName::Oracle),
+        list_provider_reserved_ips(CloudProviderName::Gcp),
+        list_provider_reserved_ips(CloudProviderName::Azure),
+    );
+
+    let mut all_reserved_ips: Vec<ReservedIpInfo> = Vec::new();
+    let mut queried_provider_names: Vec<CloudProviderName> = Vec::new();
+    for (provider_name, result) in [r_aws, r_oracle, r_gcp, r_azure] {
+        if let Some(reserved_ips) = result {
+            queried_provider_names.push(provider_name);
+            all_reserved_ips.extend(reserved_ips);
+        }
+    }
+
+    if let Some(ledger) = LedgerStore::open(&app_handle) {
+        ledger.reconcile_reserved_ips(&all_reserved_ips, &queried_provider_names, None);
+    }
+
+    Ok(all_reserved_ips)
+}
+
+#[tauri::command]
+pub async fn release_reserved_ip(
+    address_id: String,
+    region: String,
+    provider: String,
+    app_handle: AppHandle,
+) -> Result<String> {
+    let provider_name = CloudProviderName::from_str(&provider)?;
+    let cloud_provider = create_cloud_provider(provider_name).await?;
+    commands::reserved_ip::release_reserved_ip(&*cloud_provider, &region, &address_id).await?;
+
+    if let Some(ledger) = LedgerStore::open(&app_handle) {
+        ledger.mark_reserved_ip_released(&address_id);
+    }
+
+    Ok(format!("Reserved IP {} released.", address_id))
+}
+
 #[tauri::command]
 pub async fn has_profile() -> Result<bool> {
     let store = match CredentialStore::load().await {
@@ -559,9 +702,9 @@ pub async fn connect(
     let cloud_provider = create_cloud_provider(provider_name).await?;
     let daemon_client = UnixDaemonClient;
 
-    let kill_switch_enabled = crate::settings_store::SettingsStore::open(&app_handle)
-        .map(|store| store.load_vpn_settings().session_killswitch)
-        .unwrap_or(true);
+    let vpn_settings = crate::settings_store::SettingsStore::open(&app_handle)
+        .map(|store| store.load_vpn_settings())
+        .unwrap_or_default();
 
     commands::connect::connect(
         &*cloud_provider,
@@ -570,7 +713,14 @@ pub async fn connect(
         &instance_id,
         public_ip_v4,
         public_ip_v6,
-        kill_switch_enabled,
+        ConnectOptions {
+            kill_switch_enabled: vpn_settings.session_killswitch,
+            split_tunnel: vpn_settings.split_tunnel,
+            network_namespace: None,
+            allow_lan: vpn_settings.allow_lan,
+            mtu: None,
+            probe_mtu: false,
+        },
     )
     .await?;
 
@@ -594,6 +744,10 @@ pub async fn connect(
                         connection_error: Some(
                             "VPN tunnel dropped. Kill switch is blocking all traffic.".to_string(),
                         ),
+                        reconnecting: false,
+                        backend: None,
+                        mtu: None,
+                        mtu_probe_error: None,
                     };
                 }
                 tray::update_tray(&tray_handle, &status);
@@ -636,6 +790,10 @@ pub async fn disconnect(app_handle: AppHandle) -> Result<String> {
         metrics: None,
         connected_at: None,
         connection_error: None,
+        reconnecting: false,
+        backend: None,
+        mtu: None,
+        mtu_probe_error: None,
     };
     tray::update_tray(&app_handle, &disconnected_status);
     if let Err(error) = app_handle.emit("vpn-status", &disconnected_status) {
@@ -652,6 +810,11 @@ pub async fn get_vpn_status() -> Result<VpnStatus> {
     fetch_vpn_status().await
 }
 
+#[tauri::command]
+pub async fn get_traffic_history(since: Option<u64>) -> Result<Vec<SessionHistory>> {
+    commands::history::fetch_traffic_history(&UnixDaemonClient, since).await
+}
+
 #[tauri::command]
 pub async fn subscribe_to_vpn_status(app_handle: AppHandle) -> Result<()> {
     let status = fetch_vpn_status().await?;
@@ -683,6 +846,10 @@ pub async fn subscribe_to_vpn_status(app_handle: AppHandle) -> Result<()> {
                     connection_error: Some(
                         "VPN tunnel dropped. Kill switch is blocking all traffic.".to_string(),
                     ),
+                    reconnecting: false,
+                    backend: None,
+                    mtu: None,
+                    mtu_probe_error: None,
                 };
             }
             tray::update_tray(&tray_handle, &vpn_status);
@@ -737,3 +904,14 @@ pub async fn get_ledger(app_handle: AppHandle) -> Result<Vec<Value>> {
     })?;
     Ok(ledger.all_entries())
 }
+
+#[tauri::command]
+pub async fn get_reserved_ip_ledger(app_handle: AppHandle) -> Result<Vec<Value>> {
+    let ledger = LedgerStore::open(&app_handle).ok_or_else(|| -> Error {
+        ConfigurationError::InvalidFile {
+            reason: "failed to open ledger store".to_string(),
+        }
+        .into()
+    })?;
+    Ok(ledger.all_reserved_ip_entries())
+}
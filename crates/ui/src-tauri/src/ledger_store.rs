@@ -1,6 +1,10 @@
 use std::{collections::HashSet, sync::Arc};
 
-use byocvpn_core::{cloud_provider::CloudProviderName, ledger::LedgerEntry};
+use byocvpn_core::{
+    cloud_provider::{CloudProviderName, InstanceInfo, InstanceState, ReservedIpInfo},
+    ledger::{LedgerEntry, ReservedIpLedgerEntry},
+};
+use chrono::Utc;
 use log::*;
 use serde_json::Value;
 use tauri::{AppHandle, Wry};
@@ -40,12 +44,72 @@ impl LedgerStore {
                     serde_json::Value::Null
                 }),
             );
+            self.detach_reserved_ips(instance_id);
             if let Err(error) = self.0.save() {
                 warn!("Failed to save ledger store: {}", error);
             }
         }
     }
 
+    pub fn mark_stopped(&self, instance_id: &str) {
+        let key = LedgerEntry::build_store_key(instance_id);
+        if let Some(mut entry) = self.deserialize_entry(instance_id) {
+            entry.mark_stopped();
+            self.0.set(
+                key,
+                serde_json::to_value(&entry).unwrap_or_else(|error| {
+                    warn!("Failed to serialize ledger entry: {}", error);
+                    serde_json::Value::Null
+                }),
+            );
+            if let Err(error) = self.0.save() {
+                warn!("Failed to save ledger store: {}", error);
+            }
+        }
+    }
+
+    pub fn mark_started(&self, instance_id: &str) {
+        let key = LedgerEntry::build_store_key(instance_id);
+        if let Some(mut entry) = self.deserialize_entry(instance_id) {
+            entry.mark_started();
+            self.0.set(
+                key,
+                serde_json::to_value(&entry).unwrap_or_else(|error| {
+                    warn!("Failed to serialize ledger entry: {}", error);
+                    serde_json::Value::Null
+                }),
+            );
+            if let Err(error) = self.0.save() {
+                warn!("Failed to save ledger store: {}", error);
+            }
+        }
+    }
+
+    /// Brings stop and start times up to date with what the providers report,
+    /// which covers servers stopped outside this app.
+    pub fn reconcile_stopped(&self, instances: &[InstanceInfo]) {
+        for instance in instances {
+            let key = LedgerEntry::build_store_key(&instance.id);
+            if let Some(mut entry) = self.deserialize_entry_by_key(&key) {
+                match instance.state {
+                    InstanceState::Stopped if !entry.is_stopped() => entry.mark_stopped(),
+                    InstanceState::Running if entry.is_stopped() => entry.mark_started(),
+                    _ => continue,
+                }
+                self.0.set(
+                    key,
+                    serde_json::to_value(&entry).unwrap_or_else(|error| {
+                        warn!("Failed to serialize ledger entry: {}", error);
+                        serde_json::Value::Null
+                    }),
+                );
+            }
+        }
+        if let Err(error) = self.0.save() {
+            warn!("Failed to save ledger store: {}", error);
+        }
+    }
+
     pub fn update_metrics(&self, instance_id: &str, bytes_sent: u64, bytes_received: u64) {
         let key = LedgerEntry::build_store_key(instance_id);
         if let Some(mut entry) = self.deserialize_entry(instance_id) {
@@ -89,6 +153,7 @@ impl LedgerStore {
                             serde_json::Value::Null
                         }),
                     );
+                    self.detach_reserved_ips(&entry.instance_id);
                 }
             }
         }
@@ -118,13 +183,14 @@ impl LedgerStore {
         }
     }
 
+    /// Instances neither terminated nor stopped.
     pub fn running_entries(&self) -> Vec<LedgerEntry> {
         self.0
             .keys()
             .into_iter()
             .filter(|key| key.starts_with("ledger/"))
             .filter_map(|key| self.deserialize_entry_by_key(&key))
-            .filter(|entry| entry.terminated_at.is_none())
+            .filter(|entry| entry.terminated_at.is_none() && !entry.is_stopped())
             .collect()
     }
 
@@ -137,6 +203,140 @@ impl LedgerStore {
             .collect()
     }
 
+    /// Records newly seen reserved IPs, tracks which are idle, and marks those
+    /// that vanished from a queried provider and region as released.
+    pub fn reconcile_reserved_ips(
+        &self,
+        reserved_ips: &[ReservedIpInfo],
+        queried_providers: &[CloudProviderName],
+        queried_region: Option<&str>,
+    ) {
+        for reserved_ip in reserved_ips {
+            let key = ReservedIpLedgerEntry::build_store_key(&reserved_ip.id);
+            let mut entry = self
+                .deserialize_reserved_ip_entry_by_key(&key)
+                .filter(|entry| entry.released_at.is_none())
+                .unwrap_or_else(|| ReservedIpLedgerEntry {
+                    address_id: reserved_ip.id.clone(),
+                    provider: reserved_ip.provider.clone(),
+                    region: reserved_ip.region.clone(),
+                    public_ip: reserved_ip.public_ip.clone(),
+                    reserved_at: Utc::now(),
+                    released_at: None,
+                    ip_hourly_rate: reserved_ip.ip_hourly_rate,
+                    idle_since: None,
+                    idle_seconds: 0,
+                    instance_id: None,
+                });
+            // The provider can still report the address attached while the
+            // instance it was on shuts down.
+            match reserved_ip
+                .instance_id
+                .as_deref()
+                .filter(|instance_id| !self.is_terminated(instance_id))
+            {
+                Some(instance_id) => entry.mark_attached(instance_id),
+                None => entry.mark_idle(),
+            }
+            self.0.set(
+                key,
+                serde_json::to_value(&entry).unwrap_or_else(|error| {
+                    warn!("Failed to serialize reserved IP entry: {}", error);
+                    serde_json::Value::Null
+                }),
+            );
+        }
+
+        let listed_ids: HashSet<&str> = reserved_ips
+            .iter()
+            .map(|reserved_ip| reserved_ip.id.as_str())
+            .collect();
+        let keys: Vec<String> = self
+            .0
+            .keys()
+            .into_iter()
+            .filter(|key| key.starts_with("reserved-ip/"))
+            .collect();
+        for key in keys {
+            if let Some(mut entry) = self.deserialize_reserved_ip_entry_by_key(&key) {
+                if entry.released_at.is_none()
+                    && queried_providers.contains(&entry.provider)
+                    && queried_region.is_none_or(|region| entry.region == region)
+                    && !listed_ids.contains(entry.address_id.as_str())
+                {
+                    entry.mark_released();
+                    self.0.set(
+                        key,
+                        serde_json::to_value(&entry).unwrap_or_else(|error| {
+                            warn!("Failed to serialize reserved IP entry: {}", error);
+                            serde_json::Value::Null
+                        }),
+                    );
+                }
+            }
+        }
+        if let Err(error) = self.0.save() {
+            warn!("Failed to save ledger store: {}", error);
+        }
+    }
+
+    pub fn mark_reserved_ip_released(&self, address_id: &str) {
+        let key = ReservedIpLedgerEntry::build_store_key(address_id);
+        if let Some(mut entry) = self.deserialize_reserved_ip_entry_by_key(&key) {
+            entry.mark_released();
+            self.0.set(
+                key,
+                serde_json::to_value(&entry).unwrap_or_else(|error| {
+                    warn!("Failed to serialize reserved IP entry: {}", error);
+                    serde_json::Value::Null
+                }),
+            );
+            if let Err(error) = self.0.save() {
+                warn!("Failed to save ledger store: {}", error);
+            }
+        }
+    }
+
+    pub fn all_reserved_ip_entries(&self) -> Vec<Value> {
+        self.0
+            .keys()
+            .into_iter()
+            .filter(|key| key.starts_with("reserved-ip/"))
+            .filter_map(|key| self.0.get(&key))
+            .collect()
+    }
+
+    /// Starts the idle clock on reserved IPs that were attached to a now
+    /// terminated instance. Does not save.
+    fn detach_reserved_ips(&self, instance_id: &str) {
+        let keys: Vec<String> = self
+            .0
+            .keys()
+            .into_iter()
+            .filter(|key| key.starts_with("reserved-ip/"))
+            .collect();
+        for key in keys {
+            if let Some(mut entry) = self.deserialize_reserved_ip_entry_by_key(&key) {
+                if entry.released_at.is_none() && entry.instance_id.as_deref() == Some(instance_id)
+                {
+                    entry.mark_idle();
+                    self.0.set(
+                        key,
+                        serde_json::to_value(&entry).unwrap_or_else(|error| {
+                            warn!("Failed to serialize reserved IP entry: {}", error);
+                            serde_json::Value::Null
+                        }),
+                    );
+                }
+            }
+        }
+    }
+
+    fn is_terminated(&self, instance_id: &str) -> bool {
+        self.deserialize_entry(instance_id)
+            .is_some_and(|entry| entry.terminated_at.is_some())
+    }
+
     fn deserialize_entry(&self, instance_id: &str) -> Option<LedgerEntry> {
         let key = LedgerEntry::build_store_key(instance_id);
         self.deserialize_entry_by_key(&key)
@@ -146,4 +346,9 @@ impl LedgerStore {
         let value = self.0.get(key)?;
         serde_json::from_value(value).ok()
     }
+
+    fn deserialize_reserved_ip_entry_by_key(&self, key: &str) -> Option<ReservedIpLedgerEntry> {
+        let value = self.0.get(key)?;
+        serde_json::from_value(value).ok()
+    }
 }
@@ -37,7 +37,11 @@ pub fn run() {
             commands::verify_permissions,
             commands::spawn_instance,
             commands::terminate_instance,
+            commands::stop_instance,
+            commands::start_instance,
             commands::list_instances,
+            commands::list_reserved_ips,
+            commands::release_reserved_ip,
             commands::has_profile,
             commands::provision_account,
             commands::enable_region,
@@ -45,9 +49,11 @@ pub fn run() {
             commands::connect,
             commands::disconnect,
             commands::get_vpn_status,
+            commands::get_traffic_history,
             commands::subscribe_to_vpn_status,
             commands::get_instance_pricing,
             commands::get_ledger,
+            commands::get_reserved_ip_ledger,
             commands::save_file,
             commands::list_active_spawn_jobs,
             settings_store::get_notification_settings,
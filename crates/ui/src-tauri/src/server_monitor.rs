@@ -4,7 +4,7 @@ use std::{
     time::{Duration, SystemTime},
 };
 
-use byocvpn_core::{commands, ledger::LedgerEntry};
+use byocvpn_core::{cloud_provider::ServerLifetime, commands, ledger::LedgerEntry};
 use chrono::Utc;
 use humantime::format_duration;
 use log::{debug, info, warn};
@@ -96,6 +96,26 @@ pub(crate) async fn run_auto_terminate_check(app_handle: &AppHandle) {
     .await;
 }
 
+/// The auto-terminate threshold as a max lifetime for a new server, so the
+/// server enforces it on itself even when this app is not running.
+pub(crate) fn server_lifetime_from_settings(app_handle: &AppHandle) -> ServerLifetime {
+    let max_lifetime_minutes = SettingsStore::open(app_handle)
+        .map(|store| store.load_auto_terminate_settings())
+        .filter(|settings| {
+            settings.auto_terminate_enabled && settings.auto_terminate_threshold_minutes > 0
+        })
+        .map(|settings| {
+            let minutes = settings
+                .auto_terminate_threshold_minutes
+                .max(MIN_AUTO_TERMINATE_MINUTES);
+            u32::try_from(minutes).unwrap_or(u32::MAX)
+        });
+    ServerLifetime {
+        idle_timeout_minutes: None,
+        max_lifetime_minutes,
+    }
+}
+
 fn notify_long_running(app_handle: &AppHandle, entries: &[LedgerEntry], threshold_minutes: u64) {
     let threshold = Duration::from_secs(threshold_minutes * 60);
     let now = SystemTime::now();
@@ -111,7 +131,7 @@ fn notify_long_running(app_handle: &AppHandle, entries: &[LedgerEntry], threshol
     let map = last_notified.get_or_insert_with(HashMap::new);
 
     for entry in entries {
-        let elapsed_secs = (now_utc - entry.launched_at).num_seconds().max(0) as u64;
+        let elapsed_secs = (now_utc - entry.running_since()).num_seconds().max(0) as u64;
         let elapsed = Duration::from_secs(elapsed_secs);
         if elapsed < threshold {
             continue;
@@ -199,7 +219,9 @@ async fn auto_terminate_expired(
     );
 
     for entry in entries {
-        let age = now - entry.launched_at;
+        // A resumed server's own max lifetime restarts with it, so this
+        // does too.
+        let age = now - entry.running_since();
         let is_match = connected_instance_id.as_deref() == Some(entry.instance_id.as_str());
         debug!(
             "[auto-terminate] evaluating entry: ledger_id={:?}, connected_id={:?}, match={}, age_secs={}, threshold_secs={}, expired={}",
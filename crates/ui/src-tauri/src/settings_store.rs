@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use byocvpn_core::daemon_client::SplitTunnelConfig;
 use log::warn;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Wry};
@@ -12,6 +13,8 @@ const DEFAULT_THRESHOLD_MINUTES: u64 = 60;
 const DEFAULT_NOTIFICATION_UNIT: &str = "minutes";
 const SESSION_KILLSWITCH_KEY: &str = "sessionKillswitch";
 const DEFAULT_SESSION_KILLSWITCH: bool = true;
+const SPLIT_TUNNEL_KEY: &str = "splitTunnel";
+const ALLOW_LAN_KEY: &str = "allowLan";
 const AUTO_TERMINATE_ENABLED_KEY: &str = "autoTerminateEnabled";
 const AUTO_TERMINATE_THRESHOLD_MINUTES_KEY: &str = "autoTerminateThresholdMinutes";
 const AUTO_TERMINATE_UNIT_KEY: &str = "autoTerminateUnit";
@@ -22,12 +25,18 @@ const DEFAULT_AUTO_TERMINATE_UNIT: &str = "hours";
 #[serde(rename_all = "camelCase")]
 pub struct VpnSettings {
     pub session_killswitch: bool,
+    #[serde(default)]
+    pub split_tunnel: SplitTunnelConfig,
+    #[serde(default)]
+    pub allow_lan: bool,
 }
 
 impl Default for VpnSettings {
     fn default() -> Self {
         Self {
             session_killswitch: DEFAULT_SESSION_KILLSWITCH,
+            split_tunnel: SplitTunnelConfig::default(),
+            allow_lan: false,
         }
     }
 }
@@ -170,7 +179,23 @@ impl SettingsStore {
             .and_then(|value| serde_json::from_value(value).ok())
             .unwrap_or(DEFAULT_SESSION_KILLSWITCH);
 
-        VpnSettings { session_killswitch }
+        let split_tunnel = self
+            .0
+            .get(SPLIT_TUNNEL_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+
+        let allow_lan = self
+            .0
+            .get(ALLOW_LAN_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or(false);
+
+        VpnSettings {
+            session_killswitch,
+            split_tunnel,
+            allow_lan,
+        }
     }
 
     pub fn save_vpn_settings(&self, settings: &VpnSettings) {
@@ -178,6 +203,14 @@ impl SettingsStore {
             SESSION_KILLSWITCH_KEY,
             serde_json::Value::Bool(settings.session_killswitch),
         );
+        self.0.set(
+            ALLOW_LAN_KEY,
+            serde_json::Value::Bool(settings.allow_lan),
+        );
+        match serde_json::to_value(&settings.split_tunnel) {
+            Ok(value) => self.0.set(SPLIT_TUNNEL_KEY, value),
+            Err(error) => warn!("Failed to serialize split tunnel settings: {}", error),
+        }
         if let Err(error) = self.0.save() {
             warn!("Failed to save VPN settings: {}", error);
         }
@@ -165,6 +165,10 @@ fn disconnected_vpn_status() -> VpnStatus {
         metrics: None,
         connected_at: None,
         connection_error: None,
+        reconnecting: false,
+        backend: None,
+        mtu: None,
+        mtu_probe_error: None,
     }
 }
 
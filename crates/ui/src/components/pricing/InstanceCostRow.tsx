@@ -12,6 +12,7 @@ import { formatBytes } from "../../lib/bytes";
 export function InstanceCostRow({ entry }: InstanceCostRowProps) {
   const [isExpanded, setIsExpanded] = useState(false);
   const isActive = entry.terminatedAt === null;
+  const isStopped = isActive && Boolean(entry.stoppedSince);
   const bytesSentGb = entry.bytesSent / 1024 ** 3;
 
   const hourlyComputeRate =
@@ -44,7 +45,9 @@ export function InstanceCostRow({ entry }: InstanceCostRowProps) {
           {formatDate(entry.launchedAt)}
         </td>
         <td className="py-3 px-4 text-sm">
-          {isActive ? (
+          {isStopped ? (
+            <span className="text-warning-300">Stopped</span>
+          ) : isActive ? (
             <span className="inline-flex items-center gap-1.5 text-success-400">
               <span className="w-1.5 h-1.5 rounded-full bg-success-400 animate-pulse inline-block" />
               Active
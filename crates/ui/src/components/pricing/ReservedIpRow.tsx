@@ -0,0 +1,85 @@
+import { useState } from "react";
+import toast from "react-hot-toast";
+import { ReservedIpWithCost } from "../../types/ledger";
+import { ProviderIcon } from "../providers/ProviderIcon";
+import { formatDate, formatUptime } from "../../lib/time";
+import { Button } from "../primitives/Button";
+
+interface ReservedIpRowProps {
+  entry: ReservedIpWithCost;
+  onRelease: (entry: ReservedIpWithCost) => Promise<void>;
+}
+
+export function ReservedIpRow({ entry, onRelease }: ReservedIpRowProps) {
+  const [isReleasing, setIsReleasing] = useState(false);
+  const isReleased = entry.releasedAt !== null;
+  const isIdle = !isReleased && entry.idleSince !== null;
+
+  const handleRelease = async () => {
+    setIsReleasing(true);
+    try {
+      await onRelease(entry);
+      toast.success(`Released ${entry.publicIp}`);
+    } catch (releaseError) {
+      toast.error(
+        releaseError instanceof Error
+          ? releaseError.message
+          : "Failed to release reserved IP",
+      );
+    } finally {
+      setIsReleasing(false);
+    }
+  };
+
+  return (
+    <tr className="border-b border-gray-700/50">
+      <td className="py-3 px-4 w-14">
+        <ProviderIcon provider={entry.provider} className="w-9 h-9 shrink-0" />
+      </td>
+      <td className="py-3 px-4 font-mono text-sm text-gray-300">
+        {entry.publicIp}
+      </td>
+      <td className="py-3 px-4 text-sm text-gray-300">{entry.region}</td>
+      <td className="py-3 px-4 text-sm text-gray-400">
+        {formatDate(entry.reservedAt)}
+      </td>
+      <td className="py-3 px-4 text-sm">
+        {isReleased ? (
+          <span className="text-gray-400">
+            Released {formatDate(entry.releasedAt!)}
+          </span>
+        ) : isIdle ? (
+          <span className="text-warning-300">Idle</span>
+        ) : (
+          <span className="inline-flex items-center gap-1.5 text-success-400">
+            <span className="w-1.5 h-1.5 rounded-full bg-success-400 inline-block" />
+            Attached
+          </span>
+        )}
+      </td>
+      <td className="py-3 px-4 text-sm text-gray-300">
+        {formatUptime(entry.idleHours)} × ${entry.ipHourlyRate.toFixed(5)}/hr
+      </td>
+      <td className="py-3 px-4">
+        <div className="flex items-center justify-between gap-3">
+          <span className="text-sm font-semibold text-warning-300">
+            ${entry.idleCost.toFixed(4)}
+          </span>
+          {!isReleased && (
+            <Button
+              variant="ghostDanger"
+              size="none"
+              onClick={handleRelease}
+              loading={isReleasing}
+              disabled={isReleasing || !isIdle}
+              disabledStyle="dim"
+              className="text-xs px-2.5 py-1"
+            >
+              Release
+            </Button>
+          )}
+        </div>
+      </td>
+    </tr>
+  );
+}
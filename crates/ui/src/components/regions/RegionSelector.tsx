@@ -8,6 +8,7 @@ import { useEffect, useState } from "react";
 import { Instance, CloudProviderName, Region } from "../../types";
 import { useProviderRegions } from "../../hooks/useProviderRegions";
 import { JobProgressDrawer } from "../common/JobProgressDrawer";
+import { Toggle } from "../primitives/Toggle";
 
 interface RegionSelectorProps {
   provider: CloudProviderName;
@@ -21,6 +22,7 @@ export function RegionSelector({
   onSpawned,
 }: RegionSelectorProps) {
   const [selectedRegion, setSelectedRegion] = useState<Region | null>(null);
+  const [reserveIp, setReserveIp] = useState(false);
   const {
     groupedRegions,
     enabledRegions,
@@ -48,7 +50,11 @@ export function RegionSelector({
 
   const handleDeploy = async () => {
     if (selectedRegion && enabledRegions.has(selectedRegion.name)) {
-      const placeholder = await spawnInstance(selectedRegion.name, provider);
+      const placeholder = await spawnInstance(
+        selectedRegion.name,
+        provider,
+        reserveIp,
+      );
       onSpawned?.(placeholder);
       onClose();
     }
@@ -199,6 +205,22 @@ export function RegionSelector({
 
       {selectedRegion && enabledRegions.has(selectedRegion.name) && (
         <div className="border-t border-gray-700/50 p-5 flex-shrink-0">
+          <div className="flex items-center justify-between gap-4 mb-4">
+            <div className="min-w-0">
+              <p className="text-sm font-medium text-primary">
+                Reserve static IP
+              </p>
+              <p className="text-xs text-gray-500 mt-0.5">
+                Keep the address after the server is gone and reuse it for the
+                next one in this region. It is billed while idle.
+              </p>
+            </div>
+            <Toggle
+              checked={reserveIp}
+              onChange={() => setReserveIp((previous) => !previous)}
+              ariaLabel="Toggle reserved static IP"
+            />
+          </div>
           <Button
             variant="primary"
             size="none"
@@ -88,7 +88,10 @@ export function ServerCard({
   const runningStep = spawnJob?.steps.find(
     (step) => step.status === JobStepStatus.Running,
   );
-  const stepLabel = runningStep?.label ?? (isInProgress ? "Starting…" : null);
+  const stepLabel =
+    runningStep?.detail ??
+    runningStep?.label ??
+    (isInProgress ? "Starting…" : null);
 
   return (
     <SelectableCard
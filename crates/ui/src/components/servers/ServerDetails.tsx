@@ -58,10 +58,13 @@ interface ServerDetailsProps {
   instance: Instance;
   isConnecting: boolean;
   isTerminating: boolean;
+  isChangingPower: boolean;
   vpnError: string | null;
   spawnJob?: SpawnJobState;
   onConnect: (data: Instance) => void;
   onTerminate: () => void;
+  onStop: () => void;
+  onStart: () => void;
   onDismiss: () => void;
 }
 
@@ -69,16 +72,21 @@ export function ServerDetails({
   instance,
   isConnecting,
   isTerminating,
+  isChangingPower,
   vpnError,
   spawnJob,
   onConnect,
   onTerminate,
+  onStop,
+  onStart,
   onDismiss,
 }: ServerDetailsProps) {
   const regionInfo = getRegionInfo(instance.provider, instance.region ?? "");
   const isSpawning = instance.state === InstanceState.Spawning;
   const isInProgress =
     isSpawning || instance.state === InstanceState.Installing;
+  const isStopped = instance.state === InstanceState.Stopped;
+  const isStopping = instance.state === InstanceState.Stopping;
   const isFailedSpawn =
     instance.state === InstanceState.Error && spawnJob !== undefined;
 
@@ -216,6 +224,12 @@ export function ServerDetails({
                             >
                               {step.label}
                             </p>
+                            {step.status === JobStepStatus.Running &&
+                              step.detail && (
+                                <p className="text-xs text-gray-500">
+                                  {step.detail}
+                                </p>
+                              )}
                           </div>
                         </div>
                       );
@@ -230,16 +244,46 @@ export function ServerDetails({
               </Card>
             ) : (
               <>
-                <Button
-                  variant="primary"
-                  size="lg"
-                  disabledStyle="dim"
-                  loading={isConnecting}
-                  onClick={() => onConnect(instance)}
-                  className="w-full"
-                >
-                  {isConnecting ? "Connecting…" : "Connect to VPN"}
-                </Button>
+                {isStopped ? (
+                  <Button
+                    variant="primary"
+                    size="lg"
+                    disabledStyle="dim"
+                    loading={isChangingPower}
+                    onClick={onStart}
+                    className="w-full"
+                  >
+                    {isChangingPower ? "Resuming…" : "Resume Server"}
+                  </Button>
+                ) : (
+                  <>
+                    <Button
+                      variant="primary"
+                      size="lg"
+                      disabledStyle="dim"
+                      loading={isConnecting}
+                      disabled={isStopping}
+                      onClick={() => onConnect(instance)}
+                      className="w-full"
+                    >
+                      {isConnecting ? "Connecting…" : "Connect to VPN"}
+                    </Button>
+
+                    <Button
+                      variant="secondary"
+                      size="lg"
+                      disabledStyle="dim"
+                      loading={isChangingPower || isStopping}
+                      disabled={isStopping}
+                      onClick={onStop}
+                      className="w-full"
+                    >
+                      {isChangingPower || isStopping
+                        ? "Stopping…"
+                        : "Stop Server"}
+                    </Button>
+                  </>
+                )}
 
                 <Button
                   variant="danger"
@@ -87,8 +87,9 @@ export function AutoTerminateSettingsCard() {
           </div>
 
           <p className="text-xs text-gray-500">
-            Only runs while the app is open. Servers are fully terminated, so a
-            forgotten one won't keep costing you.
+            Servers are fully terminated, so a forgotten one won't keep costing
+            you. New servers also delete themselves at this age, even if the
+            app is closed.
           </p>
         </div>
       )}
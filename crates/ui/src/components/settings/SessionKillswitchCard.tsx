@@ -2,8 +2,16 @@ import { useEffect, useState } from "react";
 import { invokeCommand } from "../../lib/invokeCommand";
 import { Toggle } from "../primitives/Toggle";
 
+interface SplitTunnelSettings {
+  includeRoutes: string[];
+  excludeRoutes: string[];
+  includeDomains: string[];
+}
+
 interface VpnSettings {
   sessionKillswitch: boolean;
+  splitTunnel?: SplitTunnelSettings;
+  allowLan?: boolean;
 }
 
 const DEFAULT_SETTINGS: VpnSettings = {
@@ -29,7 +29,16 @@ export function ConnectedView({ connectedInstance }: ConnectedViewProps) {
     return () => clearInterval(interval);
   }, [startTime]);
 
-  const connectionError = isDisconnecting ? null : vpnStatus.connectionError;
+  const peerUnreachableError =
+    metrics?.peerState === "unreachable"
+      ? `Server is not answering handshakes (${metrics.handshakeAttempts} attempts).`
+      : null;
+  const reconnectingError = vpnStatus.reconnecting
+    ? "Connection lost. Reconnecting to the server..."
+    : null;
+  const connectionError = isDisconnecting
+    ? null
+    : vpnStatus.connectionError ?? reconnectingError ?? peerUnreachableError;
 
   return (
     <div className="flex flex-col h-full text-primary overflow-hidden">
@@ -27,6 +27,9 @@ export function ServerManagementView() {
     isRefreshing,
     terminatingInstanceId,
     terminateInstance,
+    powerChangingInstanceId,
+    stopInstance,
+    startInstance,
     dismissFailedInstance,
     getSpawnJobForInstance,
   } = useInstancesContext();
@@ -82,6 +85,24 @@ export function ServerManagementView() {
     }
   };
 
+  const onStop = async () => {
+    if (!selectedInstance) return;
+    await stopInstance(
+      selectedInstance.id,
+      selectedInstance.region || "",
+      selectedInstance.provider || CloudProviderName.Aws,
+    );
+  };
+
+  const onStart = async () => {
+    if (!selectedInstance) return;
+    await startInstance(
+      selectedInstance.id,
+      selectedInstance.region || "",
+      selectedInstance.provider || CloudProviderName.Aws,
+    );
+  };
+
   const onDismiss = () => {
     if (!selectedInstance) return;
     dismissFailedInstance(selectedInstance.id);
@@ -129,10 +150,15 @@ export function ServerManagementView() {
                 instance={selectedInstance}
                 isConnecting={isConnecting}
                 isTerminating={terminatingInstanceId === selectedInstance?.id}
+                isChangingPower={
+                  powerChangingInstanceId === selectedInstance?.id
+                }
                 vpnError={vpnError}
                 spawnJob={getSpawnJobForInstance(selectedInstance.id)}
                 onConnect={onConnect}
                 onTerminate={onTerminate}
+                onStop={onStop}
+                onStart={onStart}
                 onDismiss={onDismiss}
               />
             ) : (
@@ -8,12 +8,27 @@ interface InstancesContextValue {
   isRefreshing: boolean;
   isSpawning: boolean;
   terminatingInstanceId: string | null;
-  spawnInstance: (regionName: string, provider: CloudProviderName) => Promise<Instance>;
+  powerChangingInstanceId: string | null;
+  spawnInstance: (
+    regionName: string,
+    provider: CloudProviderName,
+    reserveIp?: boolean,
+  ) => Promise<Instance>;
   terminateInstance: (
     instanceId: string,
     region: string,
     provider: CloudProviderName,
   ) => Promise<void>;
+  stopInstance: (
+    instanceId: string,
+    region: string,
+    provider: CloudProviderName,
+  ) => Promise<void>;
+  startInstance: (
+    instanceId: string,
+    region: string,
+    provider: CloudProviderName,
+  ) => Promise<void>;
   dismissFailedInstance: (instanceId: string) => void;
   refetch: () => Promise<void>;
   getSpawnJobForInstance: (instanceId: string) => SpawnJobState | undefined;
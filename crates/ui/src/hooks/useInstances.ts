@@ -26,6 +26,13 @@ interface SpawnJob {
   provider: CloudProviderName;
 }
 
+// Sub-steps arrive as "<step>.<stage>" while the server installs.
+const INSTALL_STAGE_LABELS: Record<string, string> = {
+  packages: "Installing packages",
+  sysctl: "Configuring kernel networking",
+  wg_quick: "Starting WireGuard",
+};
+
 interface SpawnProgressEvent {
   jobId: string;
   stepId: string;
@@ -67,6 +74,9 @@ export function useInstances() {
   const [terminatingInstanceId, setTerminatingInstanceId] = useState<
     string | null
   >(null);
+  const [powerChangingInstanceId, setPowerChangingInstanceId] = useState<
+    string | null
+  >(null);
   const { data: spawnJobs = {} } = useQuery<Record<string, SpawnJobState>>({
     queryKey: ["spawn-jobs"],
     queryFn: async () => ({}),
@@ -184,6 +194,7 @@ export function useInstances() {
       SpawnEvent.Progress,
       ({ payload }) => {
         const { jobId, stepId, status, error: stepError } = payload;
+        const [parentStepId, stage] = stepId.split(".");
         queryClient.setQueryData<Record<string, SpawnJobState>>(
           ["spawn-jobs"],
           (previous = {}) => {
@@ -193,11 +204,15 @@ export function useInstances() {
               ...previous,
               [jobId]: {
                 ...job,
-                steps: job.steps.map((step) =>
-                  step.id === stepId
-                    ? { ...step, status, error: stepError }
-                    : step,
-                ),
+                steps: job.steps.map((step) => {
+                  if (step.id !== parentStepId) return step;
+                  if (stage === undefined) {
+                    return { ...step, status, error: stepError };
+                  }
+                  return status === JobStepStatus.Running
+                    ? { ...step, detail: INSTALL_STAGE_LABELS[stage] ?? stage }
+                    : step;
+                }),
               },
             };
           },
@@ -299,6 +314,7 @@ export function useInstances() {
   const spawnInstance = async (
     regionName: string,
     provider: CloudProviderName,
+    reserveIp = false,
   ): Promise<Instance> => {
     const tempId = `spawning-${Date.now()}`;
 
@@ -320,6 +336,7 @@ export function useInstances() {
       const job = await invokeCommand<SpawnJob>("spawn_instance", {
         region: regionName,
         provider,
+        reserveIp,
       });
 
       queryClient.setQueryData<Record<string, SpawnJobState>>(
@@ -382,6 +399,66 @@ export function useInstances() {
     }
   };
 
+  const stopInstance = async (
+    instanceId: string,
+    region: string,
+    provider: CloudProviderName,
+  ): Promise<void> => {
+    setPowerChangingInstanceId(instanceId);
+    try {
+      await invokeCommand("stop_instance", { instanceId, region, provider });
+      setInstances((previous) =>
+        previous.map((instance) =>
+          instance.id === instanceId
+            ? { ...instance, state: InstanceState.Stopping }
+            : instance,
+        ),
+      );
+      toast.success(
+        "Server is stopping. Only its disk is billed while stopped.",
+      );
+    } catch (stopError) {
+      const message =
+        stopError instanceof Error
+          ? stopError.message
+          : "Failed to stop server";
+      toast.error(message);
+      console.error("Failed to stop instance:", stopError);
+    } finally {
+      setPowerChangingInstanceId(null);
+    }
+  };
+
+  const startInstance = async (
+    instanceId: string,
+    region: string,
+    provider: CloudProviderName,
+  ): Promise<void> => {
+    setPowerChangingInstanceId(instanceId);
+    try {
+      const started = await invokeCommand<Instance>("start_instance", {
+        instanceId,
+        region,
+        provider,
+      });
+      setInstances((previous) =>
+        previous.map((instance) =>
+          instance.id === instanceId ? started : instance,
+        ),
+      );
+      toast.success("Server resumed!");
+    } catch (startError) {
+      const message =
+        startError instanceof Error
+          ? startError.message
+          : "Failed to resume server";
+      toast.error(message);
+      console.error("Failed to start instance:", startError);
+    } finally {
+      setPowerChangingInstanceId(null);
+    }
+  };
+
   function getSpawnJobForInstance(
     instanceId: string,
   ): SpawnJobState | undefined {
@@ -414,8 +491,11 @@ export function useInstances() {
     isRefreshing: isFetching && !isLoading,
     isSpawning: Object.keys(spawnJobs).length > 0,
     terminatingInstanceId,
+    powerChangingInstanceId,
     spawnInstance,
     terminateInstance,
+    stopInstance,
+    startInstance,
     dismissFailedInstance,
     refetch,
     getSpawnJobForInstance,
@@ -1,15 +1,30 @@
 import { useState, useEffect } from "react";
 import { invokeCommand } from "../lib/invokeCommand";
 import { computeElapsedHours } from "../lib/time";
-import { LedgerEntry, LedgerEntryWithCost, PricingInfo } from "../types/ledger";
+import {
+  LedgerEntry,
+  LedgerEntryWithCost,
+  PricingInfo,
+  ReservedIpLedgerEntry,
+  ReservedIpWithCost,
+} from "../types/ledger";
 
 
 function buildPricingKey(provider: string, instanceType: string): string {
   return `${provider}::${instanceType}`;
 }
 
+function priceReservedIp(entry: ReservedIpLedgerEntry): ReservedIpWithCost {
+  const openIdleHours = entry.idleSince
+    ? computeElapsedHours(entry.idleSince, entry.releasedAt)
+    : 0;
+  const idleHours = entry.idleSeconds / 3600 + openIdleHours;
+  return { ...entry, idleHours, idleCost: idleHours * entry.ipHourlyRate };
+}
+
 export function useLedger() {
   const [entries, setEntries] = useState<LedgerEntryWithCost[]>([]);
+  const [reservedIps, setReservedIps] = useState<ReservedIpWithCost[]>([]);
   const [isLoading, setIsLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
 
@@ -45,15 +60,23 @@ export function useLedger() {
       const pricedEntries: LedgerEntryWithCost[] = rawEntries.map((entry) => {
         const key = buildPricingKey(entry.provider, entry.instanceType);
         const pricing = instancePricingCache.get(key)!;
-        const uptimeHours = computeElapsedHours(
+        const elapsedHours = computeElapsedHours(
           entry.launchedAt,
           entry.terminatedAt,
         );
+        const openStoppedHours = entry.stoppedSince
+          ? computeElapsedHours(entry.stoppedSince, entry.terminatedAt)
+          : 0;
+        // A stopped instance bills no compute, but its disk is still there.
+        const uptimeHours = Math.max(
+          0,
+          elapsedHours - (entry.stoppedSeconds ?? 0) / 3600 - openStoppedHours,
+        );
         const bytesSentGb = entry.bytesSent / 1024 ** 3;
         const computeCost = uptimeHours * pricing.hourlyRate;
         const ipCost = uptimeHours * pricing.ipHourlyRate;
         const egressCost = bytesSentGb * pricing.egressRatePerGb;
-        const storageCost = pricing.storageGb * pricing.storageRatePerGbMonth / 730 * uptimeHours;
+        const storageCost = pricing.storageGb * pricing.storageRatePerGbMonth / 730 * elapsedHours;
         const estimatedCost = computeCost + ipCost + egressCost + storageCost;
         return {
           ...entry,
@@ -69,6 +92,16 @@ export function useLedger() {
       });
 
       setEntries(pricedEntries);
+
+      // Listing the providers' addresses brings the ledger up to date with
+      // attachments and releases made since the last visit.
+      await invokeCommand("list_reserved_ips").catch((listError) =>
+        console.error("Failed to refresh reserved IPs:", listError),
+      );
+      const rawReservedIps = await invokeCommand<ReservedIpLedgerEntry[]>(
+        "get_reserved_ip_ledger",
+      );
+      setReservedIps(rawReservedIps.map(priceReservedIp));
     } catch (err) {
       setError(
         err instanceof Error ? err.message : "Failed to load cost ledger",
@@ -82,5 +115,21 @@ export function useLedger() {
     fetchLedger();
   }, []);
 
-  return { entries, isLoading, error, refetch: fetchLedger };
+  const releaseReservedIp = async (entry: ReservedIpLedgerEntry) => {
+    await invokeCommand("release_reserved_ip", {
+      addressId: entry.addressId,
+      region: entry.region,
+      provider: entry.provider,
+    });
+    await fetchLedger();
+  };
+
+  return {
+    entries,
+    reservedIps,
+    isLoading,
+    error,
+    refetch: fetchLedger,
+    releaseReservedIp,
+  };
 }
@@ -11,6 +11,8 @@ export enum ServerStatus {
   CONNECTED = "connected",
 }
 
+export type PeerState = "handshaking" | "established" | "unreachable" | "reconnecting";
+
 export interface VpnMetrics {
   bytesSent: number;
   bytesReceived: number;
@@ -18,17 +20,22 @@ export interface VpnMetrics {
   packetsReceived: number;
   uploadRate: number;
   downloadRate: number;
+  lastHandshakeAt: number | null;
+  handshakeAttempts: number;
+  peerState: PeerState;
+  reconnectCount: number;
 }
 
 export type VpnStatus =
-  | { connected: true; instance: Instance; metrics: VpnMetrics | null; connectionError: string | null }
-  | { connected: false; instance: null; metrics: null; connectionError: string | null };
+  | { connected: true; instance: Instance; metrics: VpnMetrics | null; connectionError: string | null; reconnecting: boolean }
+  | { connected: false; instance: null; metrics: null; connectionError: string | null; reconnecting: boolean };
 
 const initialVpnStatus: VpnStatus = {
   connected: false,
   instance: null,
   metrics: null,
   connectionError: null,
+  reconnecting: false,
 };
 
 export function useVpnConnection() {
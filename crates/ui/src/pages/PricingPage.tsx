@@ -3,10 +3,11 @@ import { useLedger } from "../hooks/useLedger";
 import { CalendarMonth, CloudProviderName } from "../types";
 import { ProviderFilter } from "../components/pricing/ProviderFilter";
 import { InstanceCostRow } from "../components/pricing/InstanceCostRow";
+import { ReservedIpRow } from "../components/pricing/ReservedIpRow";
 import { LoadingScreen } from "../components/common/LoadingScreen";
 import { EmptyState } from "../components/primitives/EmptyState";
 import { Alert } from "../components/primitives/Alert";
-import { LedgerEntryWithCost } from "../types/ledger";
+import { LedgerEntryWithCost, ReservedIpWithCost } from "../types/ledger";
 import { Button } from "../components/primitives/Button";
 import { IconButton } from "../components/primitives/IconButton";
 
@@ -44,8 +45,21 @@ function sortEntries(entries: LedgerEntryWithCost[]): LedgerEntryWithCost[] {
   });
 }
 
+function isReservedIpInMonth(
+  entry: ReservedIpWithCost,
+  month: CalendarMonth,
+): boolean {
+  if (entry.releasedAt === null) return true;
+  const releasedAt = new Date(entry.releasedAt);
+  return (
+    releasedAt.getFullYear() === month.year &&
+    releasedAt.getMonth() + 1 === month.month
+  );
+}
+
 export function PricingPage() {
-  const { entries, isLoading, error, refetch } = useLedger();
+  const { entries, reservedIps, isLoading, error, refetch, releaseReservedIp } =
+    useLedger();
 
   const availableMonths = useMemo<CalendarMonth[]>(() => {
     const monthSet = new Set<string>();
@@ -84,6 +98,17 @@ export function PricingPage() {
     return sortEntries(providerFiltered);
   }, [filteredByMonth, selectedProvider]);
 
+  // Addresses still held are shown in every month, since they keep billing.
+  const visibleReservedIps = useMemo(
+    () =>
+      reservedIps.filter(
+        (entry) =>
+          isReservedIpInMonth(entry, calendarMonth) &&
+          (selectedProvider === null || entry.provider === selectedProvider),
+      ),
+    [reservedIps, calendarMonth, selectedProvider],
+  );
+
   const totalCost = visibleEntries.reduce(
     (sum, entry) => sum + entry.estimatedCost,
     0,
@@ -192,6 +217,35 @@ export function PricingPage() {
             </table>
           </div>
         )}
+        {!isLoading && visibleReservedIps.length > 0 && (
+          <div className="overflow-x-auto pt-6">
+            <h3 className="px-6 pb-2 text-xs font-semibold uppercase tracking-wider text-gray-500">
+              Reserved IPs
+            </h3>
+            <table className="w-full min-w-[780px]">
+              <thead className="bg-gray-800/60">
+                <tr className="text-xs font-semibold uppercase tracking-wider text-gray-300 border-b border-gray-600/60">
+                  <th className="py-3 px-4 text-left w-14"></th>
+                  <th className="py-3 px-4 text-left">Address</th>
+                  <th className="py-3 px-4 text-left">Region</th>
+                  <th className="py-3 px-4 text-left">Reserved At</th>
+                  <th className="py-3 px-4 text-left">Status</th>
+                  <th className="py-3 px-4 text-left">Idle Time</th>
+                  <th className="py-3 px-4 text-left">Idle Cost</th>
+                </tr>
+              </thead>
+              <tbody>
+                {visibleReservedIps.map((entry) => (
+                  <ReservedIpRow
+                    key={entry.addressId}
+                    entry={entry}
+                    onRelease={releaseReservedIp}
+                  />
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
       </div>
     </div>
   );
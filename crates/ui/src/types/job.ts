@@ -15,6 +15,8 @@ export interface JobStep {
 export interface JobStepState extends JobStep {
   status: JobStepStatus;
   error?: string;
+  /** What the step is doing right now, from its latest running sub-step. */
+  detail?: string;
 }
 
 export interface SpawnJobState {
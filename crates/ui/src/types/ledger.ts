@@ -9,6 +9,10 @@ export interface LedgerEntry {
   terminatedAt: string | null;
   bytesSent: number;
   bytesReceived: number;
+  // Missing on entries recorded before stops were tracked.
+  stoppedSince?: string | null;
+  stoppedSeconds?: number;
+  restartedAt?: string | null;
 }
 
 export interface PricingInfo {
@@ -29,3 +33,21 @@ export interface LedgerEntryWithCost extends LedgerEntry {
   storageGb: number;
   storageRatePerGbMonth: number;
 }
+
+export interface ReservedIpLedgerEntry {
+  addressId: string;
+  provider: CloudProviderName;
+  region: string;
+  publicIp: string;
+  reservedAt: string;
+  releasedAt: string | null;
+  ipHourlyRate: number;
+  idleSince: string | null;
+  idleSeconds: number;
+  instanceId?: string | null;
+}
+
+export interface ReservedIpWithCost extends ReservedIpLedgerEntry {
+  idleHours: number;
+  idleCost: number;
+}
@@ -0,0 +1,72 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# Measures tunnel throughput between two network namespaces joined by a veth
+# pair. Each namespace runs one end of the tunnel (crates/core/examples/
+# tunnel_bench.rs) and iperf3 is driven across it. Run on two revisions to
+# compare them. Needs root and iperf3.
+#
+#   sudo scripts/bench-tunnel.sh [seconds]
+
+DURATION="${1:-10}"
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+REPO_DIR="$(dirname "$SCRIPT_DIR")"
+BENCH_BINARY="$REPO_DIR/target/release/examples/tunnel_bench"
+
+NAMESPACE_A="byocvpn-bench-a"
+NAMESPACE_B="byocvpn-bench-b"
+
+# ---------------------------------------------------------------------------
+# Helpers
+# ---------------------------------------------------------------------------
+
+cleanup() {
+    jobs -p | xargs -r kill 2>/dev/null || true
+    ip netns delete "$NAMESPACE_A" 2>/dev/null || true
+    ip netns delete "$NAMESPACE_B" 2>/dev/null || true
+}
+
+setup_namespace() {
+    local namespace="$1" veth="$2" address="$3"
+    ip netns add "$namespace"
+    ip link set "$veth" netns "$namespace"
+    ip -n "$namespace" addr add "$address/24" dev "$veth"
+    ip -n "$namespace" link set "$veth" up
+    ip -n "$namespace" link set lo up
+}
+
+# ---------------------------------------------------------------------------
+# Main
+# ---------------------------------------------------------------------------
+
+if [[ $EUID -ne 0 ]]; then
+    echo "Run as root: network namespaces and TUN devices need it."
+    exit 1
+fi
+command -v iperf3 >/dev/null || { echo "iperf3 is required."; exit 1; }
+
+(cd "$REPO_DIR" && cargo build --release -p byocvpn_core --example tunnel_bench)
+
+trap cleanup EXIT
+cleanup
+
+ip link add veth-bench-a type veth peer name veth-bench-b
+setup_namespace "$NAMESPACE_A" veth-bench-a 10.200.0.1
+setup_namespace "$NAMESPACE_B" veth-bench-b 10.200.0.2
+
+ip netns exec "$NAMESPACE_A" "$BENCH_BINARY" a &
+ip netns exec "$NAMESPACE_B" "$BENCH_BINARY" b &
+sleep 2
+
+ip netns exec "$NAMESPACE_B" iperf3 --server --one-off --daemon
+sleep 1
+
+echo "Plain veth:"
+ip netns exec "$NAMESPACE_A" iperf3 --client 10.200.0.2 --time "$DURATION" --format m \
+    | tail -n 4
+ip netns exec "$NAMESPACE_B" iperf3 --server --one-off --daemon
+sleep 1
+
+echo "Through the tunnel:"
+ip netns exec "$NAMESPACE_A" iperf3 --client 10.201.0.2 --time "$DURATION" --format m \
+    | tail -n 4